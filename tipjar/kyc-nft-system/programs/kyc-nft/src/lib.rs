@@ -3,11 +3,9 @@ use anchor_spl::{
     associated_token::AssociatedToken,
     metadata::{
         create_master_edition_v3, create_metadata_accounts_v3,
-        sign_metadata, set_and_verify_sized_collection_item,
         CreateMasterEditionV3, CreateMetadataAccountsV3,
-        Metadata as MetadataProgram, SignMetadata,
-        SetAndVerifySizedCollectionItem,
-        mpl_token_metadata::types::{CollectionDetails, Creator, DataV2},
+        Metadata as MetadataProgram,
+        mpl_token_metadata::types::{Creator, DataV2},
     },
     token::{mint_to, Mint, MintTo, Token, TokenAccount},
 };
@@ -45,23 +43,28 @@ pub mod kyc_nft {
     ) -> Result<()> {
         require!(ctx.accounts.kyc_authority.is_active, ErrorCode::SystemNotActive);
 
-        let kyc_record = &mut ctx.accounts.kyc_record;
-        let kyc_authority = &mut ctx.accounts.kyc_authority;
+        // La PDA es mint authority y update authority de cada NFT
+        let authority_seeds: &[&[u8]] = &[
+            b"authority",
+            &[ctx.bumps.kyc_authority],
+        ];
+        let signer_seeds = &[authority_seeds];
 
         // Mintear 1 NFT
-        let cpi_context = CpiContext::new(
+        let cpi_context = CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
             MintTo {
                 mint: ctx.accounts.mint.to_account_info(),
                 to: ctx.accounts.token_account.to_account_info(),
-                authority: ctx.accounts.authority.to_account_info(),
+                authority: ctx.accounts.kyc_authority.to_account_info(),
             },
+            signer_seeds,
         );
         mint_to(cpi_context, 1)?;
 
-        // Crear metadata del NFT
+        // Crear metadata del NFT (Metaplex solo verifica al creador que firma como update authority)
         let creator = vec![Creator {
-            address: ctx.accounts.authority.key(),
+            address: ctx.accounts.kyc_authority.key(),
             verified: true,
             share: 100,
         }];
@@ -79,12 +82,6 @@ pub mod kyc_nft {
             uses: None,
         };
 
-        let authority_seeds = &[
-            b"authority",
-            &[ctx.bumps.kyc_authority],
-        ];
-        let signer_seeds = &[&authority_seeds[..]];
-
         let cpi_context = CpiContext::new_with_signer(
             ctx.accounts.metadata_program.to_account_info(),
             CreateMetadataAccountsV3 {
@@ -121,6 +118,7 @@ pub mod kyc_nft {
         create_master_edition_v3(cpi_context, Some(0))?;
 
         // Guardar registro KYC
+        let kyc_record = &mut ctx.accounts.kyc_record;
         kyc_record.user = ctx.accounts.user.key();
         kyc_record.mint = ctx.accounts.mint.key();
         kyc_record.user_name = user_name;
@@ -131,11 +129,16 @@ pub mod kyc_nft {
         kyc_record.is_valid = true;
         kyc_record.metadata_uri = metadata_uri;
 
-        kyc_authority.total_issued += 1;
+        ctx.accounts.kyc_authority.total_issued += 1;
 
-        msg!("KYC NFT emitido para usuario: {}", ctx.accounts.user.key());
-        msg!("Mint: {}", ctx.accounts.mint.key());
-        msg!("Nivel: {:?}", verification_level);
+        emit!(KycIssued {
+            user: kyc_record.user,
+            mint: kyc_record.mint,
+            verification_level,
+            issuer: ctx.accounts.authority.key(),
+            issued_at: kyc_record.issued_at,
+            expires_at: kyc_record.expires_at,
+        });
 
         Ok(())
     }
@@ -147,14 +150,23 @@ pub mod kyc_nft {
 
         require!(kyc_record.is_valid, ErrorCode::AlreadyRevoked);
 
+        // Solo el hash de la razón va al evento; el texto queda en el registro
+        let reason_hash = solana_sha256_hasher::hash(reason.as_bytes()).to_bytes();
+        let revoked_at = Clock::get()?.unix_timestamp;
+
         kyc_record.is_valid = false;
-        kyc_record.revoked_at = Some(Clock::get()?.unix_timestamp);
+        kyc_record.revoked_at = Some(revoked_at);
         kyc_record.revocation_reason = Some(reason);
 
         kyc_authority.total_revoked += 1;
 
-        msg!("KYC NFT revocado para usuario: {}", kyc_record.user);
-        msg!("Mint: {}", kyc_record.mint);
+        emit!(KycRevoked {
+            user: kyc_record.user,
+            mint: kyc_record.mint,
+            reason_hash,
+            revoker: ctx.accounts.authority.key(),
+            revoked_at,
+        });
 
         Ok(())
     }
@@ -168,10 +180,20 @@ pub mod kyc_nft {
 
         require!(kyc_record.is_valid, ErrorCode::KycRevoked);
 
+        let old_expiration = kyc_record.expires_at;
+        let renewed_at = Clock::get()?.unix_timestamp;
+
         kyc_record.expires_at = new_expiration;
-        kyc_record.last_updated = Some(Clock::get()?.unix_timestamp);
+        kyc_record.last_updated = Some(renewed_at);
 
-        msg!("KYC NFT renovado para usuario: {}", kyc_record.user);
+        emit!(KycRenewed {
+            user: kyc_record.user,
+            mint: kyc_record.mint,
+            old_expires_at: old_expiration,
+            new_expires_at: new_expiration,
+            renewed_by: ctx.accounts.authority.key(),
+            renewed_at,
+        });
 
         Ok(())
     }
@@ -193,6 +215,23 @@ pub mod kyc_nft {
 
         Ok(())
     }
+
+    /// Consultar el estado KYC de un usuario sin fallar (vista para CPI)
+    /// Devuelve KycStatus serializado vía set_return_data
+    pub fn get_kyc_status(ctx: Context<GetKycStatus>) -> Result<KycStatus> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let user = ctx.accounts.user.key();
+
+        // Sin registro emitido no hay nada que deserializar
+        if ctx.accounts.kyc_record.data_is_empty() {
+            return Ok(KycStatus::not_issued(user));
+        }
+
+        // La PDA solo puede existir con datos si este programa la inicializó
+        let data = ctx.accounts.kyc_record.try_borrow_data()?;
+        let kyc_record = KycRecord::try_deserialize(&mut &data[..])?;
+        Ok(kyc_record.status(current_time))
+    }
 }
 
 // ============================================================================
@@ -267,9 +306,10 @@ pub struct IssueKycNft<'info> {
     pub master_edition: UncheckedAccount<'info>,
 
     /// Collection mint
+    #[account(address = kyc_authority.collection_mint)]
     pub collection_mint: Account<'info, Mint>,
 
-    #[account(mut)]
+    #[account(mut, constraint = authority.key() == kyc_authority.authority)]
     pub authority: Signer<'info>,
 
     #[account(mut)]
@@ -332,6 +372,19 @@ pub struct VerifyKycStatus<'info> {
     pub user: SystemAccount<'info>,
 }
 
+#[derive(Accounts)]
+pub struct GetKycStatus<'info> {
+    /// CHECK: Puede no existir si el usuario no tiene KYC; se deserializa en la instrucción
+    #[account(
+        seeds = [b"kyc_record", user.key().as_ref()],
+        bump
+    )]
+    pub kyc_record: UncheckedAccount<'info>,
+
+    /// CHECK: Solo se usa para derivar el registro KYC
+    pub user: UncheckedAccount<'info>,
+}
+
 // ============================================================================
 // ACCOUNTS
 // ============================================================================
@@ -367,11 +420,70 @@ pub struct KycRecord {
     pub metadata_uri: String,
 }
 
+impl KycRecord {
+    /// Estado del registro al momento indicado
+    pub fn status(&self, current_time: i64) -> KycStatus {
+        let reason = if !self.is_valid {
+            KycStatusReason::Revoked
+        } else if self.expires_at.is_some_and(|expires_at| current_time >= expires_at) {
+            KycStatusReason::Expired
+        } else {
+            KycStatusReason::Valid
+        };
+
+        KycStatus {
+            user: self.user,
+            valid: reason == KycStatusReason::Valid,
+            reason,
+            verification_level: Some(self.verification_level),
+            mint: Some(self.mint),
+            issued_at: self.issued_at,
+            expires_at: self.expires_at,
+            revoked: !self.is_valid,
+            revoked_at: self.revoked_at,
+        }
+    }
+}
+
+// ============================================================================
+// RETURN DATA
+// ============================================================================
+
+/// Resultado de get_kyc_status, consumible por CPI
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct KycStatus {
+    pub user: Pubkey,
+    pub valid: bool,                                   // Vigente y no revocado
+    pub reason: KycStatusReason,                       // Motivo cuando no es válido
+    pub verification_level: Option<VerificationLevel>, // None si nunca se emitió
+    pub mint: Option<Pubkey>,
+    pub issued_at: i64,
+    pub expires_at: Option<i64>,
+    pub revoked: bool,
+    pub revoked_at: Option<i64>,
+}
+
+impl KycStatus {
+    pub fn not_issued(user: Pubkey) -> Self {
+        Self {
+            user,
+            valid: false,
+            reason: KycStatusReason::NotIssued,
+            verification_level: None,
+            mint: None,
+            issued_at: 0,
+            expires_at: None,
+            revoked: false,
+            revoked_at: None,
+        }
+    }
+}
+
 // ============================================================================
 // ENUMS
 // ============================================================================
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
 pub enum VerificationLevel {
     Basic,      // Verificación básica (email, teléfono)
     Standard,   // Verificación estándar (+ ID)
@@ -379,6 +491,14 @@ pub enum VerificationLevel {
     Premium,    // Verificación premium (+ biometría)
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KycStatusReason {
+    Valid,      // KYC vigente
+    NotIssued,  // El usuario nunca recibió NFT de KYC
+    Revoked,    // Revocado por la autoridad
+    Expired,    // Expiró y no fue renovado
+}
+
 impl VerificationLevel {
     pub fn to_string(&self) -> &str {
         match self {
@@ -390,6 +510,39 @@ impl VerificationLevel {
     }
 }
 
+// ============================================================================
+// EVENTS
+// ============================================================================
+
+#[event]
+pub struct KycIssued {
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub verification_level: VerificationLevel,
+    pub issuer: Pubkey,            // Autoridad que aprobó la verificación
+    pub issued_at: i64,
+    pub expires_at: Option<i64>,
+}
+
+#[event]
+pub struct KycRenewed {
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub old_expires_at: Option<i64>,
+    pub new_expires_at: Option<i64>,
+    pub renewed_by: Pubkey,
+    pub renewed_at: i64,
+}
+
+#[event]
+pub struct KycRevoked {
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub reason_hash: [u8; 32],     // sha256 de la razón guardada en el registro
+    pub revoker: Pubkey,
+    pub revoked_at: i64,
+}
+
 // ============================================================================
 // ERRORS
 // ============================================================================
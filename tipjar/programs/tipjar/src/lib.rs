@@ -1,12 +1,57 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use anchor_spl::token::spl_token::native_mint;
+use anchor_spl::token_interface::{
+    self, CloseAccount, Mint, SyncNative, TokenAccount, TokenInterface, TransferChecked,
+};
 
 declare_id!("4E55dXLQkqXn7zi75aeBh4LJMXeAZw4KPYwgijuSCjun");
 
 // Constantes del sistema
-const MAX_TRANSACTION_AMOUNT_MXN: u64 = 9_000_000_000; // 9,000 MXN en formato decimal (con 6 decimales)
 const PLATFORM_FEE_BPS: u64 = 50; // 0.5% de comisión (50 basis points)
 const DISPUTE_DEPOSIT_LAMPORTS: u64 = 10_000_000; // 0.01 SOL para abrir disputa
+const MAX_AD_PAYMENT_METHODS: usize = 3; // Métodos de pago por anuncio
+const TRADE_PAYMENT_WINDOW_SECONDS: i64 = 1800; // 30 minutos para pagar un trade
+const PRICE_FEED_MAX_AGE_SECONDS: i64 = 300; // 5 minutos de validez por precio
+const MAX_PREMIUM_BPS: u32 = 5_000; // Prima/descuento máximo de 50% sobre el feed
+const DEFAULT_MAX_PRICE_DEVIATION_BPS: u64 = 1_000; // 10% de banda contra la tasa de referencia
+const DAILY_VOLUME_WINDOW_SECONDS: i64 = 86_400; // Ventana de 24 horas
+const MONTHLY_VOLUME_WINDOW_SECONDS: i64 = 30 * 86_400; // Ventana de 30 días
+const SECONDS_PER_DAY: i64 = 86_400; // Buckets UTC de DailyStats
+const MAX_FEE_TIERS: usize = 8; // Niveles en el FeeSchedule
+const MAX_TIER_FEE_BPS: u64 = 1_000; // Tarifa máxima de maker o taker (10%)
+const DEFAULT_REFERRAL_SHARE_BPS: u64 = 2_000; // 20% de la comisión para el referidor
+const MAX_REFERRAL_SHARE_BPS: u64 = 5_000; // Máximo 50% de la comisión
+const MAX_TREASURY_ADMINS: usize = 10; // Firmantes del treasury (M-de-N)
+const MAX_COUNCIL_MEMBERS: usize = 10; // Miembros del consejo de administración (M-de-N)
+const DEFAULT_PROPOSAL_LIFETIME_SECONDS: i64 = 3 * 86_400; // Vigencia por defecto de una propuesta
+const MAX_PROPOSAL_LIFETIME_SECONDS: i64 = 30 * 86_400; // Vigencia máxima de una propuesta
+const DEFAULT_TIMELOCK_DELAY_SECONDS: i64 = 2 * 86_400; // Espera por defecto de un cambio sensible
+const MIN_TIMELOCK_DELAY_SECONDS: i64 = 86_400; // Espera mínima de un cambio sensible
+const MAX_TIMELOCK_DELAY_SECONDS: i64 = 30 * 86_400; // Espera máxima de un cambio sensible
+const MAX_SUSPENSION_SECONDS: i64 = 365 * 86_400; // Suspensión temporal máxima
+const MAX_CASE_REFERENCE_LEN: usize = 64; // Referencia del caso de una retención legal
+const MAX_BLOCKLIST_BATCH: usize = 10; // Direcciones por lote de alta o baja en el blocklist
+// Circuit breakers: bits de Platform.paused_operations
+const PAUSE_ORDER_CREATION: u16 = 1 << 0; // create_order, create_ad
+const PAUSE_ORDER_ACCEPTANCE: u16 = 1 << 1; // accept_order, fill_order, open_trade
+const PAUSE_DEPOSITS: u16 = 1 << 2; // deposit_to_escrow, fill_order, deposit_to_ad, create_order pre-fondeada, open_trade
+const PAUSE_RELEASES: u16 = 1 << 3; // release_funds, release_trade
+const PAUSE_DISPUTES: u16 = 1 << 4; // open_dispute, resolve_dispute, resolve_dispute_split
+const PAUSE_ALL: u16 =
+    PAUSE_ORDER_CREATION | PAUSE_ORDER_ACCEPTANCE | PAUSE_DEPOSITS | PAUSE_RELEASES | PAUSE_DISPUTES;
+
+// Límites por nivel KYC (MXN con 6 decimales): por trade, diario y mensual
+const DEFAULT_KYC_LIMITS: [TradingLimits; 4] = [
+    // Basic: 1,000 MXN por trade
+    TradingLimits { max_trade_mxn: 1_000_000_000, daily_mxn: 3_000_000_000, monthly_mxn: 20_000_000_000 },
+    // Standard: 5,000 MXN por trade
+    TradingLimits { max_trade_mxn: 5_000_000_000, daily_mxn: 15_000_000_000, monthly_mxn: 100_000_000_000 },
+    // Enhanced: 9,000 MXN por trade
+    TradingLimits { max_trade_mxn: 9_000_000_000, daily_mxn: 45_000_000_000, monthly_mxn: 270_000_000_000 },
+    // Premium: tickets de merchants profesionales
+    TradingLimits { max_trade_mxn: 500_000_000_000, daily_mxn: 2_000_000_000_000, monthly_mxn: 20_000_000_000_000 },
+];
 
 #[program]
 pub mod tipjar {
@@ -19,141 +64,478 @@ pub mod tipjar {
         platform.fee_bps = PLATFORM_FEE_BPS;
         platform.total_volume = 0;
         platform.total_transactions = 0;
-        platform.treasury = ctx.accounts.treasury.key();
+        // El treasury es un PDA del programa, separado de los escrows de usuarios
+        platform.treasury = Pubkey::find_program_address(&[b"treasury"], ctx.program_id).0;
         platform.is_active = true;
-
-        msg!("Platform initialized with authority: {}", platform.authority);
+        platform.max_price_deviation_bps = DEFAULT_MAX_PRICE_DEVIATION_BPS;
+        platform.kyc_limits = DEFAULT_KYC_LIMITS;
+        platform.default_fee_bearer = FeeBearer::Buyer;
+        platform.referral_share_bps = DEFAULT_REFERRAL_SHARE_BPS;
+        platform.admin_council = None;
+        platform.timelock_delay_seconds = DEFAULT_TIMELOCK_DELAY_SECONDS;
+        platform.guardian = platform.authority;
+        platform.compliance = platform.authority;
+        platform.paused_operations = 0;
+
+        emit!(PlatformInitialized {
+            platform: platform.key(),
+            authority: platform.authority,
+            treasury: platform.treasury,
+            fee_bps: platform.fee_bps,
+            max_price_deviation_bps: platform.max_price_deviation_bps,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
         Ok(())
     }
 
     /// Crear perfil de usuario con verificación KYC
-    pub fn create_user_profile(
-        ctx: Context<CreateUserProfile>,
-        kyc_verified: bool,
-        kyc_nft_mint: Option<Pubkey>, // Mint del NFT de verificación KYC
-    ) -> Result<()> {
+    /// El nivel y el NFT se leen del KycRecord emitido por el programa kyc_nft
+    /// El referidor (opcional) se fija aquí y no puede cambiarse después
+    pub fn create_user_profile(ctx: Context<CreateUserProfile>) -> Result<()> {
+        check_not_blocked(&ctx.accounts.user_blocked)?;
+
+        let now = Clock::get()?.unix_timestamp;
         let profile = &mut ctx.accounts.user_profile;
         profile.owner = ctx.accounts.user.key();
-        profile.kyc_verified = kyc_verified;
-        profile.kyc_nft_mint = kyc_nft_mint;
+        profile.refresh_kyc(&ctx.accounts.kyc_record, now)?;
+        profile.referrer = ctx.accounts.referrer_profile.as_ref().map(|referrer| referrer.owner);
         profile.total_trades = 0;
         profile.successful_trades = 0;
         profile.disputed_trades = 0;
         profile.is_active = true;
-        profile.created_at = Clock::get()?.unix_timestamp;
-
-        msg!("User profile created for: {}", profile.owner);
+        profile.status = UserStatus::Active;
+        profile.suspended_until = None;
+        profile.status_reason = 0;
+        profile.status_actor = None;
+        profile.status_updated_at = now;
+        profile.created_at = now;
+        profile.daily_volume_mxn = 0;
+        profile.daily_window_start = profile.created_at;
+        profile.monthly_volume_mxn = 0;
+        profile.monthly_window_start = profile.created_at;
+
+        emit!(UserProfileCreated {
+            profile: profile.key(),
+            owner: profile.owner,
+            kyc_level: profile.kyc_level,
+            kyc_nft_mint: profile.kyc_nft_mint,
+            referrer: profile.referrer,
+            timestamp: now,
+        });
         Ok(())
     }
 
-    /// Crear una orden P2P (oferta de venta de crypto por MXN)
+    /// Crear una orden P2P (venta o compra de crypto por MXN)
+    #[allow(clippy::too_many_arguments)]
     pub fn create_order(
         ctx: Context<CreateOrder>,
         order_id: String,
         amount: u64, // Cantidad en tokens/SOL (con decimales)
         amount_mxn: u64, // Cantidad equivalente en MXN (con 6 decimales)
-        token_type: TokenType,
         payment_method: PaymentMethod,
         stp_reference: String, // Referencia para integración con STP
+        prefund: bool, // Si el seller deposita en escrow al crear la orden
+        side: OrderSide, // Venta (maker = seller) o compra (maker = buyer)
+        premium_bps: Option<i32>, // Prima/descuento sobre el feed de precio (órdenes flotantes)
+        fee_bearer: Option<FeeBearer>, // Quién paga la comisión (default de la plataforma si es None)
     ) -> Result<()> {
+        ctx.accounts.platform.check_operation(PAUSE_ORDER_CREATION)?;
         require!(amount > 0, ErrorCode::InvalidAmount);
-        require!(amount_mxn > 0 && amount_mxn <= MAX_TRANSACTION_AMOUNT_MXN, ErrorCode::ExceedsMaxLimit);
-        require!(ctx.accounts.seller_profile.kyc_verified, ErrorCode::KYCRequired);
-        require!(ctx.accounts.seller_profile.is_active, ErrorCode::UserNotActive);
+
+        let supported_mint = &ctx.accounts.supported_mint;
+        supported_mint.check_trade_amount(amount)?;
+
+        // Órdenes flotantes: amount_mxn se cotiza contra el feed y se fija al aceptar
+        let amount_mxn = match premium_bps {
+            Some(premium_bps) => {
+                require!(amount_mxn == 0, ErrorCode::InvalidAmount);
+                require!(side == OrderSide::Sell, ErrorCode::InvalidOrderSide);
+                require!(
+                    premium_bps.unsigned_abs() <= MAX_PREMIUM_BPS,
+                    ErrorCode::InvalidPremium
+                );
+                let Some(price_feed) = ctx.accounts.price_feed.as_ref() else {
+                    return err!(ErrorCode::MissingPriceFeed);
+                };
+                require!(price_feed.mint == supported_mint.mint, ErrorCode::InvalidMint);
+                let price_mxn = price_feed.current_price(premium_bps, Clock::get()?.unix_timestamp)?;
+                quote_amount_mxn(amount, price_mxn, supported_mint.decimals)?
+            },
+            None => amount_mxn,
+        };
+
+        require!(amount_mxn > 0, ErrorCode::InvalidAmount);
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.maker_profile.refresh_kyc(&ctx.accounts.kyc_record, now)?;
+        ctx.accounts.maker_profile.check_can_trade(now)?;
+        check_not_blocked(&ctx.accounts.maker_blocked)?;
+
+        // Límites por trade y acumulados del maker según su nivel KYC (AML)
+        let limits = ctx.accounts.platform.trading_limits(ctx.accounts.maker_profile.kyc_level);
+        ctx.accounts.maker_profile.record_volume(amount_mxn, &limits, Clock::get()?.unix_timestamp)?;
+
+        // El precio implícito debe estar dentro de la banda de la tasa de referencia
+        ctx.accounts.reference_rate.check_price(
+            amount,
+            amount_mxn,
+            supported_mint.decimals,
+            ctx.accounts.platform.max_price_deviation_bps,
+            Clock::get()?.unix_timestamp,
+        )?;
+        // En órdenes de compra el seller deposita al llenar la orden
+        require!(!(prefund && side == OrderSide::Buy), ErrorCode::InvalidOrderSide);
+
+        let fee_bearer = fee_bearer.unwrap_or(ctx.accounts.platform.default_fee_bearer);
+
+        // Un pre-fondeo con comisión del seller fija las tarifas al crear la orden:
+        // el taker aún no se conoce y se cotiza con la tarifa del nivel base
+        let (maker_fee_bps, taker_fee_bps, fee_amount) = if prefund && fee_bearer != FeeBearer::Buyer {
+            let Some(fee_schedule) = ctx.accounts.fee_schedule.as_ref() else {
+                return err!(ErrorCode::MissingFeeSchedule);
+            };
+            let (maker_fee_bps, taker_fee_bps) = fee_schedule.prefund_rates(&ctx.accounts.maker_profile, now);
+            let fee_amount = supported_mint.fee_amount(amount, maker_fee_bps + taker_fee_bps)?;
+            (maker_fee_bps, taker_fee_bps, fee_amount)
+        } else {
+            (0, 0, 0)
+        };
+
+        // Pre-fondeo: el seller deposita en escrow desde la creación (SOL como wSOL),
+        // más su parte de la comisión si le corresponde
+        let mut escrowed_amount = 0;
+        if prefund {
+            ctx.accounts.platform.check_operation(PAUSE_DEPOSITS)?;
+            let (Some(escrow_token_account), Some(mint), Some(token_program)) = (
+                ctx.accounts.escrow_token_account.as_mut(),
+                ctx.accounts.mint.as_ref(),
+                ctx.accounts.token_program.as_ref(),
+            ) else {
+                return err!(ErrorCode::MissingTokenAccounts);
+            };
+            require!(
+                escrow_token_account.owner == ctx.accounts.escrow.key(),
+                ErrorCode::InvalidEscrowTokenAccount
+            );
+            require!(escrow_token_account.mint == supported_mint.mint, ErrorCode::InvalidMint);
+            if let Some(maker_token_account) = ctx.accounts.maker_token_account.as_ref() {
+                require!(maker_token_account.mint == supported_mint.mint, ErrorCode::InvalidMint);
+            }
+
+            escrowed_amount = deposit_tokens(
+                token_program,
+                &ctx.accounts.maker,
+                ctx.accounts.maker_token_account.as_ref(),
+                escrow_token_account,
+                mint,
+                &ctx.accounts.system_program,
+                amount.checked_add(fee_bearer.seller_share(fee_amount)).ok_or(ErrorCode::MathOverflow)?,
+            )?;
+            require!(escrowed_amount > 0, ErrorCode::InvalidAmount);
+        }
 
         let order = &mut ctx.accounts.order;
         order.order_id = order_id;
-        order.seller = ctx.accounts.seller.key();
-        order.buyer = None;
+        order.side = side;
+        match side {
+            OrderSide::Sell => {
+                order.seller = ctx.accounts.maker.key();
+                order.buyer = None;
+            },
+            OrderSide::Buy => {
+                // El seller se asigna cuando alguien llena la orden
+                order.seller = Pubkey::default();
+                order.buyer = Some(ctx.accounts.maker.key());
+            },
+        }
         order.amount = amount;
         order.amount_mxn = amount_mxn;
-        order.token_type = token_type;
+        order.maker_volume_mxn = amount_mxn;
+        order.mint = supported_mint.mint;
+        order.decimals = supported_mint.decimals;
         order.payment_method = payment_method;
         order.status = OrderStatus::Open;
         order.stp_reference = stp_reference;
         order.stp_oracle_confirmed = false;
         order.created_at = Clock::get()?.unix_timestamp;
         order.escrow = ctx.accounts.escrow.key();
+        order.escrowed_amount = escrowed_amount;
+        order.prefunded = prefund;
+        order.price_feed = premium_bps.and(ctx.accounts.price_feed.as_ref().map(|feed| feed.key()));
+        order.premium_bps = premium_bps.unwrap_or(0);
+        order.maker_fee_bps = maker_fee_bps;
+        order.taker_fee_bps = taker_fee_bps;
+        order.fee_bearer = fee_bearer;
+        order.fee_amount = fee_amount;
+        if prefund {
+            order.funded_at = Some(order.created_at);
+        }
 
-        msg!("Order created: {} for {} MXN", order.order_id, amount_mxn);
+        emit!(OrderCreated {
+            order: order.key(),
+            order_id: order.order_id.clone(),
+            side,
+            maker: ctx.accounts.maker.key(),
+            mint: order.mint,
+            amount,
+            amount_mxn,
+            payment_method: order.payment_method.clone(),
+            premium_bps,
+            prefunded: prefund,
+            fee_bearer,
+            timestamp: order.created_at,
+        });
+        if prefund {
+            emit!(OrderFunded {
+                order: order.key(),
+                order_id: order.order_id.clone(),
+                seller: order.seller,
+                buyer: order.buyer,
+                mint: order.mint,
+                amount,
+                escrowed_amount,
+                timestamp: order.created_at,
+            });
+        }
         Ok(())
     }
 
     /// Aceptar una orden (buyer acepta comprar crypto)
-    pub fn accept_order(ctx: Context<AcceptOrder>) -> Result<()> {
+    /// En órdenes flotantes fija amount_mxn con el precio actual del feed
+    pub fn accept_order(ctx: Context<AcceptOrder>, max_price_mxn: Option<u64>) -> Result<()> {
+        ctx.accounts.platform.check_operation(PAUSE_ORDER_ACCEPTANCE)?;
         let order = &mut ctx.accounts.order;
+        let now = Clock::get()?.unix_timestamp;
 
         require!(order.status == OrderStatus::Open, ErrorCode::OrderNotOpen);
-        require!(ctx.accounts.buyer_profile.kyc_verified, ErrorCode::KYCRequired);
-        require!(ctx.accounts.buyer_profile.is_active, ErrorCode::UserNotActive);
+        require!(order.side == OrderSide::Sell, ErrorCode::InvalidOrderSide);
+        ctx.accounts.buyer_profile.refresh_kyc(&ctx.accounts.kyc_record, now)?;
+        ctx.accounts.buyer_profile.check_can_trade(now)?;
+        ctx.accounts.maker_profile.check_can_trade(now)?;
+        check_not_blocked(&ctx.accounts.buyer_blocked)?;
+        check_not_blocked(&ctx.accounts.maker_blocked)?;
         require!(order.seller != ctx.accounts.buyer.key(), ErrorCode::CannotTradeWithSelf);
+        ctx.accounts.supported_mint.check_trading()?;
+
+        if let Some(feed_key) = order.price_feed {
+            let Some(price_feed) = ctx.accounts.price_feed.as_ref() else {
+                return err!(ErrorCode::MissingPriceFeed);
+            };
+            require!(price_feed.key() == feed_key, ErrorCode::MissingPriceFeed);
+            // Protección contra slippage: el buyer fija el precio máximo aceptable
+            let Some(max_price_mxn) = max_price_mxn else {
+                return err!(ErrorCode::SlippageExceeded);
+            };
+
+            let price_mxn = price_feed.current_price(order.premium_bps, now)?;
+            require!(price_mxn <= max_price_mxn, ErrorCode::SlippageExceeded);
+
+            let amount_mxn = quote_amount_mxn(order.amount, price_mxn, order.decimals)?;
+            require!(amount_mxn > 0, ErrorCode::InvalidAmount);
+
+            // Como en create_order: el precio re-cotizado debe seguir dentro de la banda de referencia
+            let Some(reference_rate) = ctx.accounts.reference_rate.as_ref() else {
+                return err!(ErrorCode::MissingReferenceRate);
+            };
+            reference_rate.check_price(
+                order.amount,
+                amount_mxn,
+                order.decimals,
+                ctx.accounts.platform.max_price_deviation_bps,
+                now,
+            )?;
+            order.amount_mxn = amount_mxn;
+        }
+
+        // Tarifas según el volumen previo y nivel KYC de maker (seller) y taker (buyer);
+        // si el seller pre-fondeó su comisión, las tarifas ya quedaron fijas al crear
+        if !order.prefunds_fee() {
+            let (maker_fee_bps, taker_fee_bps) = ctx.accounts.fee_schedule.rates(
+                &ctx.accounts.maker_profile,
+                &ctx.accounts.buyer_profile,
+                now,
+            );
+            order.maker_fee_bps = maker_fee_bps;
+            order.taker_fee_bps = taker_fee_bps;
+        }
+
+        // Límites por trade y acumulados del taker según su nivel KYC (AML)
+        let limits = ctx.accounts.platform.trading_limits(ctx.accounts.buyer_profile.kyc_level);
+        ctx.accounts.buyer_profile.record_volume(order.amount_mxn, &limits, now)?;
 
         order.buyer = Some(ctx.accounts.buyer.key());
-        order.status = OrderStatus::Accepted;
-        order.accepted_at = Some(Clock::get()?.unix_timestamp);
+        order.accepted_at = Some(now);
+        // Una orden pre-fondeada pasa directo a la fase de pago
+        order.status = if order.prefunded {
+            OrderStatus::Funded
+        } else {
+            OrderStatus::Accepted
+        };
 
-        msg!("Order {} accepted by buyer: {}", order.order_id, ctx.accounts.buyer.key());
+        emit!(OrderAccepted {
+            order: order.key(),
+            order_id: order.order_id.clone(),
+            seller: order.seller,
+            buyer: ctx.accounts.buyer.key(),
+            taker: ctx.accounts.buyer.key(),
+            amount: order.amount,
+            amount_mxn: order.amount_mxn,
+            maker_fee_bps: order.maker_fee_bps,
+            taker_fee_bps: order.taker_fee_bps,
+            timestamp: now,
+        });
         Ok(())
     }
 
-    /// Depositar fondos en escrow (seller deposita crypto)
-    pub fn deposit_to_escrow_native(ctx: Context<DepositToEscrowNative>) -> Result<()> {
+    /// Llenar una orden de compra (seller deposita crypto en escrow)
+    pub fn fill_order(ctx: Context<FillOrder>) -> Result<()> {
+        ctx.accounts.platform.check_operation(PAUSE_ORDER_ACCEPTANCE | PAUSE_DEPOSITS)?;
         let order = &mut ctx.accounts.order;
 
-        require!(order.status == OrderStatus::Accepted, ErrorCode::InvalidOrderStatus);
-        require!(order.token_type == TokenType::SOL, ErrorCode::InvalidTokenType);
-        require!(order.seller == ctx.accounts.seller.key(), ErrorCode::UnauthorizedSeller);
-
-        // Transferir SOL del seller al escrow
-        let transfer_ix = anchor_lang::solana_program::system_instruction::transfer(
-            &ctx.accounts.seller.key(),
-            &ctx.accounts.escrow.key(),
-            order.amount,
+        require!(order.status == OrderStatus::Open, ErrorCode::OrderNotOpen);
+        require!(order.side == OrderSide::Buy, ErrorCode::InvalidOrderSide);
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.seller_profile.refresh_kyc(&ctx.accounts.kyc_record, now)?;
+        ctx.accounts.seller_profile.check_can_trade(now)?;
+        ctx.accounts.maker_profile.check_can_trade(now)?;
+        check_not_blocked(&ctx.accounts.seller_blocked)?;
+        check_not_blocked(&ctx.accounts.maker_blocked)?;
+        require!(order.buyer != Some(ctx.accounts.seller.key()), ErrorCode::CannotTradeWithSelf);
+        ctx.accounts.supported_mint.check_trading()?;
+
+        // Tarifas según el volumen previo y nivel KYC de maker (buyer) y taker (seller)
+        let (maker_fee_bps, taker_fee_bps) = ctx.accounts.fee_schedule.rates(
+            &ctx.accounts.maker_profile,
+            &ctx.accounts.seller_profile,
+            now,
         );
-
-        anchor_lang::solana_program::program::invoke(
-            &transfer_ix,
-            &[
-                ctx.accounts.seller.to_account_info(),
-                ctx.accounts.escrow.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
+        order.maker_fee_bps = maker_fee_bps;
+        order.taker_fee_bps = taker_fee_bps;
+
+        // Límites por trade y acumulados del seller que llena la orden (AML)
+        let limits = ctx.accounts.platform.trading_limits(ctx.accounts.seller_profile.kyc_level);
+        ctx.accounts.seller_profile.record_volume(order.amount_mxn, &limits, now)?;
+
+        let deposit_amount = order.quote_deposit(&ctx.accounts.supported_mint)?;
+        order.escrowed_amount = deposit_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.seller,
+            ctx.accounts.seller_token_account.as_ref(),
+            &mut ctx.accounts.escrow_token_account,
+            &ctx.accounts.mint,
+            &ctx.accounts.system_program,
+            deposit_amount,
         )?;
+        require!(order.escrowed_amount > 0, ErrorCode::InvalidAmount);
 
+        order.seller = ctx.accounts.seller.key();
         order.status = OrderStatus::Funded;
-        order.funded_at = Some(Clock::get()?.unix_timestamp);
-
-        msg!("SOL deposited to escrow for order: {}", order.order_id);
+        order.accepted_at = Some(now);
+        order.funded_at = Some(now);
+
+        emit!(OrderAccepted {
+            order: order.key(),
+            order_id: order.order_id.clone(),
+            seller: order.seller,
+            buyer: order.buyer.unwrap_or_default(),
+            taker: order.seller,
+            amount: order.amount,
+            amount_mxn: order.amount_mxn,
+            maker_fee_bps: order.maker_fee_bps,
+            taker_fee_bps: order.taker_fee_bps,
+            timestamp: now,
+        });
+        emit!(OrderFunded {
+            order: order.key(),
+            order_id: order.order_id.clone(),
+            seller: order.seller,
+            buyer: order.buyer,
+            mint: order.mint,
+            amount: order.amount,
+            escrowed_amount: order.escrowed_amount,
+            timestamp: now,
+        });
         Ok(())
     }
 
-    /// Depositar tokens SPL en escrow (USDC/USDT)
-    pub fn deposit_to_escrow_spl(ctx: Context<DepositToEscrowSPL>) -> Result<()> {
+    /// Cancelar una orden abierta que no tiene fondos en escrow (solo el maker)
+    pub fn cancel_unfunded_order(ctx: Context<CancelUnfundedOrder>) -> Result<()> {
         let order = &mut ctx.accounts.order;
 
-        require!(order.status == OrderStatus::Accepted, ErrorCode::InvalidOrderStatus);
-        require!(order.token_type != TokenType::SOL, ErrorCode::InvalidTokenType);
-        require!(order.seller == ctx.accounts.seller.key(), ErrorCode::UnauthorizedSeller);
+        require!(
+            order.status == OrderStatus::Open && !order.prefunded,
+            ErrorCode::InvalidOrderStatus
+        );
+        require!(order.maker() == ctx.accounts.maker.key(), ErrorCode::Unauthorized);
 
-        // Transferir SPL tokens del seller al escrow
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.seller_token_account.to_account_info(),
-            to: ctx.accounts.escrow_token_account.to_account_info(),
-            authority: ctx.accounts.seller.to_account_info(),
-        };
+        // Sin taker todavía: solo se libera el volumen registrado al maker
+        ctx.accounts.maker_profile.release_volume(order.maker_volume_mxn, order.created_at);
+
+        order.status = OrderStatus::Cancelled;
+        ctx.accounts.daily_stats.record_cancellation()?;
+
+        emit!(OrderCancelled {
+            order: order.key(),
+            order_id: order.order_id.clone(),
+            cancelled_by: ctx.accounts.maker.key(),
+            refunded_to: None,
+            refunded_amount: 0,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
 
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+    /// Depositar fondos en escrow (seller deposita crypto)
+    /// SOL se envuelve como wrapped SOL en la cuenta de tokens del escrow
+    pub fn deposit_to_escrow(ctx: Context<DepositToEscrow>) -> Result<()> {
+        ctx.accounts.platform.check_operation(PAUSE_DEPOSITS)?;
+        let order = &mut ctx.accounts.order;
 
-        token::transfer(cpi_ctx, order.amount)?;
+        require!(order.status == OrderStatus::Accepted, ErrorCode::InvalidOrderStatus);
+        require!(order.seller == ctx.accounts.seller.key(), ErrorCode::UnauthorizedSeller);
+        ctx.accounts.supported_mint.check_trading()?;
+
+        // Se registra lo que realmente llegó al escrow (monto más la comisión del seller)
+        let deposit_amount = order.quote_deposit(&ctx.accounts.supported_mint)?;
+        order.escrowed_amount = deposit_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.seller,
+            ctx.accounts.seller_token_account.as_ref(),
+            &mut ctx.accounts.escrow_token_account,
+            &ctx.accounts.mint,
+            &ctx.accounts.system_program,
+            deposit_amount,
+        )?;
+        require!(order.escrowed_amount > 0, ErrorCode::InvalidAmount);
 
+        let now = Clock::get()?.unix_timestamp;
         order.status = OrderStatus::Funded;
-        order.funded_at = Some(Clock::get()?.unix_timestamp);
-
-        msg!("SPL tokens deposited to escrow for order: {}", order.order_id);
+        order.funded_at = Some(now);
+
+        emit!(OrderFunded {
+            order: order.key(),
+            order_id: order.order_id.clone(),
+            seller: order.seller,
+            buyer: order.buyer,
+            mint: order.mint,
+            amount: order.amount,
+            escrowed_amount: order.escrowed_amount,
+            timestamp: now,
+        });
         Ok(())
     }
 
+    /// Compatibilidad: depositar SOL en escrow (ver deposit_to_escrow)
+    pub fn deposit_to_escrow_native(ctx: Context<DepositToEscrow>) -> Result<()> {
+        require!(ctx.accounts.order.is_native(), ErrorCode::InvalidTokenType);
+        deposit_to_escrow(ctx)
+    }
+
+    /// Compatibilidad: depositar tokens SPL en escrow (ver deposit_to_escrow)
+    pub fn deposit_to_escrow_spl(ctx: Context<DepositToEscrow>) -> Result<()> {
+        require!(!ctx.accounts.order.is_native(), ErrorCode::InvalidTokenType);
+        deposit_to_escrow(ctx)
+    }
+
     /// Confirmar pago fiat (buyer confirma que envió MXN via STP)
     pub fn confirm_fiat_payment(ctx: Context<ConfirmFiatPayment>, stp_transaction_id: String) -> Result<()> {
         let order = &mut ctx.accounts.order;
@@ -161,22 +543,30 @@ pub mod tipjar {
         require!(order.status == OrderStatus::Funded, ErrorCode::InvalidOrderStatus);
         require!(order.buyer == Some(ctx.accounts.buyer.key()), ErrorCode::UnauthorizedBuyer);
 
+        let now = Clock::get()?.unix_timestamp;
         order.stp_transaction_id = Some(stp_transaction_id.clone());
         order.status = OrderStatus::PaymentConfirmed;
-        order.payment_confirmed_at = Some(Clock::get()?.unix_timestamp);
-
-        msg!("Fiat payment confirmed for order: {} with STP ID: {}", order.order_id, stp_transaction_id);
+        order.payment_confirmed_at = Some(now);
+
+        emit!(FiatPaymentConfirmed {
+            order: order.key(),
+            order_id: order.order_id.clone(),
+            buyer: ctx.accounts.buyer.key(),
+            stp_transaction_id,
+            timestamp: now,
+        });
         Ok(())
     }
 
     /// Liberar fondos del escrow (después de confirmación del oráculo STP)
     /// Solo puede ser llamado por el oráculo autorizado o después de timeout
-    pub fn release_funds_native(ctx: Context<ReleaseFundsNative>) -> Result<()> {
+    pub fn release_funds(ctx: Context<ReleaseFunds>) -> Result<()> {
+        ctx.accounts.platform.check_operation(PAUSE_RELEASES)?;
         let order = &mut ctx.accounts.order;
         let platform = &ctx.accounts.platform;
 
+        order.check_not_frozen()?;
         require!(order.status == OrderStatus::PaymentConfirmed, ErrorCode::InvalidOrderStatus);
-        require!(order.token_type == TokenType::SOL, ErrorCode::InvalidTokenType);
 
         // Verificar que es el oráculo o ha pasado suficiente tiempo
         let is_oracle = ctx.accounts.authority.key() == platform.authority;
@@ -188,57 +578,16 @@ pub mod tipjar {
 
         require!(is_oracle || is_timeout, ErrorCode::Unauthorized);
 
-        // Calcular comisión
-        let fee_amount = (order.amount * platform.fee_bps) / 10000;
-        let buyer_amount = order.amount - fee_amount;
-
-        // Transferir SOL del escrow al buyer
-        **ctx.accounts.escrow.to_account_info().try_borrow_mut_lamports()? -= buyer_amount;
-        **ctx.accounts.buyer.to_account_info().try_borrow_mut_lamports()? += buyer_amount;
-
-        // Transferir comisión al treasury
-        **ctx.accounts.escrow.to_account_info().try_borrow_mut_lamports()? -= fee_amount;
-        **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += fee_amount;
-
-        order.status = OrderStatus::Completed;
-        order.completed_at = Some(Clock::get()?.unix_timestamp);
-        order.stp_oracle_confirmed = true;
-
-        // Actualizar perfiles
-        let seller_profile = &mut ctx.accounts.seller_profile;
-        seller_profile.total_trades += 1;
-        seller_profile.successful_trades += 1;
-
-        let buyer_profile = &mut ctx.accounts.buyer_profile;
-        buyer_profile.total_trades += 1;
-        buyer_profile.successful_trades += 1;
-
-        msg!("Funds released for order: {}", order.order_id);
-        Ok(())
-    }
-
-    /// Liberar fondos SPL del escrow
-    pub fn release_funds_spl(ctx: Context<ReleaseFundsSPL>) -> Result<()> {
-        let order = &mut ctx.accounts.order;
-        let platform = &ctx.accounts.platform;
-
-        require!(order.status == OrderStatus::PaymentConfirmed, ErrorCode::InvalidOrderStatus);
-        require!(order.token_type != TokenType::SOL, ErrorCode::InvalidTokenType);
-
-        let is_oracle = ctx.accounts.authority.key() == platform.authority;
-        let is_timeout = if let Some(confirmed_at) = order.payment_confirmed_at {
-            Clock::get()?.unix_timestamp - confirmed_at > 86400
-        } else {
-            false
-        };
-
-        require!(is_oracle || is_timeout, ErrorCode::Unauthorized);
-
-        // Calcular comisión
-        let fee_amount = (order.amount * platform.fee_bps) / 10000;
-        let buyer_amount = order.amount - fee_amount;
+        // Comisión con las tarifas fijadas al aceptar (maker + taker)
+        // Si la paga el seller ya viene incluida en el escrow y el buyer recibe el monto completo
+        let fee_bps = order.fee_bps();
+        let fee_amount = order.total_fee(&ctx.accounts.supported_mint)?;
+        let buyer_amount = order
+            .escrowed_amount
+            .checked_sub(fee_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        order.fee_amount = fee_amount;
 
-        // Transferir tokens al buyer
         let escrow_bump = ctx.bumps.escrow;
         let order_key = order.key();
         let escrow_seeds = &[
@@ -248,39 +597,80 @@ pub mod tipjar {
         ];
         let signer_seeds = &[&escrow_seeds[..]];
 
-        let transfer_to_buyer = Transfer {
-            from: ctx.accounts.escrow_token_account.to_account_info(),
-            to: ctx.accounts.buyer_token_account.to_account_info(),
-            authority: ctx.accounts.escrow.to_account_info(),
-        };
-
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            transfer_to_buyer,
-            signer_seeds,
-        );
-
-        token::transfer(cpi_ctx, buyer_amount)?;
-
-        // Transferir comisión al treasury
-        let transfer_fee = Transfer {
-            from: ctx.accounts.escrow_token_account.to_account_info(),
-            to: ctx.accounts.treasury_token_account.to_account_info(),
-            authority: ctx.accounts.escrow.to_account_info(),
-        };
-
-        let cpi_ctx_fee = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            transfer_fee,
+        // Parte de la comisión para los referidores de seller y buyer
+        let (seller_fee_bps, buyer_fee_bps) = order.party_fee_bps();
+        let rewards = referral_rewards(
+            &ctx.accounts.platform,
+            fee_amount,
+            [
+                (&ctx.accounts.seller_profile, seller_fee_bps, ctx.accounts.seller_referral.as_mut()),
+                (&ctx.accounts.buyer_profile, buyer_fee_bps, ctx.accounts.buyer_referral.as_mut()),
+            ],
+        )?;
+        let referral_amount: u64 = rewards.iter().map(|reward| reward.amount).sum();
+
+        // SOL: la recompensa se paga en lamports a la ReferralAccount al vaciar el escrow
+        // Tokens: se transfiere al vault de referidos antes de repartir el resto
+        let mut referral_payouts = Vec::new();
+        credit_referral_rewards(rewards, order.mint, order_key, |referral, amount| {
+            if order.is_native() {
+                referral_payouts.push((referral.to_account_info(), None, amount));
+                return Ok(amount);
+            }
+            let Some(referral_vault) = ctx.accounts.referral_vault.as_mut() else {
+                return err!(ErrorCode::MissingTokenAccounts);
+            };
+            transfer_tokens(
+                &ctx.accounts.token_program,
+                &ctx.accounts.escrow_token_account,
+                referral_vault,
+                &ctx.accounts.mint,
+                ctx.accounts.escrow.to_account_info(),
+                signer_seeds,
+                amount,
+            )
+        })?;
+
+        // Transferir al buyer y la comisión (sin recompensas) al treasury
+        let mut payouts = vec![
+            (
+                ctx.accounts.buyer.to_account_info(),
+                ctx.accounts.buyer_token_account.as_ref(),
+                buyer_amount,
+            ),
+            (
+                ctx.accounts.treasury.to_account_info(),
+                ctx.accounts.treasury_token_account.as_ref(),
+                fee_amount - referral_amount,
+            ),
+        ];
+        payouts.extend(referral_payouts);
+
+        pay_out_escrow(
+            &ctx.accounts.escrow,
+            &ctx.accounts.escrow_token_account,
+            &ctx.accounts.mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+            ctx.accounts.seller.to_account_info(),
             signer_seeds,
-        );
-
-        token::transfer(cpi_ctx_fee, fee_amount)?;
+            &payouts,
+        )?;
 
         order.status = OrderStatus::Completed;
         order.completed_at = Some(Clock::get()?.unix_timestamp);
         order.stp_oracle_confirmed = true;
 
+        // Estadísticas globales, por mint y del día
+        ctx.accounts.platform.record_settlement(order.amount_mxn)?;
+        ctx.accounts
+            .supported_mint
+            .record_settlement(order.escrowed_amount, order.amount_mxn, fee_amount)?;
+        ctx.accounts.daily_stats.record_settlement(
+            order.amount_mxn,
+            fee_amount_mxn(order.amount_mxn, fee_amount, order.escrowed_amount)?,
+        )?;
+
         // Actualizar perfiles
         let seller_profile = &mut ctx.accounts.seller_profile;
         seller_profile.total_trades += 1;
@@ -290,18 +680,46 @@ pub mod tipjar {
         buyer_profile.total_trades += 1;
         buyer_profile.successful_trades += 1;
 
-        msg!("SPL funds released for order: {}", order.order_id);
+        emit!(FundsReleased {
+            order: order.key(),
+            order_id: order.order_id.clone(),
+            seller: order.seller,
+            buyer: ctx.accounts.buyer.key(),
+            mint: order.mint,
+            amount_mxn: order.amount_mxn,
+            buyer_amount,
+            fee_amount,
+            fee_bps,
+            fee_bearer: order.fee_bearer,
+            referral_amount,
+            released_by: ctx.accounts.authority.key(),
+            timestamp: order.completed_at.unwrap_or_default(),
+        });
         Ok(())
     }
 
+    /// Compatibilidad: liberar SOL del escrow (ver release_funds)
+    pub fn release_funds_native(ctx: Context<ReleaseFunds>) -> Result<()> {
+        require!(ctx.accounts.order.is_native(), ErrorCode::InvalidTokenType);
+        release_funds(ctx)
+    }
+
+    /// Compatibilidad: liberar tokens SPL del escrow (ver release_funds)
+    pub fn release_funds_spl(ctx: Context<ReleaseFunds>) -> Result<()> {
+        require!(!ctx.accounts.order.is_native(), ErrorCode::InvalidTokenType);
+        release_funds(ctx)
+    }
+
     /// Abrir una disputa
     pub fn open_dispute(
         ctx: Context<OpenDispute>,
         reason: String,
         evidence: String,
     ) -> Result<()> {
+        ctx.accounts.platform.check_operation(PAUSE_DISPUTES)?;
         let order = &mut ctx.accounts.order;
 
+        order.check_not_frozen()?;
         require!(
             order.status == OrderStatus::Funded || order.status == OrderStatus::PaymentConfirmed,
             ErrorCode::InvalidOrderStatus
@@ -340,6 +758,7 @@ pub mod tipjar {
         dispute.resolution = None;
 
         order.status = OrderStatus::Disputed;
+        ctx.accounts.daily_stats.record_dispute()?;
 
         // Actualizar perfil
         if initiator == order.seller {
@@ -348,82 +767,191 @@ pub mod tipjar {
             ctx.accounts.buyer_profile.disputed_trades += 1;
         }
 
-        msg!("Dispute opened for order: {}", order.order_id);
+        emit!(DisputeOpened {
+            order: order.key(),
+            order_id: order.order_id.clone(),
+            dispute: dispute.key(),
+            initiator,
+            reason: dispute.reason.clone(),
+            evidence: dispute.evidence.clone(),
+            deposit_lamports: DISPUTE_DEPOSIT_LAMPORTS,
+            timestamp: dispute.created_at,
+        });
         Ok(())
     }
 
-    /// Resolver una disputa (solo autoridad/arbitro)
+    /// Resolver una disputa (solo autoridad/arbitro) y liquidar el escrow según el fallo
     pub fn resolve_dispute(
         ctx: Context<ResolveDispute>,
         resolution: DisputeResolution,
         resolution_notes: String,
     ) -> Result<()> {
+        ctx.accounts.platform.check_operation(PAUSE_DISPUTES)?;
+        let order = &mut ctx.accounts.order;
         let dispute = &mut ctx.accounts.dispute;
-        let platform = &ctx.accounts.platform;
 
+        order.check_not_frozen()?;
+        require!(order.status == OrderStatus::Disputed, ErrorCode::InvalidOrderStatus);
         require!(dispute.status == DisputeStatus::Open, ErrorCode::DisputeAlreadyResolved);
-        require!(ctx.accounts.resolver.key() == platform.authority, ErrorCode::Unauthorized);
+        require!(ctx.accounts.resolver.key() == ctx.accounts.platform.authority, ErrorCode::Unauthorized);
 
+        let now = Clock::get()?.unix_timestamp;
         dispute.resolver = Some(ctx.accounts.resolver.key());
         dispute.resolution = Some(resolution);
         dispute.status = DisputeStatus::Resolved;
-        dispute.resolved_at = Some(Clock::get()?.unix_timestamp);
+        dispute.resolved_at = Some(now);
         dispute.resolution_notes = Some(resolution_notes);
 
-        let order = &mut ctx.accounts.order;
+        let total_fee = order.total_fee(&ctx.accounts.supported_mint)?;
+        let (seller_amount, buyer_amount, fee_amount) = order.dispute_payouts(resolution, total_fee)?;
+        order.fee_amount = fee_amount;
+
+        let escrow_bump = ctx.bumps.escrow;
+        let order_key = order.key();
+        let escrow_seeds = &[
+            b"escrow",
+            order_key.as_ref(),
+            &[escrow_bump],
+        ];
+        let signer_seeds = &[&escrow_seeds[..]];
+
+        // Parte de la comisión cobrada para los referidores de seller y buyer
+        let (seller_fee_bps, buyer_fee_bps) = order.party_fee_bps();
+        let rewards = referral_rewards(
+            &ctx.accounts.platform,
+            fee_amount,
+            [
+                (&ctx.accounts.seller_profile, seller_fee_bps, ctx.accounts.seller_referral.as_mut()),
+                (&ctx.accounts.buyer_profile, buyer_fee_bps, ctx.accounts.buyer_referral.as_mut()),
+            ],
+        )?;
+        let referral_amount: u64 = rewards.iter().map(|reward| reward.amount).sum();
+
+        let mut referral_payouts = Vec::new();
+        credit_referral_rewards(rewards, order.mint, order_key, |referral, amount| {
+            if order.is_native() {
+                referral_payouts.push((referral.to_account_info(), None, amount));
+                return Ok(amount);
+            }
+            let Some(referral_vault) = ctx.accounts.referral_vault.as_mut() else {
+                return err!(ErrorCode::MissingTokenAccounts);
+            };
+            transfer_tokens(
+                &ctx.accounts.token_program,
+                &ctx.accounts.escrow_token_account,
+                referral_vault,
+                &ctx.accounts.mint,
+                ctx.accounts.escrow.to_account_info(),
+                signer_seeds,
+                amount,
+            )
+        })?;
+
+        // Solo se paga a quien le corresponde algo; la otra parte no necesita cuenta de tokens
+        let mut payouts = vec![
+            (
+                ctx.accounts.seller.to_account_info(),
+                ctx.accounts.seller_token_account.as_ref(),
+                seller_amount,
+            ),
+            (
+                ctx.accounts.buyer.to_account_info(),
+                ctx.accounts.buyer_token_account.as_ref(),
+                buyer_amount,
+            ),
+            (
+                ctx.accounts.treasury.to_account_info(),
+                ctx.accounts.treasury_token_account.as_ref(),
+                fee_amount - referral_amount,
+            ),
+        ];
+        payouts.retain(|(_, _, amount)| *amount > 0);
+        payouts.extend(referral_payouts);
+
+        pay_out_escrow(
+            &ctx.accounts.escrow,
+            &ctx.accounts.escrow_token_account,
+            &ctx.accounts.mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+            ctx.accounts.seller.to_account_info(),
+            signer_seeds,
+            &payouts,
+        )?;
 
-        // Actualizar estado según resolución
         match resolution {
-            DisputeResolution::FavorBuyer => {
-                order.status = OrderStatus::Completed;
-                // Los fondos se liberarán al buyer en release_funds
-            },
             DisputeResolution::FavorSeller => {
+                // Como en cancel_order: la orden deja de contar en los límites de maker y taker
+                let (maker_profile, taker_profile) = if order.maker() == order.seller {
+                    (&mut ctx.accounts.seller_profile, &mut ctx.accounts.buyer_profile)
+                } else {
+                    (&mut ctx.accounts.buyer_profile, &mut ctx.accounts.seller_profile)
+                };
+                maker_profile.release_volume(order.maker_volume_mxn, order.created_at);
+                if let Some(accepted_at) = order.accepted_at {
+                    taker_profile.release_volume(order.amount_mxn, accepted_at);
+                }
+
                 order.status = OrderStatus::Cancelled;
-                // Los fondos se devolverán al seller en cancel_order
+                ctx.accounts.daily_stats.record_cancellation()?;
             },
-            DisputeResolution::Split => {
-                order.status = OrderStatus::PartialRefund;
-                // Implementar lógica de split 50/50
+            DisputeResolution::FavorBuyer | DisputeResolution::Split => {
+                order.status = if matches!(resolution, DisputeResolution::FavorBuyer) {
+                    order.completed_at = Some(now);
+                    OrderStatus::Completed
+                } else {
+                    OrderStatus::PartialRefund
+                };
+
+                // Estadísticas globales, por mint y del día
+                ctx.accounts.platform.record_settlement(order.amount_mxn)?;
+                ctx.accounts
+                    .supported_mint
+                    .record_settlement(order.escrowed_amount, order.amount_mxn, fee_amount)?;
+                ctx.accounts.daily_stats.record_settlement(
+                    order.amount_mxn,
+                    fee_amount_mxn(order.amount_mxn, fee_amount, order.escrowed_amount)?,
+                )?;
             },
         }
 
-        msg!("Dispute resolved for order: {}", order.order_id);
+        emit!(DisputeResolved {
+            order: order.key(),
+            order_id: order.order_id.clone(),
+            dispute: dispute.key(),
+            resolver: ctx.accounts.resolver.key(),
+            resolution,
+            resolution_notes: dispute.resolution_notes.clone().unwrap_or_default(),
+            seller_amount,
+            buyer_amount,
+            fee_amount,
+            referral_amount,
+            timestamp: now,
+        });
         Ok(())
     }
 
-    /// Cancelar orden y devolver fondos (SOL)
-    pub fn cancel_order_native(ctx: Context<CancelOrderNative>) -> Result<()> {
+    /// Cancelar orden y devolver al seller los fondos del escrow
+    pub fn cancel_order(ctx: Context<CancelOrder>) -> Result<()> {
         let order = &mut ctx.accounts.order;
 
+        order.check_not_frozen()?;
         require!(
-            order.status == OrderStatus::Funded || order.status == OrderStatus::Cancelled,
+            order.status == OrderStatus::Funded
+                || (order.status == OrderStatus::Open && order.prefunded),
             ErrorCode::InvalidOrderStatus
         );
         require!(order.seller == ctx.accounts.seller.key(), ErrorCode::UnauthorizedSeller);
 
-        // Devolver SOL del escrow al seller
-        **ctx.accounts.escrow.to_account_info().try_borrow_mut_lamports()? -= order.amount;
-        **ctx.accounts.seller.to_account_info().try_borrow_mut_lamports()? += order.amount;
-
-        order.status = OrderStatus::Cancelled;
-
-        msg!("Order {} cancelled and funds returned", order.order_id);
-        Ok(())
-    }
-
-    /// Cancelar orden y devolver fondos (SPL tokens)
-    pub fn cancel_order_spl(ctx: Context<CancelOrderSPL>) -> Result<()> {
-        let order = &mut ctx.accounts.order;
-
-        require!(
-            order.status == OrderStatus::Funded || order.status == OrderStatus::Cancelled,
-            ErrorCode::InvalidOrderStatus
-        );
-        require!(order.seller == ctx.accounts.seller.key(), ErrorCode::UnauthorizedSeller);
-        require!(order.token_type != TokenType::SOL, ErrorCode::InvalidTokenType);
+        // Liberar el volumen que la orden registró a maker y taker en sus ventanas de límites
+        ctx.accounts.maker_profile.release_volume(order.maker_volume_mxn, order.created_at);
+        if let Some(accepted_at) = order.accepted_at {
+            let Some(taker_profile) = ctx.accounts.taker_profile.as_mut() else {
+                return err!(ErrorCode::MissingTakerProfile);
+            };
+            taker_profile.release_volume(order.amount_mxn, accepted_at);
+        }
 
-        // Devolver SPL tokens del escrow al seller
         let escrow_bump = ctx.bumps.escrow;
         let order_key = order.key();
         let escrow_seeds = &[
@@ -433,73 +961,62 @@ pub mod tipjar {
         ];
         let signer_seeds = &[&escrow_seeds[..]];
 
-        let transfer_back = Transfer {
-            from: ctx.accounts.escrow_token_account.to_account_info(),
-            to: ctx.accounts.seller_token_account.to_account_info(),
-            authority: ctx.accounts.escrow.to_account_info(),
-        };
-
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            transfer_back,
+        pay_out_escrow(
+            &ctx.accounts.escrow,
+            &ctx.accounts.escrow_token_account,
+            &ctx.accounts.mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+            ctx.accounts.seller.to_account_info(),
             signer_seeds,
-        );
-
-        token::transfer(cpi_ctx, order.amount)?;
+            &[(
+                ctx.accounts.seller.to_account_info(),
+                ctx.accounts.seller_token_account.as_ref(),
+                order.escrowed_amount,
+            )],
+        )?;
 
         order.status = OrderStatus::Cancelled;
-
-        msg!("Order {} cancelled and SPL tokens returned", order.order_id);
+        ctx.accounts.daily_stats.record_cancellation()?;
+
+        emit!(OrderCancelled {
+            order: order.key(),
+            order_id: order.order_id.clone(),
+            cancelled_by: ctx.accounts.seller.key(),
+            refunded_to: Some(order.seller),
+            refunded_amount: order.escrowed_amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
         Ok(())
     }
 
-    /// Resolver disputa con división 50/50 (SOL)
-    pub fn resolve_dispute_split_native(ctx: Context<ResolveDisputeSplitNative>) -> Result<()> {
-        let order = &mut ctx.accounts.order;
-        let platform = &ctx.accounts.platform;
-
-        require!(order.status == OrderStatus::Disputed, ErrorCode::InvalidOrderStatus);
-        require!(ctx.accounts.resolver.key() == platform.authority, ErrorCode::Unauthorized);
-        require!(order.token_type == TokenType::SOL, ErrorCode::InvalidTokenType);
-
-        // Dividir fondos 50/50 después de descontar comisión
-        let fee_amount = (order.amount * platform.fee_bps) / 10000;
-        let remaining = order.amount - fee_amount;
-        let seller_amount = remaining / 2;
-        let buyer_amount = remaining - seller_amount;
-
-        // Transferir al seller
-        **ctx.accounts.escrow.to_account_info().try_borrow_mut_lamports()? -= seller_amount;
-        **ctx.accounts.seller.to_account_info().try_borrow_mut_lamports()? += seller_amount;
-
-        // Transferir al buyer
-        **ctx.accounts.escrow.to_account_info().try_borrow_mut_lamports()? -= buyer_amount;
-        **ctx.accounts.buyer.to_account_info().try_borrow_mut_lamports()? += buyer_amount;
-
-        // Transferir comisión al treasury
-        **ctx.accounts.escrow.to_account_info().try_borrow_mut_lamports()? -= fee_amount;
-        **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += fee_amount;
-
-        order.status = OrderStatus::PartialRefund;
+    /// Compatibilidad: cancelar orden de SOL (ver cancel_order)
+    pub fn cancel_order_native(ctx: Context<CancelOrder>) -> Result<()> {
+        require!(ctx.accounts.order.is_native(), ErrorCode::InvalidTokenType);
+        cancel_order(ctx)
+    }
 
-        msg!("Dispute resolved with 50/50 split for order: {}", order.order_id);
-        Ok(())
+    /// Compatibilidad: cancelar orden de tokens SPL (ver cancel_order)
+    pub fn cancel_order_spl(ctx: Context<CancelOrder>) -> Result<()> {
+        require!(!ctx.accounts.order.is_native(), ErrorCode::InvalidTokenType);
+        cancel_order(ctx)
     }
 
-    /// Resolver disputa con división 50/50 (SPL tokens)
-    pub fn resolve_dispute_split_spl(ctx: Context<ResolveDisputeSplitSPL>) -> Result<()> {
+    /// Resolver disputa con división 50/50
+    pub fn resolve_dispute_split(ctx: Context<ResolveDisputeSplit>) -> Result<()> {
+        ctx.accounts.platform.check_operation(PAUSE_DISPUTES)?;
         let order = &mut ctx.accounts.order;
         let platform = &ctx.accounts.platform;
 
+        order.check_not_frozen()?;
         require!(order.status == OrderStatus::Disputed, ErrorCode::InvalidOrderStatus);
         require!(ctx.accounts.resolver.key() == platform.authority, ErrorCode::Unauthorized);
-        require!(order.token_type != TokenType::SOL, ErrorCode::InvalidTokenType);
 
-        // Calcular división
-        let fee_amount = (order.amount * platform.fee_bps) / 10000;
-        let remaining = order.amount - fee_amount;
-        let seller_amount = remaining / 2;
-        let buyer_amount = remaining - seller_amount;
+        // Dividir el monto 50/50 (ver Order::dispute_payouts)
+        let total_fee = order.total_fee(&ctx.accounts.supported_mint)?;
+        let (seller_amount, buyer_amount, fee_amount) =
+            order.dispute_payouts(DisputeResolution::Split, total_fee)?;
+        order.fee_amount = fee_amount;
 
         let escrow_bump = ctx.bumps.escrow;
         let order_key = order.key();
@@ -510,55 +1027,106 @@ pub mod tipjar {
         ];
         let signer_seeds = &[&escrow_seeds[..]];
 
-        // Transferir al seller
-        let transfer_to_seller = Transfer {
-            from: ctx.accounts.escrow_token_account.to_account_info(),
-            to: ctx.accounts.seller_token_account.to_account_info(),
-            authority: ctx.accounts.escrow.to_account_info(),
-        };
-
-        let cpi_ctx_seller = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            transfer_to_seller,
-            signer_seeds,
-        );
-
-        token::transfer(cpi_ctx_seller, seller_amount)?;
-
-        // Transferir al buyer
-        let transfer_to_buyer = Transfer {
-            from: ctx.accounts.escrow_token_account.to_account_info(),
-            to: ctx.accounts.buyer_token_account.to_account_info(),
-            authority: ctx.accounts.escrow.to_account_info(),
-        };
+        // Parte de la comisión cobrada para los referidores de seller y buyer
+        let (seller_fee_bps, buyer_fee_bps) = order.party_fee_bps();
+        let rewards = referral_rewards(
+            &ctx.accounts.platform,
+            fee_amount,
+            [
+                (&ctx.accounts.seller_profile, seller_fee_bps, ctx.accounts.seller_referral.as_mut()),
+                (&ctx.accounts.buyer_profile, buyer_fee_bps, ctx.accounts.buyer_referral.as_mut()),
+            ],
+        )?;
+        let referral_amount: u64 = rewards.iter().map(|reward| reward.amount).sum();
+
+        let mut referral_payouts = Vec::new();
+        credit_referral_rewards(rewards, order.mint, order_key, |referral, amount| {
+            if order.is_native() {
+                referral_payouts.push((referral.to_account_info(), None, amount));
+                return Ok(amount);
+            }
+            let Some(referral_vault) = ctx.accounts.referral_vault.as_mut() else {
+                return err!(ErrorCode::MissingTokenAccounts);
+            };
+            transfer_tokens(
+                &ctx.accounts.token_program,
+                &ctx.accounts.escrow_token_account,
+                referral_vault,
+                &ctx.accounts.mint,
+                ctx.accounts.escrow.to_account_info(),
+                signer_seeds,
+                amount,
+            )
+        })?;
 
-        let cpi_ctx_buyer = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            transfer_to_buyer,
+        let mut payouts = vec![
+            (
+                ctx.accounts.seller.to_account_info(),
+                ctx.accounts.seller_token_account.as_ref(),
+                seller_amount,
+            ),
+            (
+                ctx.accounts.buyer.to_account_info(),
+                ctx.accounts.buyer_token_account.as_ref(),
+                buyer_amount,
+            ),
+            (
+                ctx.accounts.treasury.to_account_info(),
+                ctx.accounts.treasury_token_account.as_ref(),
+                fee_amount - referral_amount,
+            ),
+        ];
+        payouts.extend(referral_payouts);
+
+        pay_out_escrow(
+            &ctx.accounts.escrow,
+            &ctx.accounts.escrow_token_account,
+            &ctx.accounts.mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+            ctx.accounts.seller.to_account_info(),
             signer_seeds,
-        );
-
-        token::transfer(cpi_ctx_buyer, buyer_amount)?;
+            &payouts,
+        )?;
 
-        // Transferir comisión al treasury
-        let transfer_fee = Transfer {
-            from: ctx.accounts.escrow_token_account.to_account_info(),
-            to: ctx.accounts.treasury_token_account.to_account_info(),
-            authority: ctx.accounts.escrow.to_account_info(),
-        };
+        order.status = OrderStatus::PartialRefund;
 
-        let cpi_ctx_fee = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            transfer_fee,
-            signer_seeds,
-        );
+        // Estadísticas globales, por mint y del día
+        ctx.accounts.platform.record_settlement(order.amount_mxn)?;
+        ctx.accounts
+            .supported_mint
+            .record_settlement(order.escrowed_amount, order.amount_mxn, fee_amount)?;
+        ctx.accounts.daily_stats.record_settlement(
+            order.amount_mxn,
+            fee_amount_mxn(order.amount_mxn, fee_amount, order.escrowed_amount)?,
+        )?;
 
-        token::transfer(cpi_ctx_fee, fee_amount)?;
+        emit!(DisputeSplitSettled {
+            order: order.key(),
+            order_id: order.order_id.clone(),
+            seller: order.seller,
+            buyer: ctx.accounts.buyer.key(),
+            resolver: ctx.accounts.resolver.key(),
+            mint: order.mint,
+            seller_amount,
+            buyer_amount,
+            fee_amount,
+            referral_amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
 
-        order.status = OrderStatus::PartialRefund;
+    /// Compatibilidad: división 50/50 de una orden de SOL (ver resolve_dispute_split)
+    pub fn resolve_dispute_split_native(ctx: Context<ResolveDisputeSplit>) -> Result<()> {
+        require!(ctx.accounts.order.is_native(), ErrorCode::InvalidTokenType);
+        resolve_dispute_split(ctx)
+    }
 
-        msg!("Dispute resolved with 50/50 split for order: {}", order.order_id);
-        Ok(())
+    /// Compatibilidad: división 50/50 de una orden de tokens SPL (ver resolve_dispute_split)
+    pub fn resolve_dispute_split_spl(ctx: Context<ResolveDisputeSplit>) -> Result<()> {
+        require!(!ctx.accounts.order.is_native(), ErrorCode::InvalidTokenType);
+        resolve_dispute_split(ctx)
     }
 
     /// Actualizar estado del oráculo STP (solo para autoridad/oráculo)
@@ -573,526 +1141,5896 @@ pub mod tipjar {
 
         order.stp_oracle_confirmed = confirmed;
 
-        msg!("Oracle status updated for order: {} to {}", order.order_id, confirmed);
+        emit!(OracleStatusUpdated {
+            order: order.key(),
+            order_id: order.order_id.clone(),
+            oracle: ctx.accounts.oracle.key(),
+            confirmed,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
         Ok(())
     }
-}
 
-// ============================================================================
-// CONTEXTOS DE INSTRUCCIONES
-// ============================================================================
+    /// Registrar un mint soportado con sus límites y comisión (solo autoridad)
+    pub fn register_mint(
+        ctx: Context<RegisterMint>,
+        min_trade_amount: u64,
+        max_trade_amount: u64,
+        fee_bps_override: Option<u64>, // Comisión específica del mint (sustituye la del esquema)
+        min_fee: u64, // Comisión mínima en unidades del token
+        max_fee: Option<u64>, // Tope de comisión en unidades del token
+    ) -> Result<()> {
+        require!(
+            min_trade_amount > 0 && min_trade_amount <= max_trade_amount,
+            ErrorCode::InvalidTradeLimits
+        );
+        require!(fee_bps_override.is_none_or(|fee| fee <= 10_000), ErrorCode::InvalidAmount);
+        require!(max_fee.is_none_or(|max_fee| min_fee <= max_fee), ErrorCode::InvalidFeeSchedule);
+
+        let mint = ctx.accounts.mint.key();
+        consume_setup_approval(
+            &ctx.accounts.platform,
+            ctx.accounts.council.as_ref(),
+            ctx.accounts.proposal.as_mut(),
+            ctx.accounts.authority.key(),
+            |action| {
+                matches!(
+                    *action,
+                    AdminAction::RegisterMint {
+                        mint: approved_mint,
+                        min_trade_amount: approved_min_trade_amount,
+                        max_trade_amount: approved_max_trade_amount,
+                        fee_bps_override: approved_fee_bps_override,
+                        min_fee: approved_min_fee,
+                        max_fee: approved_max_fee,
+                    } if approved_mint == mint
+                        && approved_min_trade_amount == min_trade_amount
+                        && approved_max_trade_amount == max_trade_amount
+                        && approved_fee_bps_override == fee_bps_override
+                        && approved_min_fee == min_fee
+                        && approved_max_fee == max_fee
+                )
+            },
+        )?;
 
-#[derive(Accounts)]
-pub struct InitializePlatform<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + Platform::INIT_SPACE,
-        seeds = [b"platform"],
-        bump
-    )]
-    pub platform: Account<'info, Platform>,
+        let supported_mint = &mut ctx.accounts.supported_mint;
+        supported_mint.mint = mint;
+        supported_mint.decimals = ctx.accounts.mint.decimals;
+        supported_mint.min_trade_amount = min_trade_amount;
+        supported_mint.max_trade_amount = max_trade_amount;
+        supported_mint.fee_bps_override = fee_bps_override;
+        supported_mint.min_fee = min_fee;
+        supported_mint.max_fee = max_fee;
+        supported_mint.enabled = true;
+        supported_mint.trading_paused = false;
+        supported_mint.total_volume = 0;
+        supported_mint.total_volume_mxn = 0;
+        supported_mint.total_fees = 0;
+        supported_mint.total_transactions = 0;
+
+        emit!(MintConfigUpdated {
+            mint: supported_mint.mint,
+            decimals: supported_mint.decimals,
+            min_trade_amount,
+            max_trade_amount,
+            fee_bps_override,
+            min_fee,
+            max_fee,
+            enabled: true,
+            authority: ctx.accounts.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
 
-    #[account(mut)]
-    pub authority: Signer<'info>,
+    /// Actualizar límites, comisión o estado de un mint soportado (solo autoridad)
+    pub fn update_mint(
+        ctx: Context<UpdateMint>,
+        min_trade_amount: u64,
+        max_trade_amount: u64,
+        fee_bps_override: Option<u64>,
+        min_fee: u64,
+        max_fee: Option<u64>,
+        enabled: bool,
+    ) -> Result<()> {
+        require!(
+            min_trade_amount > 0 && min_trade_amount <= max_trade_amount,
+            ErrorCode::InvalidTradeLimits
+        );
+        require!(fee_bps_override.is_none_or(|fee| fee <= 10_000), ErrorCode::InvalidAmount);
+        require!(max_fee.is_none_or(|max_fee| min_fee <= max_fee), ErrorCode::InvalidFeeSchedule);
+
+        let supported_mint = &mut ctx.accounts.supported_mint;
+        supported_mint.min_trade_amount = min_trade_amount;
+        supported_mint.max_trade_amount = max_trade_amount;
+        supported_mint.fee_bps_override = fee_bps_override;
+        supported_mint.min_fee = min_fee;
+        supported_mint.max_fee = max_fee;
+        supported_mint.enabled = enabled;
+
+        emit!(MintConfigUpdated {
+            mint: supported_mint.mint,
+            decimals: supported_mint.decimals,
+            min_trade_amount,
+            max_trade_amount,
+            fee_bps_override,
+            min_fee,
+            max_fee,
+            enabled,
+            authority: ctx.accounts.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
 
-    /// CHECK: Treasury para recibir comisiones
-    pub treasury: AccountInfo<'info>,
+    /// Crear la tasa de referencia de un token (solo autoridad)
+    pub fn initialize_reference_rate(
+        ctx: Context<InitializeReferenceRate>,
+        max_staleness_seconds: i64,
+    ) -> Result<()> {
+        require!(max_staleness_seconds > 0, ErrorCode::InvalidAmount);
+
+        let mint = ctx.accounts.supported_mint.mint;
+        consume_setup_approval(
+            &ctx.accounts.platform,
+            ctx.accounts.council.as_ref(),
+            ctx.accounts.proposal.as_mut(),
+            ctx.accounts.authority.key(),
+            |action| {
+                matches!(
+                    *action,
+                    AdminAction::InitializeReferenceRate {
+                        mint: approved_mint,
+                        max_staleness_seconds: approved_staleness,
+                    } if approved_mint == mint && approved_staleness == max_staleness_seconds
+                )
+            },
+        )?;
 
-    pub system_program: Program<'info, System>,
-}
+        let reference_rate = &mut ctx.accounts.reference_rate;
+        reference_rate.mint = ctx.accounts.supported_mint.mint;
+        reference_rate.rate_mxn = 0;
+        reference_rate.updated_at = 0;
+        reference_rate.max_staleness_seconds = max_staleness_seconds;
+
+        emit!(ReferenceRateInitialized {
+            mint: reference_rate.mint,
+            max_staleness_seconds,
+            authority: ctx.accounts.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
 
-#[derive(Accounts)]
-pub struct CreateUserProfile<'info> {
-    #[account(
-        init,
-        payer = user,
-        space = 8 + UserProfile::INIT_SPACE,
-        seeds = [b"user_profile", user.key().as_ref()],
-        bump
-    )]
-    pub user_profile: Account<'info, UserProfile>,
+    /// Actualizar la tasa de referencia (solo oráculo)
+    pub fn update_reference_rate(ctx: Context<UpdateReferenceRate>, rate_mxn: u64) -> Result<()> {
+        require!(ctx.accounts.oracle.key() == ctx.accounts.platform.authority, ErrorCode::Unauthorized);
+        require!(rate_mxn > 0, ErrorCode::InvalidAmount);
+
+        let reference_rate = &mut ctx.accounts.reference_rate;
+        reference_rate.rate_mxn = rate_mxn;
+        reference_rate.updated_at = Clock::get()?.unix_timestamp;
+
+        emit!(ReferenceRateUpdated {
+            mint: reference_rate.mint,
+            rate_mxn,
+            oracle: ctx.accounts.oracle.key(),
+            timestamp: reference_rate.updated_at,
+        });
+        Ok(())
+    }
 
-    #[account(mut)]
-    pub user: Signer<'info>,
+    /// Configurar la desviación máxima permitida contra la tasa de referencia
+    pub fn set_max_price_deviation(ctx: Context<UpdatePlatformConfig>, max_deviation_bps: u64) -> Result<()> {
+        require!(max_deviation_bps > 0 && max_deviation_bps <= 10_000, ErrorCode::InvalidAmount);
 
-    pub system_program: Program<'info, System>,
-}
+        let platform = &mut ctx.accounts.platform;
+        platform.max_price_deviation_bps = max_deviation_bps;
 
-#[derive(Accounts)]
-#[instruction(order_id: String)]
-pub struct CreateOrder<'info> {
+        emit!(MaxPriceDeviationUpdated {
+            max_price_deviation_bps: max_deviation_bps,
+            authority: ctx.accounts.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Configurar los límites por trade y acumulados de un nivel KYC (solo autoridad)
+    pub fn set_trading_limits(
+        ctx: Context<UpdatePlatformConfig>,
+        level: VerificationLevel,
+        limits: TradingLimits,
+    ) -> Result<()> {
+        limits.validate()?;
+
+        let platform = &mut ctx.accounts.platform;
+        platform.kyc_limits[level as usize] = limits;
+
+        emit!(TradingLimitsUpdated {
+            level,
+            limits,
+            authority: ctx.accounts.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Configurar quién paga la comisión en órdenes que no lo especifican (solo autoridad)
+    pub fn set_default_fee_bearer(ctx: Context<UpdatePlatformConfig>, fee_bearer: FeeBearer) -> Result<()> {
+        let platform = &mut ctx.accounts.platform;
+        platform.default_fee_bearer = fee_bearer;
+
+        emit!(DefaultFeeBearerUpdated {
+            fee_bearer,
+            authority: ctx.accounts.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Configurar la parte de la comisión que reciben los referidores (solo autoridad)
+    pub fn set_referral_share(ctx: Context<UpdatePlatformConfig>, share_bps: u64) -> Result<()> {
+        require!(share_bps <= MAX_REFERRAL_SHARE_BPS, ErrorCode::InvalidAmount);
+
+        let platform = &mut ctx.accounts.platform;
+        platform.referral_share_bps = share_bps;
+
+        emit!(ReferralShareUpdated {
+            referral_share_bps: share_bps,
+            authority: ctx.accounts.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Designar al guardián de los circuit breakers (solo autoridad, antes del consejo)
+    pub fn set_guardian(ctx: Context<UpdatePlatformConfig>, guardian: Pubkey) -> Result<()> {
+        let platform = &mut ctx.accounts.platform;
+        platform.guardian = guardian;
+
+        emit!(GuardianUpdated {
+            guardian,
+            authority: ctx.accounts.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Pausar o reanudar familias de instrucciones (solo guardián)
+    /// Las cancelaciones nunca se pausan para que los usuarios puedan salir
+    pub fn set_paused_operations(ctx: Context<UpdateCircuitBreaker>, paused_operations: u16) -> Result<()> {
+        require!(paused_operations & !PAUSE_ALL == 0, ErrorCode::InvalidPauseFlags);
+
+        let platform = &mut ctx.accounts.platform;
+        platform.paused_operations = paused_operations;
+
+        emit!(CircuitBreakerUpdated {
+            paused_operations,
+            guardian: ctx.accounts.guardian.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Pausar o reanudar nuevas operaciones en un mint (solo guardián)
+    /// Las liquidaciones y cancelaciones de operaciones en curso siguen permitidas
+    pub fn set_mint_trading_paused(ctx: Context<UpdateMintCircuitBreaker>, paused: bool) -> Result<()> {
+        let supported_mint = &mut ctx.accounts.supported_mint;
+        supported_mint.trading_paused = paused;
+
+        emit!(MintCircuitBreakerUpdated {
+            mint: supported_mint.mint,
+            trading_paused: paused,
+            guardian: ctx.accounts.guardian.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Designar al oficial de cumplimiento (solo autoridad, antes del consejo)
+    pub fn set_compliance_officer(ctx: Context<UpdatePlatformConfig>, compliance: Pubkey) -> Result<()> {
+        let platform = &mut ctx.accounts.platform;
+        platform.compliance = compliance;
+
+        emit!(ComplianceOfficerUpdated {
+            compliance,
+            authority: ctx.accounts.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Suspender temporalmente a un usuario (autoridad o cumplimiento)
+    /// Solo le impide iniciar operaciones nuevas; las que tenga en curso pueden terminar
+    pub fn suspend_user(ctx: Context<ModerateUser>, reason_code: u16, duration_seconds: i64) -> Result<()> {
+        change_user_status(
+            &mut ctx.accounts.user_profile,
+            UserStatus::Suspended,
+            Some(duration_seconds),
+            reason_code,
+            ctx.accounts.moderator.key(),
+            Clock::get()?.unix_timestamp,
+        )
+    }
+
+    /// Banear a un usuario de forma indefinida (autoridad o cumplimiento)
+    pub fn ban_user(ctx: Context<ModerateUser>, reason_code: u16) -> Result<()> {
+        change_user_status(
+            &mut ctx.accounts.user_profile,
+            UserStatus::Banned,
+            None,
+            reason_code,
+            ctx.accounts.moderator.key(),
+            Clock::get()?.unix_timestamp,
+        )
+    }
+
+    /// Reactivar a un usuario suspendido o baneado (autoridad o cumplimiento)
+    pub fn reinstate_user(ctx: Context<ModerateUser>, reason_code: u16) -> Result<()> {
+        change_user_status(
+            &mut ctx.accounts.user_profile,
+            UserStatus::Active,
+            None,
+            reason_code,
+            ctx.accounts.moderator.key(),
+            Clock::get()?.unix_timestamp,
+        )
+    }
+
+    /// Congelar el escrow de una orden por requerimiento de autoridades (autoridad o cumplimiento)
+    /// La orden queda en `Frozen`: no se puede liberar, cancelar ni resolver su disputa
+    pub fn place_legal_hold(ctx: Context<ManageLegalHold>, case_reference: String) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+
+        require!(
+            !case_reference.is_empty() && case_reference.len() <= MAX_CASE_REFERENCE_LEN,
+            ErrorCode::InvalidCaseReference
+        );
+        order.check_not_frozen()?;
+        require!(order.holds_escrow(), ErrorCode::OrderNotHoldable);
+
+        let now = Clock::get()?.unix_timestamp;
+        order.held_status = Some(order.status.clone());
+        order.status = OrderStatus::Frozen;
+        order.legal_hold_case = Some(case_reference.clone());
+        order.legal_hold_by = Some(ctx.accounts.moderator.key());
+        order.legal_hold_at = Some(now);
+
+        emit!(LegalHoldPlaced {
+            order: order.key(),
+            order_id: order.order_id.clone(),
+            case_reference,
+            placed_by: ctx.accounts.moderator.key(),
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Levantar la retención legal y regresar la orden al estado que tenía
+    pub fn lift_legal_hold(ctx: Context<ManageLegalHold>) -> Result<()> {
+        let order = &mut ctx.accounts.order;
+
+        require!(order.status == OrderStatus::Frozen, ErrorCode::OrderNotFrozen);
+
+        let restored_status = order.held_status.take().ok_or(ErrorCode::OrderNotFrozen)?;
+        let case_reference = order.legal_hold_case.take().unwrap_or_default();
+        order.status = restored_status.clone();
+        order.legal_hold_by = None;
+        order.legal_hold_at = None;
+
+        emit!(LegalHoldLifted {
+            order: order.key(),
+            order_id: order.order_id.clone(),
+            case_reference,
+            restored_status,
+            lifted_by: ctx.accounts.moderator.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Transferir los fondos congelados a la cuenta que indique una orden judicial (solo autoridad)
+    /// Requiere un `SeizeFrozenFunds` aprobado por el consejo para esta orden y destino, con su timelock cumplido;
+    /// la orden queda en `Seized` con la referencia del caso como registro
+    pub fn transfer_frozen_funds(ctx: Context<TransferFrozenFunds>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let pending_change = &ctx.accounts.pending_change;
+        let order = &mut ctx.accounts.order;
+
+        let AdminAction::SeizeFrozenFunds { order: approved_order, destination } = pending_change.action else {
+            return err!(ErrorCode::CourtTransferNotApproved);
+        };
+        require!(
+            approved_order == order.key() && destination == ctx.accounts.destination.key(),
+            ErrorCode::CourtTransferNotApproved
+        );
+        require!(now >= pending_change.eta, ErrorCode::ChangeStillLocked);
+        require!(order.status == OrderStatus::Frozen, ErrorCode::OrderNotFrozen);
+
+        let escrow_bump = ctx.bumps.escrow;
+        let order_key = order.key();
+        let escrow_seeds = &[
+            b"escrow",
+            order_key.as_ref(),
+            &[escrow_bump],
+        ];
+        let signer_seeds = &[&escrow_seeds[..]];
+
+        pay_out_escrow(
+            &ctx.accounts.escrow,
+            &ctx.accounts.escrow_token_account,
+            &ctx.accounts.mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+            ctx.accounts.seller.to_account_info(),
+            signer_seeds,
+            &[(
+                ctx.accounts.destination.to_account_info(),
+                ctx.accounts.destination_token_account.as_ref(),
+                order.escrowed_amount,
+            )],
+        )?;
+
+        order.status = OrderStatus::Seized;
+        order.held_status = None;
+        order.completed_at = Some(now);
+
+        emit!(FrozenFundsTransferred {
+            order: order.key(),
+            order_id: order.order_id.clone(),
+            case_reference: order.legal_hold_case.clone().unwrap_or_default(),
+            destination: ctx.accounts.destination.key(),
+            amount: order.escrowed_amount,
+            pending_change: ctx.accounts.pending_change.key(),
+            authority: ctx.accounts.authority.key(),
+            timestamp: now,
+        });
+        emit!(ChangeExecuted {
+            pending_change: ctx.accounts.pending_change.key(),
+            proposal: ctx.accounts.pending_change.proposal,
+            action: ctx.accounts.pending_change.action.clone(),
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Agregar direcciones al blocklist (autoridad o cumplimiento)
+    /// `remaining_accounts` trae el PDA `[b"blocked_address", address]` de cada dirección, en el mismo orden;
+    /// las que ya estaban bloqueadas se omiten
+    pub fn block_addresses<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UpdateBlocklist<'info>>,
+        addresses: Vec<Pubkey>,
+        reason_code: u16,
+    ) -> Result<()> {
+        require!(
+            !addresses.is_empty()
+                && addresses.len() <= MAX_BLOCKLIST_BATCH
+                && addresses.len() == ctx.remaining_accounts.len(),
+            ErrorCode::InvalidBlocklistBatch
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let moderator = ctx.accounts.moderator.key();
+
+        for (address, entry_info) in addresses.iter().zip(ctx.remaining_accounts.iter()) {
+            let (entry_key, bump) =
+                Pubkey::find_program_address(&[b"blocked_address", address.as_ref()], &crate::ID);
+            require!(entry_info.key() == entry_key, ErrorCode::InvalidBlockedAddressAccount);
+            if is_blocked(entry_info) {
+                continue;
+            }
+
+            let entry_seeds = &[b"blocked_address", address.as_ref(), &[bump]];
+            create_program_pda(
+                &ctx.accounts.moderator,
+                entry_info,
+                &ctx.accounts.system_program,
+                &[&entry_seeds[..]],
+                8 + BlockedAddress::INIT_SPACE,
+            )?;
+
+            let entry = BlockedAddress {
+                address: *address,
+                reason_code,
+                blocked_by: moderator,
+                blocked_at: now,
+            };
+            entry.try_serialize(&mut &mut entry_info.try_borrow_mut_data()?[..])?;
+
+            emit!(BlockedAddressAdded {
+                address: *address,
+                reason_code,
+                blocked_by: moderator,
+                timestamp: now,
+            });
+        }
+        Ok(())
+    }
+
+    /// Quitar direcciones del blocklist (autoridad o cumplimiento)
+    /// Igual que `block_addresses`, con los PDAs en `remaining_accounts`; la renta regresa al moderador
+    pub fn unblock_addresses<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UpdateBlocklist<'info>>,
+        addresses: Vec<Pubkey>,
+    ) -> Result<()> {
+        require!(
+            !addresses.is_empty()
+                && addresses.len() <= MAX_BLOCKLIST_BATCH
+                && addresses.len() == ctx.remaining_accounts.len(),
+            ErrorCode::InvalidBlocklistBatch
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+
+        for (address, entry_info) in addresses.iter().zip(ctx.remaining_accounts.iter()) {
+            let (entry_key, _) =
+                Pubkey::find_program_address(&[b"blocked_address", address.as_ref()], &crate::ID);
+            require!(entry_info.key() == entry_key, ErrorCode::InvalidBlockedAddressAccount);
+            if !is_blocked(entry_info) {
+                continue;
+            }
+
+            let entry = Account::<BlockedAddress>::try_from(entry_info)?;
+            entry.close(ctx.accounts.moderator.to_account_info())?;
+
+            emit!(BlockedAddressRemoved {
+                address: *address,
+                unblocked_by: ctx.accounts.moderator.key(),
+                timestamp: now,
+            });
+        }
+        Ok(())
+    }
+
+    /// Crear el vault de recompensas de referidos de un mint SPL (solo autoridad)
+    /// Las recompensas en SOL se guardan en la propia ReferralAccount
+    pub fn initialize_referral_vault(ctx: Context<InitializeReferralVault>) -> Result<()> {
+        require!(!ctx.accounts.supported_mint.is_native(), ErrorCode::InvalidTokenType);
+        Ok(())
+    }
+
+    /// Cobrar las recompensas de referidos acumuladas en un mint
+    pub fn claim_referral_rewards(ctx: Context<ClaimReferralRewards>) -> Result<()> {
+        let referral = &mut ctx.accounts.referral_account;
+        let amount = referral.claimable;
+        require!(amount > 0, ErrorCode::NoReferralRewards);
+
+        if referral.mint == native_mint::ID {
+            // Los lamports acumulados están en la ReferralAccount, encima de su renta
+            referral.sub_lamports(amount)?;
+            ctx.accounts.referrer.add_lamports(amount)?;
+        } else {
+            let (Some(referral_vault), Some(referrer_token_account), Some(mint), Some(token_program)) = (
+                ctx.accounts.referral_vault.as_ref(),
+                ctx.accounts.referrer_token_account.as_mut(),
+                ctx.accounts.mint.as_ref(),
+                ctx.accounts.token_program.as_ref(),
+            ) else {
+                return err!(ErrorCode::MissingTokenAccounts);
+            };
+
+            let platform_seeds = &[b"platform".as_ref(), &[ctx.bumps.platform]];
+            transfer_tokens(
+                token_program,
+                referral_vault,
+                referrer_token_account,
+                mint,
+                ctx.accounts.platform.to_account_info(),
+                &[&platform_seeds[..]],
+                amount,
+            )?;
+        }
+
+        let now = Clock::get()?.unix_timestamp;
+        referral.claimable = 0;
+        referral.total_claimed = referral.total_claimed.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+        referral.last_claimed_at = Some(now);
+
+        emit!(ReferralRewardsClaimed {
+            referral_account: referral.key(),
+            referrer: referral.referrer,
+            mint: referral.mint,
+            amount,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Crear el treasury de la plataforma con sus administradores (solo autoridad, una vez)
+    /// Las comisiones en SOL se guardan en la cuenta Treasury y las de tokens en su ATA por mint
+    pub fn initialize_treasury(
+        ctx: Context<InitializeTreasury>,
+        admins: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        Treasury::validate_admins(&admins, threshold)?;
+
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.admins = admins;
+        treasury.threshold = threshold;
+        treasury.withdrawal_count = 0;
+
+        emit!(TreasuryInitialized {
+            treasury: treasury.key(),
+            admins: treasury.admins.clone(),
+            threshold,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Proponer un retiro del treasury (solo administradores); cuenta como primera aprobación
+    pub fn propose_treasury_withdrawal(
+        ctx: Context<ProposeTreasuryWithdrawal>,
+        mint: Pubkey, // Native mint para SOL
+        amount: u64,
+        recipient: Pubkey, // Wallet que recibe (dueña de la cuenta de tokens en SPL)
+    ) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        let proposer = ctx.accounts.admin.key();
+        ctx.accounts.treasury.check_admin(&proposer)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let treasury = &mut ctx.accounts.treasury;
+        let withdrawal = &mut ctx.accounts.withdrawal;
+        withdrawal.index = treasury.withdrawal_count;
+        withdrawal.mint = mint;
+        withdrawal.amount = amount;
+        withdrawal.recipient = recipient;
+        withdrawal.proposer = proposer;
+        withdrawal.approvals = vec![proposer];
+        withdrawal.created_at = now;
+        withdrawal.executed_at = None;
+        treasury.withdrawal_count += 1;
+
+        emit!(TreasuryWithdrawalProposed {
+            withdrawal: withdrawal.key(),
+            index: withdrawal.index,
+            mint,
+            amount,
+            recipient,
+            proposer,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Aprobar un retiro pendiente del treasury (solo administradores)
+    pub fn approve_treasury_withdrawal(ctx: Context<ApproveTreasuryWithdrawal>) -> Result<()> {
+        let admin = ctx.accounts.admin.key();
+        ctx.accounts.treasury.check_admin(&admin)?;
+
+        let withdrawal = &mut ctx.accounts.withdrawal;
+        require!(withdrawal.executed_at.is_none(), ErrorCode::WithdrawalAlreadyExecuted);
+        require!(!withdrawal.approvals.contains(&admin), ErrorCode::AlreadyApproved);
+        withdrawal.approvals.push(admin);
+
+        emit!(TreasuryWithdrawalApproved {
+            withdrawal: withdrawal.key(),
+            index: withdrawal.index,
+            admin,
+            approvals: withdrawal.approvals.len() as u8,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Ejecutar un retiro del treasury con al menos `threshold` aprobaciones
+    pub fn withdraw_treasury(ctx: Context<WithdrawTreasury>) -> Result<()> {
+        let treasury = &ctx.accounts.treasury;
+        let withdrawal = &mut ctx.accounts.withdrawal;
+        treasury.check_admin(&ctx.accounts.admin.key())?;
+        require!(withdrawal.executed_at.is_none(), ErrorCode::WithdrawalAlreadyExecuted);
+        require!(
+            withdrawal.approvals.len() >= treasury.threshold as usize,
+            ErrorCode::ApprovalThresholdNotMet
+        );
+
+        pay_from_treasury(
+            treasury,
+            ctx.bumps.treasury,
+            withdrawal.mint,
+            withdrawal.amount,
+            &ctx.accounts.recipient,
+            ctx.accounts.treasury_token_account.as_ref(),
+            ctx.accounts.recipient_token_account.as_mut(),
+            ctx.accounts.mint.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+        )?;
+
+        let now = Clock::get()?.unix_timestamp;
+        withdrawal.executed_at = Some(now);
+
+        emit!(TreasuryWithdrawn {
+            withdrawal: withdrawal.key(),
+            index: withdrawal.index,
+            mint: withdrawal.mint,
+            amount: withdrawal.amount,
+            recipient: withdrawal.recipient,
+            executed_by: ctx.accounts.admin.key(),
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Crear el consejo de administración (solo autoridad, una vez)
+    /// A partir de entonces la configuración de la plataforma solo cambia por propuestas aprobadas
+    pub fn initialize_admin_council(
+        ctx: Context<InitializeAdminCouncil>,
+        members: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        require!(
+            is_valid_admin_set(&members, threshold, MAX_COUNCIL_MEMBERS),
+            ErrorCode::InvalidCouncilMembers
+        );
+
+        let council = &mut ctx.accounts.council;
+        council.members = members;
+        council.threshold = threshold;
+        council.proposal_count = 0;
+        ctx.accounts.platform.admin_council = Some(council.key());
+
+        emit!(AdminCouncilInitialized {
+            council: council.key(),
+            members: council.members.clone(),
+            threshold,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Proponer una acción privilegiada (solo miembros); cuenta como primera aprobación
+    pub fn propose_admin_action(
+        ctx: Context<ProposeAdminAction>,
+        action: AdminAction,
+        lifetime_seconds: Option<i64>,
+    ) -> Result<()> {
+        let proposer = ctx.accounts.proposer.key();
+        ctx.accounts.council.check_member(&proposer)?;
+        action.validate()?;
+
+        let lifetime = lifetime_seconds.unwrap_or(DEFAULT_PROPOSAL_LIFETIME_SECONDS);
+        require!(
+            lifetime > 0 && lifetime <= MAX_PROPOSAL_LIFETIME_SECONDS,
+            ErrorCode::InvalidProposalLifetime
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let council = &mut ctx.accounts.council;
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.index = council.proposal_count;
+        proposal.proposer = proposer;
+        proposal.action = action;
+        proposal.approvals = vec![proposer];
+        proposal.created_at = now;
+        proposal.expires_at = now + lifetime;
+        proposal.executed_at = None;
+        council.proposal_count += 1;
+
+        emit!(AdminActionProposed {
+            proposal: proposal.key(),
+            index: proposal.index,
+            action: proposal.action.clone(),
+            proposer,
+            expires_at: proposal.expires_at,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Aprobar una propuesta vigente (solo miembros)
+    pub fn approve_admin_action(ctx: Context<ApproveAdminAction>) -> Result<()> {
+        let member = ctx.accounts.member.key();
+        ctx.accounts.council.check_member(&member)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.check_pending(now)?;
+        require!(!proposal.approvals.contains(&member), ErrorCode::AlreadyApproved);
+        proposal.approvals.push(member);
+
+        emit!(AdminActionApproved {
+            proposal: proposal.key(),
+            index: proposal.index,
+            member,
+            approvals: proposal.approvals.len() as u8,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Ejecutar una propuesta con al menos `threshold` aprobaciones de miembros actuales
+    /// Cada acción requiere sus cuentas opcionales (treasury, perfil, destinatario y tokens)
+    pub fn execute_admin_action(ctx: Context<ExecuteAdminAction>) -> Result<()> {
+        let executor = ctx.accounts.executor.key();
+        let council_key = ctx.accounts.council.key();
+        ctx.accounts.council.check_member(&executor)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let proposal = &ctx.accounts.proposal;
+        proposal.check_pending(now)?;
+        require!(
+            ctx.accounts.council.approval_count(&proposal.approvals) >= ctx.accounts.council.threshold as usize,
+            ErrorCode::ApprovalThresholdNotMet
+        );
+
+        let action = proposal.action.clone();
+        action.validate()?;
+
+        match action {
+            AdminAction::WithdrawTreasury { mint, amount, recipient } => {
+                let (Some(treasury), Some(recipient_info), Some(treasury_bump)) = (
+                    ctx.accounts.treasury.as_ref(),
+                    ctx.accounts.recipient.as_ref(),
+                    ctx.bumps.treasury,
+                ) else {
+                    return err!(ErrorCode::MissingAdminActionAccount);
+                };
+                require!(recipient_info.key() == recipient, ErrorCode::MissingAdminActionAccount);
+
+                pay_from_treasury(
+                    treasury,
+                    treasury_bump,
+                    mint,
+                    amount,
+                    recipient_info,
+                    ctx.accounts.treasury_token_account.as_ref(),
+                    ctx.accounts.recipient_token_account.as_mut(),
+                    ctx.accounts.mint.as_ref(),
+                    ctx.accounts.token_program.as_ref(),
+                )?;
+            }
+            _ if action.is_timelocked() => {
+                // Los cambios sensibles quedan en cola para que los usuarios puedan salir antes
+                let proposal_key = ctx.accounts.proposal.key();
+                let pending_change = ctx
+                    .accounts
+                    .pending_change
+                    .as_mut()
+                    .ok_or(ErrorCode::MissingAdminActionAccount)?;
+                pending_change.proposal = proposal_key;
+                pending_change.action = action.clone();
+                pending_change.scheduled_by = executor;
+                pending_change.scheduled_at = now;
+                pending_change.eta = now + ctx.accounts.platform.timelock_delay_seconds;
+
+                emit!(ChangeScheduled {
+                    pending_change: pending_change.key(),
+                    proposal: proposal_key,
+                    action: action.clone(),
+                    eta: pending_change.eta,
+                    timestamp: now,
+                });
+            }
+            _ => {
+                let treasury = ctx.accounts.treasury.as_deref_mut();
+                apply_config_change(
+                    &action,
+                    &mut ctx.accounts.platform,
+                    &mut ctx.accounts.council,
+                    treasury,
+                    None,
+                    None,
+                    council_key,
+                    now,
+                )?;
+            }
+        }
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.executed_at = Some(now);
+
+        emit!(AdminActionExecuted {
+            proposal: proposal.key(),
+            index: proposal.index,
+            action,
+            executed_by: executor,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Aplicar un cambio programado una vez cumplida su espera (cualquiera puede ejecutarlo)
+    pub fn execute_pending_change(ctx: Context<ExecutePendingChange>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let pending_change = &ctx.accounts.pending_change;
+        require!(now >= pending_change.eta, ErrorCode::ChangeStillLocked);
+
+        let action = pending_change.action.clone();
+        action.validate()?;
+        let council_key = ctx.accounts.council.key();
+        let treasury = ctx.accounts.treasury.as_deref_mut();
+        let fee_schedule = ctx.accounts.fee_schedule.as_deref_mut();
+        let supported_mint = ctx.accounts.supported_mint.as_deref_mut();
+        apply_config_change(
+            &action,
+            &mut ctx.accounts.platform,
+            &mut ctx.accounts.council,
+            treasury,
+            fee_schedule,
+            supported_mint,
+            council_key,
+            now,
+        )?;
+
+        emit!(ChangeExecuted {
+            pending_change: ctx.accounts.pending_change.key(),
+            proposal: ctx.accounts.pending_change.proposal,
+            action,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Cancelar un cambio programado durante su espera (cualquier miembro del consejo)
+    pub fn cancel_pending_change(ctx: Context<CancelPendingChange>) -> Result<()> {
+        let member = ctx.accounts.member.key();
+        ctx.accounts.council.check_member(&member)?;
+
+        emit!(ChangeCancelled {
+            pending_change: ctx.accounts.pending_change.key(),
+            proposal: ctx.accounts.pending_change.proposal,
+            cancelled_by: member,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Crear el esquema de tarifas con un nivel único basado en la comisión de la plataforma
+    pub fn initialize_fee_schedule(ctx: Context<InitializeFeeSchedule>) -> Result<()> {
+        let maker_fee_bps = ctx.accounts.platform.fee_bps / 2;
+        let fee_schedule = &mut ctx.accounts.fee_schedule;
+        fee_schedule.tiers = vec![FeeTier {
+            min_volume_mxn: 0,
+            min_kyc_level: VerificationLevel::Basic,
+            maker_fee_bps,
+            taker_fee_bps: ctx.accounts.platform.fee_bps - maker_fee_bps,
+        }];
+        fee_schedule.updated_at = Clock::get()?.unix_timestamp;
+
+        emit!(FeeScheduleUpdated {
+            tiers: fee_schedule.tiers.clone(),
+            authority: ctx.accounts.authority.key(),
+            timestamp: fee_schedule.updated_at,
+        });
+        Ok(())
+    }
+
+    /// Reemplazar los niveles de tarifas por volumen y nivel KYC (solo autoridad)
+    pub fn set_fee_schedule(ctx: Context<UpdateFeeSchedule>, tiers: Vec<FeeTier>) -> Result<()> {
+        FeeSchedule::validate_tiers(&tiers)?;
+
+        let fee_schedule = &mut ctx.accounts.fee_schedule;
+        fee_schedule.tiers = tiers;
+        fee_schedule.updated_at = Clock::get()?.unix_timestamp;
+
+        emit!(FeeScheduleUpdated {
+            tiers: fee_schedule.tiers.clone(),
+            authority: ctx.accounts.authority.key(),
+            timestamp: fee_schedule.updated_at,
+        });
+        Ok(())
+    }
+
+    /// Crear un feed de precio MXN (solo autoridad)
+    pub fn initialize_price_feed(
+        ctx: Context<InitializePriceFeed>,
+        feed_id: String,
+        mint: Pubkey,
+        updater: Pubkey,
+    ) -> Result<()> {
+        let price_feed_key = ctx.accounts.price_feed.key();
+        consume_setup_approval(
+            &ctx.accounts.platform,
+            ctx.accounts.council.as_ref(),
+            ctx.accounts.proposal.as_mut(),
+            ctx.accounts.authority.key(),
+            |action| {
+                matches!(
+                    *action,
+                    AdminAction::InitializePriceFeed {
+                        price_feed: approved_feed,
+                        mint: approved_mint,
+                        updater: approved_updater,
+                    } if approved_feed == price_feed_key && approved_mint == mint && approved_updater == updater
+                )
+            },
+        )?;
+
+        let price_feed = &mut ctx.accounts.price_feed;
+        price_feed.feed_id = feed_id;
+        price_feed.mint = mint;
+        price_feed.updater = updater;
+        price_feed.price_mxn = 0;
+        price_feed.updated_at = 0;
+
+        emit!(PriceFeedInitialized {
+            price_feed: price_feed.key(),
+            feed_id: price_feed.feed_id.clone(),
+            mint,
+            updater,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Publicar un nuevo precio en el feed (solo el updater del feed)
+    pub fn update_price_feed(ctx: Context<UpdatePriceFeed>, price_mxn: u64) -> Result<()> {
+        require!(price_mxn > 0, ErrorCode::InvalidAmount);
+
+        let price_feed = &mut ctx.accounts.price_feed;
+        price_feed.price_mxn = price_mxn;
+        price_feed.updated_at = Clock::get()?.unix_timestamp;
+
+        emit!(PriceFeedUpdated {
+            price_feed: price_feed.key(),
+            feed_id: price_feed.feed_id.clone(),
+            mint: price_feed.mint,
+            price_mxn,
+            updater: ctx.accounts.updater.key(),
+            timestamp: price_feed.updated_at,
+        });
+        Ok(())
+    }
+
+    /// Crear un anuncio (inventario de crypto con precio y límites por trade)
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_ad(
+        ctx: Context<CreateAd>,
+        ad_id: String,
+        price_mxn: u64, // Precio en MXN (6 decimales) por unidad entera del token
+        min_trade_mxn: u64,
+        max_trade_mxn: u64,
+        payment_methods: Vec<PaymentMethod>,
+    ) -> Result<()> {
+        ctx.accounts.platform.check_operation(PAUSE_ORDER_CREATION)?;
+        require!(price_mxn > 0, ErrorCode::InvalidAmount);
+        require!(
+            min_trade_mxn > 0 && min_trade_mxn <= max_trade_mxn,
+            ErrorCode::InvalidTradeLimits
+        );
+        require!(
+            !payment_methods.is_empty() && payment_methods.len() <= MAX_AD_PAYMENT_METHODS,
+            ErrorCode::InvalidPaymentMethods
+        );
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.merchant_profile.refresh_kyc(&ctx.accounts.kyc_record, now)?;
+        ctx.accounts.merchant_profile.check_can_trade(now)?;
+        let limits = ctx.accounts.platform.trading_limits(ctx.accounts.merchant_profile.kyc_level);
+        require!(max_trade_mxn <= limits.max_trade_mxn, ErrorCode::ExceedsMaxLimit);
+        check_not_blocked(&ctx.accounts.merchant_blocked)?;
+        ctx.accounts.supported_mint.check_trading()?;
+
+        // Como en create_order: el precio del anuncio debe estar dentro de la banda de referencia
+        ctx.accounts.reference_rate.check_unit_price(
+            price_mxn as u128,
+            ctx.accounts.platform.max_price_deviation_bps,
+            now,
+        )?;
+
+        let ad = &mut ctx.accounts.ad;
+        ad.ad_id = ad_id;
+        ad.merchant = ctx.accounts.merchant.key();
+        ad.mint = ctx.accounts.supported_mint.mint;
+        ad.decimals = ctx.accounts.supported_mint.decimals;
+        ad.price_mxn = price_mxn;
+        ad.min_trade_mxn = min_trade_mxn;
+        ad.max_trade_mxn = max_trade_mxn;
+        ad.payment_methods = payment_methods;
+        ad.vault = ctx.accounts.vault.key();
+        // La cuenta de tokens queda registrada para validar los movimientos posteriores
+        ad.vault_token_account = ctx.accounts.vault_token_account.key();
+        ad.available_amount = 0;
+        ad.locked_amount = 0;
+        ad.trade_count = 0;
+        ad.is_active = true;
+        ad.created_at = now;
+
+        emit!(AdCreated {
+            ad: ad.key(),
+            ad_id: ad.ad_id.clone(),
+            merchant: ad.merchant,
+            mint: ad.mint,
+            price_mxn,
+            min_trade_mxn,
+            max_trade_mxn,
+            timestamp: ad.created_at,
+        });
+        Ok(())
+    }
+
+    /// Actualizar precio, límites o estado de un anuncio
+    pub fn update_ad(
+        ctx: Context<UpdateAd>,
+        price_mxn: u64,
+        min_trade_mxn: u64,
+        max_trade_mxn: u64,
+        is_active: bool,
+    ) -> Result<()> {
+        require!(price_mxn > 0, ErrorCode::InvalidAmount);
+        require!(
+            min_trade_mxn > 0 && min_trade_mxn <= max_trade_mxn,
+            ErrorCode::InvalidTradeLimits
+        );
+        let limits = ctx.accounts.platform.trading_limits(ctx.accounts.merchant_profile.kyc_level);
+        require!(max_trade_mxn <= limits.max_trade_mxn, ErrorCode::ExceedsMaxLimit);
+        ctx.accounts.reference_rate.check_unit_price(
+            price_mxn as u128,
+            ctx.accounts.platform.max_price_deviation_bps,
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        let ad = &mut ctx.accounts.ad;
+        ad.price_mxn = price_mxn;
+        ad.min_trade_mxn = min_trade_mxn;
+        ad.max_trade_mxn = max_trade_mxn;
+        ad.is_active = is_active;
+
+        emit!(AdUpdated {
+            ad: ad.key(),
+            ad_id: ad.ad_id.clone(),
+            merchant: ad.merchant,
+            price_mxn,
+            min_trade_mxn,
+            max_trade_mxn,
+            is_active,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Depositar inventario en la bóveda del anuncio
+    pub fn deposit_to_ad(ctx: Context<DepositToAd>, amount: u64) -> Result<()> {
+        ctx.accounts.platform.check_operation(PAUSE_DEPOSITS)?;
+        require!(amount > 0, ErrorCode::InvalidAmount);
+
+        let ad = &mut ctx.accounts.ad;
+
+        // Inventario efectivamente recibido por la bóveda (el SOL se envuelve como wSOL)
+        let received = deposit_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.merchant,
+            ctx.accounts.merchant_token_account.as_ref(),
+            &mut ctx.accounts.vault_token_account,
+            &ctx.accounts.mint,
+            &ctx.accounts.system_program,
+            amount,
+        )?;
+
+        ad.available_amount = ad.available_amount.checked_add(received).ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(AdInventoryChanged {
+            ad: ad.key(),
+            ad_id: ad.ad_id.clone(),
+            merchant: ad.merchant,
+            deposited: received,
+            withdrawn: 0,
+            available_amount: ad.available_amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Retirar inventario libre (no comprometido en trades) del anuncio
+    pub fn withdraw_from_ad(ctx: Context<WithdrawFromAd>, amount: u64) -> Result<()> {
+        let ad = &mut ctx.accounts.ad;
+
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(amount <= ad.available_amount, ErrorCode::InsufficientInventory);
+
+        let vault_bump = ctx.bumps.vault;
+        let ad_key = ad.key();
+        let vault_seeds = &[
+            b"ad_vault",
+            ad_key.as_ref(),
+            &[vault_bump],
+        ];
+        let signer_seeds = &[&vault_seeds[..]];
+
+        pay_out_vault(
+            &ctx.accounts.vault,
+            &ctx.accounts.vault_token_account,
+            ctx.accounts.unwrap_token_account.as_ref(),
+            &ctx.accounts.mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+            ctx.accounts.merchant.to_account_info(),
+            signer_seeds,
+            &[(
+                ctx.accounts.merchant.to_account_info(),
+                ctx.accounts.merchant_token_account.as_ref(),
+                amount,
+            )],
+        )?;
+
+        ad.available_amount -= amount;
+
+        emit!(AdInventoryChanged {
+            ad: ad.key(),
+            ad_id: ad.ad_id.clone(),
+            merchant: ad.merchant,
+            deposited: 0,
+            withdrawn: amount,
+            available_amount: ad.available_amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Abrir un trade contra un anuncio (buyer reserva parte del inventario)
+    pub fn open_trade(
+        ctx: Context<OpenTrade>,
+        amount_mxn: u64,
+        payment_method: PaymentMethod,
+    ) -> Result<()> {
+        ctx.accounts.platform.check_operation(PAUSE_ORDER_ACCEPTANCE | PAUSE_DEPOSITS)?;
+        let ad = &mut ctx.accounts.ad;
+
+        require!(ad.is_active, ErrorCode::AdNotActive);
+        require!(
+            amount_mxn >= ad.min_trade_mxn && amount_mxn <= ad.max_trade_mxn,
+            ErrorCode::TradeOutsideAdLimits
+        );
+        require!(ad.payment_methods.contains(&payment_method), ErrorCode::InvalidPaymentMethods);
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.buyer_profile.refresh_kyc(&ctx.accounts.kyc_record, now)?;
+        ctx.accounts.buyer_profile.check_can_trade(now)?;
+        ctx.accounts.merchant_profile.check_can_trade(now)?;
+        check_not_blocked(&ctx.accounts.buyer_blocked)?;
+        check_not_blocked(&ctx.accounts.merchant_blocked)?;
+        require!(ad.merchant != ctx.accounts.buyer.key(), ErrorCode::CannotTradeWithSelf);
+
+        // Convertir MXN a unidades del token según el precio del anuncio
+        let amount = (amount_mxn as u128)
+            .checked_mul(10u128.pow(ad.decimals as u32))
+            .and_then(|v| v.checked_div(ad.price_mxn as u128))
+            .and_then(|v| u64::try_from(v).ok())
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        ctx.accounts.supported_mint.check_trade_amount(amount)?;
+
+        // Tarifas según el volumen previo y nivel KYC de maker (merchant) y taker (buyer)
+        let (maker_fee_bps, taker_fee_bps) = ctx.accounts.fee_schedule.rates(
+            &ctx.accounts.merchant_profile,
+            &ctx.accounts.buyer_profile,
+            Clock::get()?.unix_timestamp,
+        );
+        let fee_amount = ctx.accounts.supported_mint.fee_amount(amount, maker_fee_bps + taker_fee_bps)?;
+
+        // El inventario cubre el monto más la parte de la comisión que aporta el merchant
+        let fee_bearer = ctx.accounts.platform.default_fee_bearer;
+        let locked_amount = amount
+            .checked_add(fee_bearer.seller_share(fee_amount))
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(locked_amount <= ad.available_amount, ErrorCode::InsufficientInventory);
+
+        // Límites por trade y acumulados de buyer y merchant según su nivel KYC (AML)
+        let buyer_limits = ctx.accounts.platform.trading_limits(ctx.accounts.buyer_profile.kyc_level);
+        ctx.accounts.buyer_profile.record_volume(amount_mxn, &buyer_limits, now)?;
+        let merchant_limits = ctx.accounts.platform.trading_limits(ctx.accounts.merchant_profile.kyc_level);
+        ctx.accounts.merchant_profile.record_volume(amount_mxn, &merchant_limits, now)?;
+
+        ad.available_amount -= locked_amount;
+        ad.locked_amount += locked_amount;
+        ad.trade_count += 1;
+
+        let trade = &mut ctx.accounts.trade;
+        trade.ad = ad.key();
+        trade.trade_index = ad.trade_count - 1;
+        trade.merchant = ad.merchant;
+        trade.buyer = ctx.accounts.buyer.key();
+        trade.amount = amount;
+        trade.amount_mxn = amount_mxn;
+        trade.payment_method = payment_method;
+        trade.maker_fee_bps = maker_fee_bps;
+        trade.taker_fee_bps = taker_fee_bps;
+        trade.fee_bearer = fee_bearer;
+        trade.fee_amount = fee_amount;
+        trade.status = TradeStatus::Open;
+        trade.stp_transaction_id = None;
+        trade.created_at = now;
+        trade.payment_confirmed_at = None;
+        trade.completed_at = None;
+
+        emit!(TradeOpened {
+            trade: trade.key(),
+            ad: trade.ad,
+            trade_index: trade.trade_index,
+            merchant: trade.merchant,
+            buyer: trade.buyer,
+            amount,
+            amount_mxn,
+            payment_method: trade.payment_method.clone(),
+            maker_fee_bps,
+            taker_fee_bps,
+            fee_bearer,
+            fee_amount,
+            timestamp: trade.created_at,
+        });
+        Ok(())
+    }
+
+    /// Confirmar pago fiat de un trade (buyer confirma que envió MXN)
+    pub fn confirm_trade_payment(
+        ctx: Context<ConfirmTradePayment>,
+        stp_transaction_id: String,
+    ) -> Result<()> {
+        let trade = &mut ctx.accounts.trade;
+
+        require!(trade.status == TradeStatus::Open, ErrorCode::InvalidOrderStatus);
+        require!(trade.buyer == ctx.accounts.buyer.key(), ErrorCode::UnauthorizedBuyer);
+
+        let now = Clock::get()?.unix_timestamp;
+        trade.stp_transaction_id = Some(stp_transaction_id.clone());
+        trade.status = TradeStatus::PaymentConfirmed;
+        trade.payment_confirmed_at = Some(now);
+
+        emit!(TradePaymentConfirmed {
+            trade: trade.key(),
+            ad: trade.ad,
+            trade_index: trade.trade_index,
+            buyer: trade.buyer,
+            stp_transaction_id,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Liberar los fondos de un trade al buyer
+    /// Puede llamarlo el merchant, el oráculo o cualquiera después de timeout
+    pub fn release_trade(ctx: Context<ReleaseTrade>) -> Result<()> {
+        ctx.accounts.platform.check_operation(PAUSE_RELEASES)?;
+        let trade = &mut ctx.accounts.trade;
+        let ad = &mut ctx.accounts.ad;
+        let platform = &ctx.accounts.platform;
+
+        require!(trade.status == TradeStatus::PaymentConfirmed, ErrorCode::InvalidOrderStatus);
+
+        let caller = ctx.accounts.authority.key();
+        let is_merchant = caller == ad.merchant;
+        let is_oracle = caller == platform.authority;
+        let is_timeout = if let Some(confirmed_at) = trade.payment_confirmed_at {
+            Clock::get()?.unix_timestamp - confirmed_at > 86400 // 24 horas
+        } else {
+            false
+        };
+
+        require!(is_merchant || is_oracle || is_timeout, ErrorCode::Unauthorized);
+
+        // Comisión fijada al abrir el trade (maker + taker); el buyer paga la parte
+        // que no aportó el merchant desde el inventario
+        let fee_bps = trade.maker_fee_bps + trade.taker_fee_bps;
+        let fee_amount = trade.fee_amount;
+        let locked_amount = trade.locked_amount()?;
+        let buyer_amount = locked_amount - fee_amount;
+
+        let vault_bump = ctx.bumps.vault;
+        let ad_key = ad.key();
+        let vault_seeds = &[
+            b"ad_vault",
+            ad_key.as_ref(),
+            &[vault_bump],
+        ];
+        let signer_seeds = &[&vault_seeds[..]];
+
+        // Parte de la comisión para los referidores del merchant (maker) y del buyer (taker)
+        let rewards = referral_rewards(
+            platform,
+            fee_amount,
+            [
+                (&ctx.accounts.merchant_profile, trade.maker_fee_bps, ctx.accounts.merchant_referral.as_mut()),
+                (&ctx.accounts.buyer_profile, trade.taker_fee_bps, ctx.accounts.buyer_referral.as_mut()),
+            ],
+        )?;
+        let referral_amount: u64 = rewards.iter().map(|reward| reward.amount).sum();
+
+        // SOL: la recompensa se paga en lamports a la ReferralAccount al desenvolver
+        // Tokens: se transfiere al vault de referidos antes de repartir el resto
+        let mut referral_payouts = Vec::new();
+        credit_referral_rewards(rewards, ad.mint, trade.key(), |referral, amount| {
+            if ad.is_native() {
+                referral_payouts.push((referral.to_account_info(), None, amount));
+                return Ok(amount);
+            }
+            let Some(referral_vault) = ctx.accounts.referral_vault.as_mut() else {
+                return err!(ErrorCode::MissingTokenAccounts);
+            };
+            transfer_tokens(
+                &ctx.accounts.token_program,
+                &ctx.accounts.vault_token_account,
+                referral_vault,
+                &ctx.accounts.mint,
+                ctx.accounts.vault.to_account_info(),
+                signer_seeds,
+                amount,
+            )
+        })?;
+
+        // Transferir al buyer y la comisión (sin recompensas) al treasury
+        let mut payouts = vec![
+            (
+                ctx.accounts.buyer.to_account_info(),
+                ctx.accounts.buyer_token_account.as_ref(),
+                buyer_amount,
+            ),
+            (
+                ctx.accounts.treasury.to_account_info(),
+                ctx.accounts.treasury_token_account.as_ref(),
+                fee_amount - referral_amount,
+            ),
+        ];
+        payouts.extend(referral_payouts);
+
+        pay_out_vault(
+            &ctx.accounts.vault,
+            &ctx.accounts.vault_token_account,
+            ctx.accounts.unwrap_token_account.as_ref(),
+            &ctx.accounts.mint,
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+            ctx.accounts.authority.to_account_info(),
+            signer_seeds,
+            &payouts,
+        )?;
+
+        ad.locked_amount -= locked_amount;
+
+        trade.status = TradeStatus::Completed;
+        trade.completed_at = Some(Clock::get()?.unix_timestamp);
+
+        // Estadísticas globales, por mint y del día
+        ctx.accounts.platform.record_settlement(trade.amount_mxn)?;
+        ctx.accounts
+            .supported_mint
+            .record_settlement(trade.amount, trade.amount_mxn, fee_amount)?;
+        ctx.accounts.daily_stats.record_settlement(
+            trade.amount_mxn,
+            fee_amount_mxn(trade.amount_mxn, fee_amount, trade.amount)?,
+        )?;
+
+        // Actualizar perfiles
+        let merchant_profile = &mut ctx.accounts.merchant_profile;
+        merchant_profile.total_trades += 1;
+        merchant_profile.successful_trades += 1;
+
+        let buyer_profile = &mut ctx.accounts.buyer_profile;
+        buyer_profile.total_trades += 1;
+        buyer_profile.successful_trades += 1;
+
+        emit!(TradeReleased {
+            trade: trade.key(),
+            ad: trade.ad,
+            trade_index: trade.trade_index,
+            merchant: trade.merchant,
+            buyer: trade.buyer,
+            mint: ad.mint,
+            amount_mxn: trade.amount_mxn,
+            buyer_amount,
+            fee_amount,
+            fee_bps,
+            referral_amount,
+            released_by: caller,
+            timestamp: trade.completed_at.unwrap_or_default(),
+        });
+        Ok(())
+    }
+
+    /// Cancelar un trade y devolver su monto al inventario del anuncio
+    /// El buyer puede cancelar antes de pagar; el merchant solo si el trade expiró
+    pub fn cancel_trade(ctx: Context<CancelTrade>) -> Result<()> {
+        let trade = &mut ctx.accounts.trade;
+        let ad = &mut ctx.accounts.ad;
+        let platform = &ctx.accounts.platform;
+
+        let caller = ctx.accounts.authority.key();
+        let is_buyer = caller == trade.buyer;
+        let is_oracle = caller == platform.authority;
+        let is_expired = Clock::get()?.unix_timestamp - trade.created_at > TRADE_PAYMENT_WINDOW_SECONDS;
+
+        match trade.status {
+            TradeStatus::Open => {
+                require!(
+                    is_buyer || is_oracle || (caller == ad.merchant && is_expired),
+                    ErrorCode::Unauthorized
+                );
+            },
+            // Con pago confirmado solo el oráculo puede revertir el trade
+            TradeStatus::PaymentConfirmed => {
+                require!(is_oracle, ErrorCode::Unauthorized);
+            },
+            _ => return err!(ErrorCode::InvalidOrderStatus),
+        }
+
+        let locked_amount = trade.locked_amount()?;
+        ad.locked_amount -= locked_amount;
+        ad.available_amount += locked_amount;
+
+        // El volumen registrado al abrir el trade deja de contar para ambos
+        ctx.accounts.buyer_profile.release_volume(trade.amount_mxn, trade.created_at);
+        ctx.accounts.merchant_profile.release_volume(trade.amount_mxn, trade.created_at);
+
+        trade.status = TradeStatus::Cancelled;
+        trade.completed_at = Some(Clock::get()?.unix_timestamp);
+        ctx.accounts.daily_stats.record_cancellation()?;
+
+        emit!(TradeCancelled {
+            trade: trade.key(),
+            ad: trade.ad,
+            trade_index: trade.trade_index,
+            cancelled_by: caller,
+            returned_amount: locked_amount,
+            timestamp: trade.completed_at.unwrap_or_default(),
+        });
+        Ok(())
+    }
+}
+
+// ============================================================================
+// CONTEXTOS DE INSTRUCCIONES
+// ============================================================================
+
+#[derive(Accounts)]
+pub struct InitializePlatform<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Platform::INIT_SPACE,
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateUserProfile<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = 8 + UserProfile::INIT_SPACE,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: KycRecord del programa kyc_nft, validado en UserProfile::refresh_kyc
+    #[account(
+        seeds = [b"kyc_record", user.key().as_ref()],
+        bump,
+        seeds::program = kyc_nft::ID
+    )]
+    pub kyc_record: UncheckedAccount<'info>,
+
+    /// CHECK: Entrada del blocklist del usuario, validada en check_not_blocked
+    #[account(
+        seeds = [b"blocked_address", user.key().as_ref()],
+        bump
+    )]
+    pub user_blocked: UncheckedAccount<'info>,
+
+    /// Perfil de quien refirió al usuario (opcional)
+    pub referrer_profile: Option<Account<'info, UserProfile>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: String)]
+pub struct CreateOrder<'info> {
+    #[account(
+        init,
+        payer = maker,
+        space = 8 + Order::INIT_SPACE,
+        seeds = [b"order", order_id.as_bytes()],
+        bump
+    )]
+    pub order: Account<'info, Order>,
+
+    /// CHECK: This is a PDA used as escrow wallet, validated by seeds
+    #[account(
+        mut,
+        seeds = [b"escrow", order.key().as_ref()],
+        bump
+    )]
+    pub escrow: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_profile: Account<'info, UserProfile>,
+
+    /// Seller en órdenes de venta, buyer en órdenes de compra
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    /// CHECK: KycRecord del programa kyc_nft, validado en UserProfile::refresh_kyc
+    #[account(
+        seeds = [b"kyc_record", maker.key().as_ref()],
+        bump,
+        seeds::program = kyc_nft::ID
+    )]
+    pub kyc_record: UncheckedAccount<'info>,
+
+    /// CHECK: Entrada del blocklist del maker, validada en check_not_blocked
+    #[account(
+        seeds = [b"blocked_address", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_blocked: UncheckedAccount<'info>,
+
+    pub supported_mint: Account<'info, SupportedMint>,
+
+    /// Solo requeridas para pre-fondear; maker_token_account no aplica a SOL
+    #[account(mut)]
+    pub maker_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub escrow_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = supported_mint.mint @ ErrorCode::InvalidMint)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
+    /// Solo requerido para órdenes flotantes
+    pub price_feed: Option<Account<'info, PriceFeed>>,
+
+    /// Solo requerido para pre-fondear con comisión del seller (seller o split)
+    #[account(
+        seeds = [b"fee_schedule"],
+        bump
+    )]
+    pub fee_schedule: Option<Account<'info, FeeSchedule>>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(
+        seeds = [b"reference_rate", supported_mint.mint.as_ref()],
+        bump
+    )]
+    pub reference_rate: Account<'info, ReferenceRate>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptOrder<'info> {
+    #[account(mut)]
+    pub order: Account<'info, Order>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", buyer.key().as_ref()],
+        bump
+    )]
+    pub buyer_profile: Account<'info, UserProfile>,
+
+    /// Perfil del maker (seller), para su tarifa
+    #[account(
+        seeds = [b"user_profile", order.maker().as_ref()],
+        bump
+    )]
+    pub maker_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    /// CHECK: KycRecord del programa kyc_nft, validado en UserProfile::refresh_kyc
+    #[account(
+        seeds = [b"kyc_record", buyer.key().as_ref()],
+        bump,
+        seeds::program = kyc_nft::ID
+    )]
+    pub kyc_record: UncheckedAccount<'info>,
+
+    /// CHECK: Entrada del blocklist del buyer, validada en check_not_blocked
+    #[account(
+        seeds = [b"blocked_address", buyer.key().as_ref()],
+        bump
+    )]
+    pub buyer_blocked: UncheckedAccount<'info>,
+
+    /// CHECK: Entrada del blocklist del maker, validada en check_not_blocked
+    #[account(
+        seeds = [b"blocked_address", order.maker().as_ref()],
+        bump
+    )]
+    pub maker_blocked: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"supported_mint", order.mint.as_ref()],
+        bump
+    )]
+    pub supported_mint: Account<'info, SupportedMint>,
+
+    /// Solo requerido para órdenes flotantes
+    pub price_feed: Option<Account<'info, PriceFeed>>,
+
+    /// Solo requerida para órdenes flotantes
+    #[account(
+        seeds = [b"reference_rate", order.mint.as_ref()],
+        bump
+    )]
+    pub reference_rate: Option<Account<'info, ReferenceRate>>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(
+        seeds = [b"fee_schedule"],
+        bump
+    )]
+    pub fee_schedule: Account<'info, FeeSchedule>,
+}
+
+#[derive(Accounts)]
+pub struct FillOrder<'info> {
+    #[account(mut)]
+    pub order: Account<'info, Order>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", order.key().as_ref()],
+        bump
+    )]
+    pub escrow: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", seller.key().as_ref()],
+        bump
+    )]
+    pub seller_profile: Account<'info, UserProfile>,
+
+    /// Perfil del maker (buyer), para su tarifa
+    #[account(
+        seeds = [b"user_profile", order.maker().as_ref()],
+        bump
+    )]
+    pub maker_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    /// CHECK: KycRecord del programa kyc_nft, validado en UserProfile::refresh_kyc
+    #[account(
+        seeds = [b"kyc_record", seller.key().as_ref()],
+        bump,
+        seeds::program = kyc_nft::ID
+    )]
+    pub kyc_record: UncheckedAccount<'info>,
+
+    /// CHECK: Entrada del blocklist del seller, validada en check_not_blocked
+    #[account(
+        seeds = [b"blocked_address", seller.key().as_ref()],
+        bump
+    )]
+    pub seller_blocked: UncheckedAccount<'info>,
+
+    /// CHECK: Entrada del blocklist del maker, validada en check_not_blocked
+    #[account(
+        seeds = [b"blocked_address", order.maker().as_ref()],
+        bump
+    )]
+    pub maker_blocked: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"supported_mint", order.mint.as_ref()],
+        bump
+    )]
+    pub supported_mint: Account<'info, SupportedMint>,
+
+    /// Solo requerida para tokens SPL; el SOL se envuelve desde la wallet del seller
+    #[account(mut, constraint = seller_token_account.mint == order.mint @ ErrorCode::InvalidMint)]
+    pub seller_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = escrow_token_account.mint == order.mint @ ErrorCode::InvalidMint,
+        constraint = escrow_token_account.owner == escrow.key() @ ErrorCode::InvalidEscrowTokenAccount
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = order.mint @ ErrorCode::InvalidMint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(
+        seeds = [b"fee_schedule"],
+        bump
+    )]
+    pub fee_schedule: Account<'info, FeeSchedule>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelUnfundedOrder<'info> {
+    #[account(mut)]
+    pub order: Account<'info, Order>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", order.maker().as_ref()],
+        bump
+    )]
+    pub maker_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = 8 + DailyStats::INIT_SPACE,
+        seeds = [b"daily_stats", DailyStats::current_day()?.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub daily_stats: Account<'info, DailyStats>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositToEscrow<'info> {
+    #[account(mut)]
+    pub order: Account<'info, Order>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", order.key().as_ref()],
+        bump
+    )]
+    pub escrow: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"supported_mint", order.mint.as_ref()],
+        bump
+    )]
+    pub supported_mint: Account<'info, SupportedMint>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform: Account<'info, Platform>,
+
+    /// Solo requerida para tokens SPL; el SOL se envuelve desde la wallet del seller
+    #[account(mut, constraint = seller_token_account.mint == order.mint @ ErrorCode::InvalidMint)]
+    pub seller_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = escrow_token_account.mint == order.mint @ ErrorCode::InvalidMint,
+        constraint = escrow_token_account.owner == escrow.key() @ ErrorCode::InvalidEscrowTokenAccount
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    #[account(address = order.mint @ ErrorCode::InvalidMint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ConfirmFiatPayment<'info> {
+    #[account(mut)]
+    pub order: Account<'info, Order>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseFunds<'info> {
+    #[account(mut)]
+    pub order: Account<'info, Order>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", order.key().as_ref()],
+        bump
+    )]
+    pub escrow: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(
+        mut,
+        seeds = [b"supported_mint", order.mint.as_ref()],
+        bump
+    )]
+    pub supported_mint: Account<'info, SupportedMint>,
+
+    #[account(
+        mut,
+        constraint = escrow_token_account.mint == order.mint @ ErrorCode::InvalidMint,
+        constraint = escrow_token_account.owner == escrow.key() @ ErrorCode::InvalidEscrowTokenAccount
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Seller, recibe la renta de la cuenta wSOL del escrow
+    #[account(mut, address = order.seller)]
+    pub seller: AccountInfo<'info>,
+
+    /// CHECK: Buyer que recibe los fondos, validado contra la orden
+    #[account(mut, constraint = order.buyer == Some(buyer.key()) @ ErrorCode::UnauthorizedBuyer)]
+    pub buyer: AccountInfo<'info>,
+
+    /// Treasury de la plataforma (PDA del programa)
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    /// Solo requeridas para tokens SPL; el SOL se paga desenvuelto a las wallets
+    #[account(
+        mut,
+        constraint = buyer_token_account.mint == order.mint @ ErrorCode::InvalidMint,
+        constraint = buyer_token_account.owner == buyer.key() @ ErrorCode::InvalidRecipientTokenAccount
+    )]
+    pub buyer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// ATA del treasury para el mint de la orden
+    #[account(
+        mut,
+        constraint = treasury_token_account.mint == order.mint @ ErrorCode::InvalidMint,
+        constraint = treasury_token_account.key()
+            == get_associated_token_address_with_program_id(&treasury.key(), &order.mint, &token_program.key())
+            @ ErrorCode::InvalidRecipientTokenAccount
+    )]
+    pub treasury_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", order.seller.as_ref()],
+        bump
+    )]
+    pub seller_profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", order.buyer.unwrap().as_ref()],
+        bump
+    )]
+    pub buyer_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + DailyStats::INIT_SPACE,
+        seeds = [b"daily_stats", DailyStats::current_day()?.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub daily_stats: Account<'info, DailyStats>,
+
+    /// Solo requeridas si el participante tiene referidor
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + ReferralAccount::INIT_SPACE,
+        seeds = [b"referral", seller_profile.referrer.unwrap_or_default().as_ref(), order.mint.as_ref()],
+        bump
+    )]
+    pub seller_referral: Option<Account<'info, ReferralAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + ReferralAccount::INIT_SPACE,
+        seeds = [b"referral", buyer_profile.referrer.unwrap_or_default().as_ref(), order.mint.as_ref()],
+        bump
+    )]
+    pub buyer_referral: Option<Account<'info, ReferralAccount>>,
+
+    /// Vault de recompensas de referidos, solo para tokens SPL
+    #[account(
+        mut,
+        seeds = [b"referral_vault", order.mint.as_ref()],
+        bump
+    )]
+    pub referral_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = order.mint @ ErrorCode::InvalidMint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct OpenDispute<'info> {
+    #[account(
+        init,
+        payer = initiator,
+        space = 8 + Dispute::INIT_SPACE,
+        seeds = [b"dispute", order.key().as_ref()],
+        bump
+    )]
+    pub dispute: Account<'info, Dispute>,
+
+    #[account(mut)]
+    pub order: Account<'info, Order>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", order.seller.as_ref()],
+        bump
+    )]
+    pub seller_profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", order.buyer.unwrap().as_ref()],
+        bump
+    )]
+    pub buyer_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub initiator: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = initiator,
+        space = 8 + DailyStats::INIT_SPACE,
+        seeds = [b"daily_stats", DailyStats::current_day()?.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub daily_stats: Account<'info, DailyStats>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveDispute<'info> {
+    #[account(
+        mut,
+        seeds = [b"dispute", order.key().as_ref()],
+        bump
+    )]
+    pub dispute: Account<'info, Dispute>,
+
+    #[account(mut)]
+    pub order: Account<'info, Order>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", order.key().as_ref()],
+        bump
+    )]
+    pub escrow: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(
+        mut,
+        seeds = [b"supported_mint", order.mint.as_ref()],
+        bump
+    )]
+    pub supported_mint: Account<'info, SupportedMint>,
+
+    #[account(
+        mut,
+        constraint = escrow_token_account.mint == order.mint @ ErrorCode::InvalidMint,
+        constraint = escrow_token_account.owner == escrow.key() @ ErrorCode::InvalidEscrowTokenAccount
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Seller, recibe el reembolso y la renta de la cuenta wSOL del escrow
+    #[account(mut, address = order.seller)]
+    pub seller: AccountInfo<'info>,
+
+    /// CHECK: Buyer que recibe los fondos, validado contra la orden
+    #[account(mut, constraint = order.buyer == Some(buyer.key()) @ ErrorCode::UnauthorizedBuyer)]
+    pub buyer: AccountInfo<'info>,
+
+    /// Treasury de la plataforma (PDA del programa)
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    /// Solo requeridas para tokens SPL
+    #[account(
+        mut,
+        constraint = seller_token_account.mint == order.mint @ ErrorCode::InvalidMint,
+        constraint = seller_token_account.owner == seller.key() @ ErrorCode::InvalidRecipientTokenAccount
+    )]
+    pub seller_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = buyer_token_account.mint == order.mint @ ErrorCode::InvalidMint,
+        constraint = buyer_token_account.owner == buyer.key() @ ErrorCode::InvalidRecipientTokenAccount
+    )]
+    pub buyer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// ATA del treasury para el mint de la orden
+    #[account(
+        mut,
+        constraint = treasury_token_account.mint == order.mint @ ErrorCode::InvalidMint,
+        constraint = treasury_token_account.key()
+            == get_associated_token_address_with_program_id(&treasury.key(), &order.mint, &token_program.key())
+            @ ErrorCode::InvalidRecipientTokenAccount
+    )]
+    pub treasury_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Mutables: un fallo a favor del seller libera el volumen de la orden
+    #[account(
+        mut,
+        seeds = [b"user_profile", order.seller.as_ref()],
+        bump
+    )]
+    pub seller_profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", order.buyer.unwrap_or_default().as_ref()],
+        bump
+    )]
+    pub buyer_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub resolver: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = resolver,
+        space = 8 + DailyStats::INIT_SPACE,
+        seeds = [b"daily_stats", DailyStats::current_day()?.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub daily_stats: Account<'info, DailyStats>,
+
+    /// Solo requeridas si el participante tiene referidor
+    #[account(
+        init_if_needed,
+        payer = resolver,
+        space = 8 + ReferralAccount::INIT_SPACE,
+        seeds = [b"referral", seller_profile.referrer.unwrap_or_default().as_ref(), order.mint.as_ref()],
+        bump
+    )]
+    pub seller_referral: Option<Account<'info, ReferralAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = resolver,
+        space = 8 + ReferralAccount::INIT_SPACE,
+        seeds = [b"referral", buyer_profile.referrer.unwrap_or_default().as_ref(), order.mint.as_ref()],
+        bump
+    )]
+    pub buyer_referral: Option<Account<'info, ReferralAccount>>,
+
+    /// Vault de recompensas de referidos, solo para tokens SPL
+    #[account(
+        mut,
+        seeds = [b"referral_vault", order.mint.as_ref()],
+        bump
+    )]
+    pub referral_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = order.mint @ ErrorCode::InvalidMint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelOrder<'info> {
+    #[account(mut)]
+    pub order: Account<'info, Order>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", order.key().as_ref()],
+        bump
+    )]
+    pub escrow: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_token_account.mint == order.mint @ ErrorCode::InvalidMint,
+        constraint = escrow_token_account.owner == escrow.key() @ ErrorCode::InvalidEscrowTokenAccount
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Solo requerida para tokens SPL
+    #[account(
+        mut,
+        constraint = seller_token_account.mint == order.mint @ ErrorCode::InvalidMint,
+        constraint = seller_token_account.owner == seller.key() @ ErrorCode::InvalidRecipientTokenAccount
+    )]
+    pub seller_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", order.maker().as_ref()],
+        bump
+    )]
+    pub maker_profile: Account<'info, UserProfile>,
+
+    /// Solo requerida si la orden ya fue aceptada o llenada
+    #[account(
+        mut,
+        seeds = [b"user_profile", order.taker().unwrap_or_default().as_ref()],
+        bump
+    )]
+    pub taker_profile: Option<Account<'info, UserProfile>>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = seller,
+        space = 8 + DailyStats::INIT_SPACE,
+        seeds = [b"daily_stats", DailyStats::current_day()?.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub daily_stats: Account<'info, DailyStats>,
+
+    #[account(address = order.mint @ ErrorCode::InvalidMint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveDisputeSplit<'info> {
+    #[account(mut)]
+    pub order: Account<'info, Order>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", order.key().as_ref()],
+        bump
+    )]
+    pub escrow: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(
+        mut,
+        seeds = [b"supported_mint", order.mint.as_ref()],
+        bump
+    )]
+    pub supported_mint: Account<'info, SupportedMint>,
+
+    #[account(
+        mut,
+        constraint = escrow_token_account.mint == order.mint @ ErrorCode::InvalidMint,
+        constraint = escrow_token_account.owner == escrow.key() @ ErrorCode::InvalidEscrowTokenAccount
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Seller que recibe mitad de fondos, validado contra la orden
+    #[account(mut, address = order.seller)]
+    pub seller: AccountInfo<'info>,
+
+    /// CHECK: Buyer que recibe mitad de fondos, validado contra la orden
+    #[account(mut, constraint = order.buyer == Some(buyer.key()) @ ErrorCode::UnauthorizedBuyer)]
+    pub buyer: AccountInfo<'info>,
+
+    /// Treasury de la plataforma (PDA del programa)
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    /// Solo requeridas para tokens SPL
+    #[account(
+        mut,
+        constraint = seller_token_account.mint == order.mint @ ErrorCode::InvalidMint,
+        constraint = seller_token_account.owner == seller.key() @ ErrorCode::InvalidRecipientTokenAccount
+    )]
+    pub seller_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = buyer_token_account.mint == order.mint @ ErrorCode::InvalidMint,
+        constraint = buyer_token_account.owner == buyer.key() @ ErrorCode::InvalidRecipientTokenAccount
+    )]
+    pub buyer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// ATA del treasury para el mint de la orden
+    #[account(
+        mut,
+        constraint = treasury_token_account.mint == order.mint @ ErrorCode::InvalidMint,
+        constraint = treasury_token_account.key()
+            == get_associated_token_address_with_program_id(&treasury.key(), &order.mint, &token_program.key())
+            @ ErrorCode::InvalidRecipientTokenAccount
+    )]
+    pub treasury_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [b"user_profile", order.seller.as_ref()],
+        bump
+    )]
+    pub seller_profile: Account<'info, UserProfile>,
+
+    #[account(
+        seeds = [b"user_profile", order.buyer.unwrap_or_default().as_ref()],
+        bump
+    )]
+    pub buyer_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub resolver: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = resolver,
+        space = 8 + DailyStats::INIT_SPACE,
+        seeds = [b"daily_stats", DailyStats::current_day()?.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub daily_stats: Account<'info, DailyStats>,
+
+    /// Solo requeridas si el participante tiene referidor
+    #[account(
+        init_if_needed,
+        payer = resolver,
+        space = 8 + ReferralAccount::INIT_SPACE,
+        seeds = [b"referral", seller_profile.referrer.unwrap_or_default().as_ref(), order.mint.as_ref()],
+        bump
+    )]
+    pub seller_referral: Option<Account<'info, ReferralAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = resolver,
+        space = 8 + ReferralAccount::INIT_SPACE,
+        seeds = [b"referral", buyer_profile.referrer.unwrap_or_default().as_ref(), order.mint.as_ref()],
+        bump
+    )]
+    pub buyer_referral: Option<Account<'info, ReferralAccount>>,
+
+    /// Vault de recompensas de referidos, solo para tokens SPL
+    #[account(
+        mut,
+        seeds = [b"referral_vault", order.mint.as_ref()],
+        bump
+    )]
+    pub referral_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = order.mint @ ErrorCode::InvalidMint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateOracleStatus<'info> {
+    #[account(mut)]
+    pub order: Account<'info, Order>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform: Account<'info, Platform>,
+
+    pub oracle: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterMint<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + SupportedMint::INIT_SPACE,
+        seeds = [b"supported_mint", mint.key().as_ref()],
+        bump
+    )]
+    pub supported_mint: Account<'info, SupportedMint>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump,
+        has_one = authority
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// Solo con el consejo activo: propuesta aprobada con esta misma alta, se consume al aplicarla
+    #[account(mut)]
+    pub proposal: Option<Account<'info, AdminProposal>>,
+
+    #[account(
+        seeds = [b"admin_council"],
+        bump
+    )]
+    pub council: Option<Account<'info, AdminCouncil>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMint<'info> {
+    #[account(mut)]
+    pub supported_mint: Account<'info, SupportedMint>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump,
+        has_one = authority,
+        constraint = platform.admin_council.is_none() @ ErrorCode::CouncilGoverned
+    )]
+    pub platform: Account<'info, Platform>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeFeeSchedule<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + FeeSchedule::INIT_SPACE,
+        seeds = [b"fee_schedule"],
+        bump
+    )]
+    pub fee_schedule: Account<'info, FeeSchedule>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump,
+        has_one = authority
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateFeeSchedule<'info> {
+    #[account(
+        mut,
+        seeds = [b"fee_schedule"],
+        bump
+    )]
+    pub fee_schedule: Account<'info, FeeSchedule>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump,
+        has_one = authority,
+        constraint = platform.admin_council.is_none() @ ErrorCode::CouncilGoverned
+    )]
+    pub platform: Account<'info, Platform>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeReferenceRate<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ReferenceRate::INIT_SPACE,
+        seeds = [b"reference_rate", supported_mint.mint.as_ref()],
+        bump
+    )]
+    pub reference_rate: Account<'info, ReferenceRate>,
+
+    pub supported_mint: Account<'info, SupportedMint>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump,
+        has_one = authority
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// Solo con el consejo activo: propuesta aprobada con esta misma alta, se consume al aplicarla
+    #[account(mut)]
+    pub proposal: Option<Account<'info, AdminProposal>>,
+
+    #[account(
+        seeds = [b"admin_council"],
+        bump
+    )]
+    pub council: Option<Account<'info, AdminCouncil>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateReferenceRate<'info> {
+    #[account(mut)]
+    pub reference_rate: Account<'info, ReferenceRate>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform: Account<'info, Platform>,
+
+    pub oracle: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePlatformConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump,
+        has_one = authority,
+        constraint = platform.admin_council.is_none() @ ErrorCode::CouncilGoverned
+    )]
+    pub platform: Account<'info, Platform>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateCircuitBreaker<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump,
+        has_one = guardian
+    )]
+    pub platform: Account<'info, Platform>,
+
+    pub guardian: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ModerateUser<'info> {
+    #[account(
+        mut,
+        seeds = [b"user_profile", user_profile.owner.as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump,
+        constraint = platform.is_moderator(&moderator.key()) @ ErrorCode::Unauthorized
+    )]
+    pub platform: Account<'info, Platform>,
+
+    pub moderator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateBlocklist<'info> {
+    #[account(
+        seeds = [b"platform"],
+        bump,
+        constraint = platform.is_moderator(&moderator.key()) @ ErrorCode::Unauthorized
+    )]
+    pub platform: Account<'info, Platform>,
+
+    /// Paga la renta de las entradas nuevas y la recupera al quitarlas
+    #[account(mut)]
+    pub moderator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ManageLegalHold<'info> {
+    #[account(mut)]
+    pub order: Account<'info, Order>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump,
+        constraint = platform.is_moderator(&moderator.key()) @ ErrorCode::Unauthorized
+    )]
+    pub platform: Account<'info, Platform>,
+
+    pub moderator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct TransferFrozenFunds<'info> {
+    #[account(mut)]
+    pub order: Account<'info, Order>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", order.key().as_ref()],
+        bump
+    )]
+    pub escrow: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub platform: Account<'info, Platform>,
+
+    pub authority: Signer<'info>,
+
+    /// Transferencia aprobada por el consejo (`SeizeFrozenFunds`); se consume al ejecutarla
+    #[account(
+        mut,
+        seeds = [b"pending_change", pending_change.proposal.as_ref()],
+        bump,
+        close = scheduled_by
+    )]
+    pub pending_change: Account<'info, PendingChange>,
+
+    /// Quien programó el cambio recupera la renta
+    #[account(mut, address = pending_change.scheduled_by)]
+    pub scheduled_by: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = escrow_token_account.mint == order.mint @ ErrorCode::InvalidMint,
+        constraint = escrow_token_account.owner == escrow.key() @ ErrorCode::InvalidEscrowTokenAccount
+    )]
+    pub escrow_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Seller, recibe la renta de la cuenta wSOL del escrow
+    #[account(mut, address = order.seller)]
+    pub seller: AccountInfo<'info>,
+
+    /// CHECK: Cuenta designada por la orden judicial, validada contra el cambio aprobado
+    #[account(mut)]
+    pub destination: AccountInfo<'info>,
+
+    /// Solo requerida para tokens SPL
+    #[account(
+        mut,
+        constraint = destination_token_account.mint == order.mint @ ErrorCode::InvalidMint,
+        constraint = destination_token_account.owner == destination.key() @ ErrorCode::InvalidRecipientTokenAccount
+    )]
+    pub destination_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = order.mint @ ErrorCode::InvalidMint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMintCircuitBreaker<'info> {
+    #[account(
+        mut,
+        seeds = [b"supported_mint", supported_mint.mint.as_ref()],
+        bump
+    )]
+    pub supported_mint: Account<'info, SupportedMint>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump,
+        has_one = guardian
+    )]
+    pub platform: Account<'info, Platform>,
+
+    pub guardian: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(feed_id: String)]
+pub struct InitializePriceFeed<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + PriceFeed::INIT_SPACE,
+        seeds = [b"price_feed", feed_id.as_bytes()],
+        bump
+    )]
+    pub price_feed: Account<'info, PriceFeed>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump,
+        has_one = authority
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// Solo con el consejo activo: propuesta aprobada con esta misma alta, se consume al aplicarla
+    #[account(mut)]
+    pub proposal: Option<Account<'info, AdminProposal>>,
+
+    #[account(
+        seeds = [b"admin_council"],
+        bump
+    )]
+    pub council: Option<Account<'info, AdminCouncil>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePriceFeed<'info> {
+    #[account(mut, has_one = updater)]
+    pub price_feed: Account<'info, PriceFeed>,
+
+    pub updater: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(ad_id: String)]
+pub struct CreateAd<'info> {
+    #[account(
+        init,
+        payer = merchant,
+        space = 8 + Ad::INIT_SPACE,
+        seeds = [b"ad", ad_id.as_bytes()],
+        bump
+    )]
+    pub ad: Account<'info, Ad>,
+
+    #[account(
+        seeds = [b"ad_vault", ad.key().as_ref()],
+        bump
+    )]
+    pub vault: SystemAccount<'info>,
+
+    /// Cuenta de tokens de la bóveda (wSOL para SOL)
+    #[account(
+        constraint = vault_token_account.mint == supported_mint.mint @ ErrorCode::InvalidMint,
+        constraint = vault_token_account.owner == vault.key() @ ErrorCode::InvalidEscrowTokenAccount
+    )]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub supported_mint: Account<'info, SupportedMint>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", merchant.key().as_ref()],
+        bump
+    )]
+    pub merchant_profile: Account<'info, UserProfile>,
+
+    /// CHECK: KycRecord del programa kyc_nft, validado en UserProfile::refresh_kyc
+    #[account(
+        seeds = [b"kyc_record", merchant.key().as_ref()],
+        bump,
+        seeds::program = kyc_nft::ID
+    )]
+    pub kyc_record: UncheckedAccount<'info>,
+
+    /// CHECK: Entrada del blocklist del merchant, validada en check_not_blocked
+    #[account(
+        seeds = [b"blocked_address", merchant.key().as_ref()],
+        bump
+    )]
+    pub merchant_blocked: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(
+        seeds = [b"reference_rate", supported_mint.mint.as_ref()],
+        bump
+    )]
+    pub reference_rate: Account<'info, ReferenceRate>,
+
+    #[account(mut)]
+    pub merchant: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateAd<'info> {
+    #[account(mut, has_one = merchant)]
+    pub ad: Account<'info, Ad>,
+
+    #[account(
+        seeds = [b"user_profile", merchant.key().as_ref()],
+        bump
+    )]
+    pub merchant_profile: Account<'info, UserProfile>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(
+        seeds = [b"reference_rate", ad.mint.as_ref()],
+        bump
+    )]
+    pub reference_rate: Account<'info, ReferenceRate>,
+
+    pub merchant: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DepositToAd<'info> {
+    #[account(mut, has_one = merchant, has_one = vault_token_account @ ErrorCode::InvalidEscrowTokenAccount)]
+    pub ad: Account<'info, Ad>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform: Account<'info, Platform>,
+
+    /// Solo requerida para anuncios SPL; el SOL se envuelve desde la wallet del merchant
+    #[account(
+        mut,
+        constraint = merchant_token_account.mint == ad.mint @ ErrorCode::InvalidMint
+    )]
+    pub merchant_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub merchant: Signer<'info>,
+
+    #[account(address = ad.mint @ ErrorCode::InvalidMint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFromAd<'info> {
+    #[account(mut, has_one = merchant, has_one = vault_token_account @ ErrorCode::InvalidEscrowTokenAccount)]
+    pub ad: Account<'info, Ad>,
+
+    #[account(
+        mut,
+        seeds = [b"ad_vault", ad.key().as_ref()],
+        bump
+    )]
+    pub vault: SystemAccount<'info>,
+
+    /// Solo requerida para anuncios SPL; el SOL se paga desenvuelto a la wallet
+    #[account(
+        mut,
+        constraint = merchant_token_account.mint == ad.mint @ ErrorCode::InvalidMint
+    )]
+    pub merchant_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Cuenta wSOL temporal para desenvolver, solo requerida para anuncios SOL
+    #[account(
+        init,
+        payer = merchant,
+        seeds = [b"ad_unwrap", ad.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = vault,
+        token::token_program = token_program
+    )]
+    pub unwrap_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub merchant: Signer<'info>,
+
+    #[account(address = ad.mint @ ErrorCode::InvalidMint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct OpenTrade<'info> {
+    #[account(mut)]
+    pub ad: Account<'info, Ad>,
+
+    #[account(
+        init,
+        payer = buyer,
+        space = 8 + Trade::INIT_SPACE,
+        seeds = [b"trade", ad.key().as_ref(), &ad.trade_count.to_le_bytes()],
+        bump
+    )]
+    pub trade: Account<'info, Trade>,
+
+    #[account(
+        seeds = [b"supported_mint", ad.mint.as_ref()],
+        bump
+    )]
+    pub supported_mint: Account<'info, SupportedMint>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", buyer.key().as_ref()],
+        bump
+    )]
+    pub buyer_profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", ad.merchant.as_ref()],
+        bump
+    )]
+    pub merchant_profile: Account<'info, UserProfile>,
+
+    /// CHECK: KycRecord del programa kyc_nft, validado en UserProfile::refresh_kyc
+    #[account(
+        seeds = [b"kyc_record", buyer.key().as_ref()],
+        bump,
+        seeds::program = kyc_nft::ID
+    )]
+    pub kyc_record: UncheckedAccount<'info>,
+
+    /// CHECK: Entrada del blocklist del buyer, validada en check_not_blocked
+    #[account(
+        seeds = [b"blocked_address", buyer.key().as_ref()],
+        bump
+    )]
+    pub buyer_blocked: UncheckedAccount<'info>,
+
+    /// CHECK: Entrada del blocklist del merchant, validada en check_not_blocked
+    #[account(
+        seeds = [b"blocked_address", ad.merchant.as_ref()],
+        bump
+    )]
+    pub merchant_blocked: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"fee_schedule"],
+        bump
+    )]
+    pub fee_schedule: Account<'info, FeeSchedule>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ConfirmTradePayment<'info> {
+    #[account(mut)]
+    pub trade: Account<'info, Trade>,
+
+    pub buyer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseTrade<'info> {
+    #[account(mut, has_one = ad)]
+    pub trade: Account<'info, Trade>,
+
+    #[account(mut, has_one = vault_token_account @ ErrorCode::InvalidEscrowTokenAccount)]
+    pub ad: Account<'info, Ad>,
+
+    #[account(
+        mut,
+        seeds = [b"ad_vault", ad.key().as_ref()],
+        bump
+    )]
+    pub vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(
+        mut,
+        seeds = [b"supported_mint", ad.mint.as_ref()],
+        bump
+    )]
+    pub supported_mint: Account<'info, SupportedMint>,
+
+    /// CHECK: Buyer que recibe los fondos, validado contra el trade
+    #[account(mut, address = trade.buyer)]
+    pub buyer: AccountInfo<'info>,
+
+    /// Treasury de la plataforma (PDA del programa)
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(mut)]
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Cuenta wSOL temporal para desenvolver, solo requerida para anuncios SOL
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"ad_unwrap", ad.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = vault,
+        token::token_program = token_program
+    )]
+    pub unwrap_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Solo requeridas para anuncios SPL; el SOL se paga desenvuelto a las wallets
+    #[account(
+        mut,
+        constraint = buyer_token_account.mint == ad.mint @ ErrorCode::InvalidMint,
+        constraint = buyer_token_account.owner == trade.buyer @ ErrorCode::InvalidRecipientTokenAccount
+    )]
+    pub buyer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// ATA del treasury para el mint del anuncio
+    #[account(
+        mut,
+        constraint = treasury_token_account.mint == ad.mint @ ErrorCode::InvalidMint,
+        constraint = treasury_token_account.key()
+            == get_associated_token_address_with_program_id(&treasury.key(), &ad.mint, &token_program.key())
+            @ ErrorCode::InvalidRecipientTokenAccount
+    )]
+    pub treasury_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", trade.merchant.as_ref()],
+        bump
+    )]
+    pub merchant_profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", trade.buyer.as_ref()],
+        bump
+    )]
+    pub buyer_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + DailyStats::INIT_SPACE,
+        seeds = [b"daily_stats", DailyStats::current_day()?.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub daily_stats: Account<'info, DailyStats>,
+
+    /// Solo requeridas si el participante tiene referidor
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + ReferralAccount::INIT_SPACE,
+        seeds = [b"referral", merchant_profile.referrer.unwrap_or_default().as_ref(), ad.mint.as_ref()],
+        bump
+    )]
+    pub merchant_referral: Option<Account<'info, ReferralAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + ReferralAccount::INIT_SPACE,
+        seeds = [b"referral", buyer_profile.referrer.unwrap_or_default().as_ref(), ad.mint.as_ref()],
+        bump
+    )]
+    pub buyer_referral: Option<Account<'info, ReferralAccount>>,
+
+    /// Vault de recompensas de referidos, solo para tokens SPL
+    #[account(
+        mut,
+        seeds = [b"referral_vault", ad.mint.as_ref()],
+        bump
+    )]
+    pub referral_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = ad.mint @ ErrorCode::InvalidMint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelTrade<'info> {
+    #[account(mut, has_one = ad)]
+    pub trade: Account<'info, Trade>,
+
+    #[account(mut)]
+    pub ad: Account<'info, Ad>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", trade.buyer.as_ref()],
+        bump
+    )]
+    pub buyer_profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", trade.merchant.as_ref()],
+        bump
+    )]
+    pub merchant_profile: Account<'info, UserProfile>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + DailyStats::INIT_SPACE,
+        seeds = [b"daily_stats", DailyStats::current_day()?.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub daily_stats: Account<'info, DailyStats>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeTreasury<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Treasury::INIT_SPACE,
+        seeds = [b"treasury"],
+        bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump,
+        has_one = authority
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeTreasuryWithdrawal<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + TreasuryWithdrawal::INIT_SPACE,
+        seeds = [b"treasury_withdrawal", treasury.withdrawal_count.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub withdrawal: Account<'info, TreasuryWithdrawal>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveTreasuryWithdrawal<'info> {
+    #[account(
+        mut,
+        seeds = [b"treasury_withdrawal", withdrawal.index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub withdrawal: Account<'info, TreasuryWithdrawal>,
+
+    #[account(
+        seeds = [b"treasury"],
+        bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTreasury<'info> {
+    #[account(
+        mut,
+        seeds = [b"treasury_withdrawal", withdrawal.index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub withdrawal: Account<'info, TreasuryWithdrawal>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    pub admin: Signer<'info>,
+
+    /// CHECK: Destinatario del retiro, validado contra la propuesta
+    #[account(mut, address = withdrawal.recipient)]
+    pub recipient: AccountInfo<'info>,
+
+    /// Solo requeridas para tokens SPL; el SOL se paga a la wallet del destinatario
+    #[account(mut)]
+    pub treasury_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub recipient_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeAdminCouncil<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + AdminCouncil::INIT_SPACE,
+        seeds = [b"admin_council"],
+        bump
+    )]
+    pub council: Account<'info, AdminCouncil>,
+
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump,
+        has_one = authority
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAdminAction<'info> {
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + AdminProposal::INIT_SPACE,
+        seeds = [b"admin_proposal", council.proposal_count.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub proposal: Account<'info, AdminProposal>,
+
+    #[account(
+        mut,
+        seeds = [b"admin_council"],
+        bump
+    )]
+    pub council: Account<'info, AdminCouncil>,
+
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveAdminAction<'info> {
+    #[account(
+        mut,
+        seeds = [b"admin_proposal", proposal.index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub proposal: Account<'info, AdminProposal>,
+
+    #[account(
+        seeds = [b"admin_council"],
+        bump
+    )]
+    pub council: Account<'info, AdminCouncil>,
+
+    pub member: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteAdminAction<'info> {
+    #[account(
+        mut,
+        seeds = [b"admin_proposal", proposal.index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub proposal: Account<'info, AdminProposal>,
+
+    #[account(
+        mut,
+        seeds = [b"admin_council"],
+        bump
+    )]
+    pub council: Account<'info, AdminCouncil>,
+
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(mut)]
+    pub executor: Signer<'info>,
+
+    /// Solo para acciones con timelock: el cambio queda programado en vez de aplicarse
     #[account(
         init,
-        payer = seller,
-        space = 8 + Order::INIT_SPACE,
-        seeds = [b"order", order_id.as_bytes()],
+        payer = executor,
+        space = 8 + PendingChange::INIT_SPACE,
+        seeds = [b"pending_change", proposal.key().as_ref()],
         bump
     )]
-    pub order: Account<'info, Order>,
+    pub pending_change: Option<Account<'info, PendingChange>>,
+
+    /// Solo para retiros y cambios de administradores del treasury
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump
+    )]
+    pub treasury: Option<Account<'info, Treasury>>,
+
+    /// CHECK: Destinatario de un retiro, validado contra la acción
+    #[account(mut)]
+    pub recipient: Option<AccountInfo<'info>>,
+
+    /// Solo requeridas para retiros de tokens SPL
+    #[account(mut)]
+    pub treasury_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub recipient_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecutePendingChange<'info> {
+    #[account(
+        mut,
+        seeds = [b"pending_change", pending_change.proposal.as_ref()],
+        bump,
+        close = scheduled_by
+    )]
+    pub pending_change: Account<'info, PendingChange>,
+
+    /// Quien programó el cambio recupera la renta
+    #[account(mut, address = pending_change.scheduled_by)]
+    pub scheduled_by: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"admin_council"],
+        bump
+    )]
+    pub council: Account<'info, AdminCouncil>,
+
+    #[account(
+        mut,
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform: Account<'info, Platform>,
+
+    /// Solo para cambios de administradores del treasury
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump
+    )]
+    pub treasury: Option<Account<'info, Treasury>>,
+
+    /// Solo para cambios del esquema de tarifas
+    #[account(
+        mut,
+        seeds = [b"fee_schedule"],
+        bump
+    )]
+    pub fee_schedule: Option<Account<'info, FeeSchedule>>,
+
+    /// Solo para cambios de límites, comisión o estado de un mint
+    #[account(
+        mut,
+        seeds = [b"supported_mint", supported_mint.mint.as_ref()],
+        bump
+    )]
+    pub supported_mint: Option<Account<'info, SupportedMint>>,
+}
+
+#[derive(Accounts)]
+pub struct CancelPendingChange<'info> {
+    #[account(
+        mut,
+        seeds = [b"pending_change", pending_change.proposal.as_ref()],
+        bump,
+        close = scheduled_by
+    )]
+    pub pending_change: Account<'info, PendingChange>,
+
+    /// Quien programó el cambio recupera la renta
+    #[account(mut, address = pending_change.scheduled_by)]
+    pub scheduled_by: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"admin_council"],
+        bump
+    )]
+    pub council: Account<'info, AdminCouncil>,
+
+    pub member: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeReferralVault<'info> {
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"referral_vault", mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = platform,
+        token::token_program = token_program
+    )]
+    pub referral_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump,
+        has_one = authority
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(
+        seeds = [b"supported_mint", mint.key().as_ref()],
+        bump
+    )]
+    pub supported_mint: Account<'info, SupportedMint>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimReferralRewards<'info> {
+    #[account(
+        mut,
+        seeds = [b"referral", referrer.key().as_ref(), referral_account.mint.as_ref()],
+        bump,
+        has_one = referrer
+    )]
+    pub referral_account: Account<'info, ReferralAccount>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+
+    /// Solo requeridas para tokens SPL; el SOL se paga a la wallet del referidor
+    #[account(
+        mut,
+        seeds = [b"referral_vault", referral_account.mint.as_ref()],
+        bump
+    )]
+    pub referral_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = referrer_token_account.mint == referral_account.mint @ ErrorCode::InvalidMint,
+        constraint = referrer_token_account.owner == referrer.key() @ ErrorCode::InvalidRecipientTokenAccount
+    )]
+    pub referrer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = referral_account.mint @ ErrorCode::InvalidMint)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+// ============================================================================
+// ESTRUCTURAS DE DATOS
+// ============================================================================
+
+#[account]
+#[derive(InitSpace)]
+pub struct Platform {
+    pub authority: Pubkey,          // Autoridad de la plataforma
+    pub treasury: Pubkey,            // Cuenta para recibir comisiones
+    pub fee_bps: u64,                // Comisión en basis points (100 = 1%)
+    pub total_volume: u64,           // Volumen total liquidado (MXN con 6 decimales)
+    pub total_transactions: u64,     // Total de liquidaciones (completadas o divididas)
+    pub is_active: bool,             // Estado de la plataforma
+    pub max_price_deviation_bps: u64, // Desviación máxima contra la tasa de referencia
+    pub kyc_limits: [TradingLimits; 4], // Límites por nivel KYC (Basic, Standard, Enhanced, Premium)
+    pub default_fee_bearer: FeeBearer, // Quién paga la comisión si la orden no lo indica
+    pub referral_share_bps: u64,     // Parte de la comisión para referidores (bps de la comisión)
+    pub admin_council: Option<Pubkey>, // Consejo que gobierna la configuración (si existe)
+    pub timelock_delay_seconds: i64, // Espera de los cambios sensibles antes de aplicarse
+    pub guardian: Pubkey,            // Rol que controla los circuit breakers
+    pub paused_operations: u16,      // Familias de instrucciones pausadas (bits PAUSE_*)
+    pub compliance: Pubkey,          // Rol que suspende, banea y reactiva usuarios
+}
+
+impl Platform {
+    /// Autoridad u oficial de cumplimiento
+    pub fn is_moderator(&self, key: &Pubkey) -> bool {
+        *key == self.authority || *key == self.compliance
+    }
+
+    /// Verificar que ninguna de las familias de instrucciones indicadas esté pausada
+    pub fn check_operation(&self, operations: u16) -> Result<()> {
+        require!(self.paused_operations & operations == 0, ErrorCode::OperationPaused);
+        Ok(())
+    }
+
+    /// Recompensa del referidor de un participante: su parte de la comisión
+    /// (según su tarifa de maker o taker) por la proporción de referidos
+    pub fn referral_reward(&self, fee_amount: u64, party_fee_bps: u64, total_fee_bps: u64) -> Result<u64> {
+        if total_fee_bps == 0 {
+            return Ok(0);
+        }
+        let reward = (fee_amount as u128)
+            .checked_mul(party_fee_bps as u128)
+            .and_then(|value| value.checked_mul(self.referral_share_bps as u128))
+            .ok_or(ErrorCode::MathOverflow)?
+            / (total_fee_bps as u128 * 10_000);
+        Ok(reward as u64)
+    }
+
+    /// Sumar una liquidación a los contadores globales
+    pub fn record_settlement(&mut self, amount_mxn: u64) -> Result<()> {
+        self.total_volume = self.total_volume.checked_add(amount_mxn).ok_or(ErrorCode::MathOverflow)?;
+        self.total_transactions = self.total_transactions.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Límites que aplican a un usuario según su nivel KYC
+    pub fn trading_limits(&self, level: VerificationLevel) -> TradingLimits {
+        self.kyc_limits[level as usize]
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct TradingLimits {
+    pub max_trade_mxn: u64,          // Máximo por trade
+    pub daily_mxn: u64,              // Volumen máximo en 24 horas
+    pub monthly_mxn: u64,            // Volumen máximo en 30 días
+}
+
+impl TradingLimits {
+    /// Validar que los límites sean positivos y crecientes (trade <= diario <= mensual)
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.max_trade_mxn > 0 && self.max_trade_mxn <= self.daily_mxn && self.daily_mxn <= self.monthly_mxn,
+            ErrorCode::InvalidTradeLimits
+        );
+        Ok(())
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct UserProfile {
+    pub owner: Pubkey,               // Wallet del usuario
+    pub kyc_verified: bool,          // Si el usuario pasó KYC
+    pub kyc_nft_mint: Option<Pubkey>, // NFT de verificación KYC
+    pub kyc_level: VerificationLevel, // Nivel de verificación KYC
+    pub total_trades: u64,           // Total de trades
+    pub successful_trades: u64,      // Trades exitosos
+    pub disputed_trades: u64,        // Trades en disputa
+    pub is_active: bool,             // Falso mientras tenga una suspensión o baneo registrado
+    pub created_at: i64,             // Timestamp de creación
+    pub daily_volume_mxn: u64,       // Volumen MXN en la ventana diaria actual
+    pub daily_window_start: i64,     // Inicio de la ventana diaria
+    pub monthly_volume_mxn: u64,     // Volumen MXN en la ventana mensual actual
+    pub monthly_window_start: i64,   // Inicio de la ventana mensual
+    pub referrer: Option<Pubkey>,    // Quién refirió al usuario (fijado al crear el perfil)
+    pub status: UserStatus,          // Activo, suspendido o baneado
+    pub suspended_until: Option<i64>, // Fin de la suspensión temporal
+    pub status_reason: u16,          // Código de motivo del último cambio de estado
+    pub status_actor: Option<Pubkey>, // Quién hizo el último cambio de estado
+    pub status_updated_at: i64,      // Timestamp del último cambio de estado
+}
+
+impl UserProfile {
+    /// Verificar que el usuario pueda iniciar operaciones nuevas
+    /// Las suspensiones temporales vencen solas al llegar `suspended_until`
+    pub fn check_can_trade(&self, now: i64) -> Result<()> {
+        match self.status {
+            UserStatus::Active => Ok(()),
+            UserStatus::Suspended if self.suspended_until.is_some_and(|until| now >= until) => Ok(()),
+            UserStatus::Suspended => err!(ErrorCode::UserSuspended),
+            UserStatus::Banned => err!(ErrorCode::UserBanned),
+        }
+    }
+
+    /// Validar un nuevo estado: las suspensiones requieren una duración acotada
+    pub fn validate_status(status: UserStatus, duration_seconds: Option<i64>) -> Result<()> {
+        match (status, duration_seconds) {
+            (UserStatus::Suspended, Some(duration)) => {
+                require!(duration > 0 && duration <= MAX_SUSPENSION_SECONDS, ErrorCode::InvalidSuspension);
+            }
+            (UserStatus::Suspended, None) => return err!(ErrorCode::InvalidSuspension),
+            (_, Some(_)) => return err!(ErrorCode::InvalidSuspension),
+            (_, None) => {}
+        }
+        Ok(())
+    }
+
+    /// Volumen MXN de la ventana de 30 días vigente (0 si ya expiró)
+    pub fn trailing_volume_mxn(&self, now: i64) -> u64 {
+        if now - self.monthly_window_start >= MONTHLY_VOLUME_WINDOW_SECONDS {
+            0
+        } else {
+            self.monthly_volume_mxn
+        }
+    }
+
+    /// Sincronizar el estado KYC con el KycRecord vigente del usuario
+    pub fn refresh_kyc(&mut self, kyc_record: &AccountInfo, now: i64) -> Result<()> {
+        let record = load_kyc_record(kyc_record, &self.owner, now)?;
+        self.kyc_verified = true;
+        self.kyc_level = record.verification_level.into();
+        self.kyc_nft_mint = Some(record.mint);
+        Ok(())
+    }
+
+    /// Sumar volumen MXN a las ventanas diaria y mensual validando los límites
+    /// Cada ventana se reinicia cuando expira su duración
+    pub fn record_volume(&mut self, amount_mxn: u64, limits: &TradingLimits, now: i64) -> Result<()> {
+        require!(amount_mxn <= limits.max_trade_mxn, ErrorCode::ExceedsMaxLimit);
+
+        if now - self.daily_window_start >= DAILY_VOLUME_WINDOW_SECONDS {
+            self.daily_window_start = now;
+            self.daily_volume_mxn = 0;
+        }
+        if now - self.monthly_window_start >= MONTHLY_VOLUME_WINDOW_SECONDS {
+            self.monthly_window_start = now;
+            self.monthly_volume_mxn = 0;
+        }
+
+        let daily_volume_mxn = self
+            .daily_volume_mxn
+            .checked_add(amount_mxn)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let monthly_volume_mxn = self
+            .monthly_volume_mxn
+            .checked_add(amount_mxn)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        require!(daily_volume_mxn <= limits.daily_mxn, ErrorCode::DailyVolumeLimitExceeded);
+        require!(monthly_volume_mxn <= limits.monthly_mxn, ErrorCode::MonthlyVolumeLimitExceeded);
+
+        self.daily_volume_mxn = daily_volume_mxn;
+        self.monthly_volume_mxn = monthly_volume_mxn;
+        Ok(())
+    }
+
+    /// Descontar el volumen de una operación cancelada
+    /// Solo afecta las ventanas que ya contenían el registro (`recorded_at`)
+    pub fn release_volume(&mut self, amount_mxn: u64, recorded_at: i64) {
+        if recorded_at >= self.daily_window_start {
+            self.daily_volume_mxn = self.daily_volume_mxn.saturating_sub(amount_mxn);
+        }
+        if recorded_at >= self.monthly_window_start {
+            self.monthly_volume_mxn = self.monthly_volume_mxn.saturating_sub(amount_mxn);
+        }
+    }
+}
+
+/// Nivel de tarifas: aplica a usuarios con al menos ese volumen de 30 días y nivel KYC
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub struct FeeTier {
+    pub min_volume_mxn: u64,         // Volumen MXN mínimo en la ventana de 30 días
+    pub min_kyc_level: VerificationLevel, // Nivel KYC mínimo
+    pub maker_fee_bps: u64,          // Tarifa para quien publica la orden o anuncio
+    pub taker_fee_bps: u64,          // Tarifa para quien la toma
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct FeeSchedule {
+    #[max_len(8)]
+    pub tiers: Vec<FeeTier>,         // El primero debe cubrir a todos (volumen 0, Basic)
+    pub updated_at: i64,             // Timestamp de la última modificación
+}
+
+impl FeeSchedule {
+    /// Validar los niveles antes de guardarlos
+    pub fn validate_tiers(tiers: &[FeeTier]) -> Result<()> {
+        require!(
+            !tiers.is_empty() && tiers.len() <= MAX_FEE_TIERS,
+            ErrorCode::InvalidFeeSchedule
+        );
+        require!(
+            tiers[0].min_volume_mxn == 0 && tiers[0].min_kyc_level == VerificationLevel::Basic,
+            ErrorCode::InvalidFeeSchedule
+        );
+        require!(
+            tiers
+                .iter()
+                .all(|tier| tier.maker_fee_bps <= MAX_TIER_FEE_BPS && tier.taker_fee_bps <= MAX_TIER_FEE_BPS),
+            ErrorCode::InvalidFeeSchedule
+        );
+        Ok(())
+    }
+
+    /// Tarifa más baja entre los niveles que el usuario alcanza
+    fn best_rate(&self, profile: &UserProfile, now: i64, rate: impl Fn(&FeeTier) -> u64) -> u64 {
+        let volume_mxn = profile.trailing_volume_mxn(now);
+        self.tiers
+            .iter()
+            .filter(|tier| {
+                volume_mxn >= tier.min_volume_mxn && profile.kyc_level as u8 >= tier.min_kyc_level as u8
+            })
+            .map(rate)
+            .min()
+            .unwrap_or(MAX_TIER_FEE_BPS)
+    }
+
+    /// Tarifas (maker, taker) en bps para una operación
+    pub fn rates(&self, maker: &UserProfile, taker: &UserProfile, now: i64) -> (u64, u64) {
+        (
+            self.best_rate(maker, now, |tier| tier.maker_fee_bps),
+            self.best_rate(taker, now, |tier| tier.taker_fee_bps),
+        )
+    }
+
+    /// Tarifas (maker, taker) de una orden pre-fondeada antes de conocer al taker:
+    /// el taker paga la tarifa del nivel base
+    pub fn prefund_rates(&self, maker: &UserProfile, now: i64) -> (u64, u64) {
+        (
+            self.best_rate(maker, now, |tier| tier.maker_fee_bps),
+            self.tiers.first().map_or(MAX_TIER_FEE_BPS, |tier| tier.taker_fee_bps),
+        )
+    }
+}
+
+/// Estadísticas de la plataforma para un día UTC
+#[account]
+#[derive(InitSpace)]
+pub struct DailyStats {
+    pub day: i64,                    // Días desde epoch (unix_timestamp / 86400)
+    pub volume_mxn: u64,             // Volumen liquidado en MXN
+    pub trade_count: u64,            // Liquidaciones (órdenes y trades de anuncios)
+    pub fees_mxn: u64,               // Comisiones cobradas, equivalente en MXN
+    pub disputes: u64,               // Disputas abiertas
+    pub cancellations: u64,          // Órdenes y trades cancelados
+}
+
+impl DailyStats {
+    /// Día UTC actual según el reloj del cluster (semilla de la PDA)
+    pub fn current_day() -> Result<i64> {
+        Ok(Clock::get()?.unix_timestamp.div_euclid(SECONDS_PER_DAY))
+    }
 
-    /// CHECK: This is a PDA used as escrow wallet, validated by seeds
-    #[account(
-        mut,
-        seeds = [b"escrow", order.key().as_ref()],
-        bump
-    )]
-    pub escrow: UncheckedAccount<'info>,
+    pub fn record_settlement(&mut self, amount_mxn: u64, fee_mxn: u64) -> Result<()> {
+        self.day = Self::current_day()?;
+        self.volume_mxn = self.volume_mxn.checked_add(amount_mxn).ok_or(ErrorCode::MathOverflow)?;
+        self.trade_count = self.trade_count.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        self.fees_mxn = self.fees_mxn.checked_add(fee_mxn).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn record_dispute(&mut self) -> Result<()> {
+        self.day = Self::current_day()?;
+        self.disputes = self.disputes.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn record_cancellation(&mut self) -> Result<()> {
+        self.day = Self::current_day()?;
+        self.cancellations = self.cancellations.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+}
+
+/// Leer y validar el KycRecord de un usuario: dueño (programa kyc_nft), discriminador,
+/// vigencia y expiración
+pub fn load_kyc_record(info: &AccountInfo, user: &Pubkey, now: i64) -> Result<kyc_nft::KycRecord> {
+    require!(!info.data_is_empty(), ErrorCode::KYCRequired);
+    require!(info.owner == &kyc_nft::ID, ErrorCode::InvalidKycRecord);
+
+    let data = info.try_borrow_data()?;
+    let record = kyc_nft::KycRecord::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(ErrorCode::InvalidKycRecord))?;
+
+    require!(record.user == *user, ErrorCode::InvalidKycRecord);
+    require!(record.is_valid, ErrorCode::KYCRequired);
+    if let Some(expires_at) = record.expires_at {
+        require!(now < expires_at, ErrorCode::KycExpired);
+    }
+    Ok(record)
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Order {
+    #[max_len(50)]
+    pub order_id: String,            // ID único de la orden
+    pub side: OrderSide,             // Lado del maker (venta o compra)
+    pub seller: Pubkey,              // Vendedor de crypto (default hasta llenar una orden de compra)
+    pub buyer: Option<Pubkey>,       // Comprador de crypto
+    pub amount: u64,                 // Cantidad en tokens/SOL
+    pub amount_mxn: u64,             // Cantidad en MXN (con decimales)
+    pub maker_volume_mxn: u64,       // Volumen MXN registrado al maker al crear (las flotantes se recotizan al aceptar)
+    pub mint: Pubkey,                // Mint del token (native mint para SOL)
+    pub decimals: u8,                // Decimales del mint
+    pub payment_method: PaymentMethod, // Método de pago
+    pub status: OrderStatus,         // Estado de la orden
+    #[max_len(100)]
+    pub stp_reference: String,       // Referencia STP para el pago
+    #[max_len(100)]
+    pub stp_transaction_id: Option<String>, // ID de transacción STP
+    pub stp_oracle_confirmed: bool,  // Si el oráculo confirmó el pago STP
+    pub escrow: Pubkey,              // Cuenta escrow
+    pub escrowed_amount: u64,        // Monto efectivamente recibido en escrow (neto de transfer-fee)
+    pub prefunded: bool,             // Si el seller fondeó el escrow al crear la orden
+    pub price_feed: Option<Pubkey>,  // Feed de precio (solo órdenes flotantes)
+    pub premium_bps: i32,            // Prima (+) o descuento (-) sobre el feed
+    pub maker_fee_bps: u64,          // Tarifa del maker fijada al aceptar
+    pub taker_fee_bps: u64,          // Tarifa del taker fijada al aceptar
+    pub fee_bearer: FeeBearer,       // Quién paga la comisión
+    pub fee_amount: u64,             // Comisión fijada al fondear (seller/split) o cobrada al liquidar
+    pub created_at: i64,             // Timestamp de creación
+    pub accepted_at: Option<i64>,    // Timestamp de aceptación
+    pub funded_at: Option<i64>,      // Timestamp de fondeo
+    pub payment_confirmed_at: Option<i64>, // Timestamp de confirmación de pago
+    pub completed_at: Option<i64>,   // Timestamp de completado
+    pub held_status: Option<OrderStatus>, // Estado previo a la retención legal
+    #[max_len(64)]
+    pub legal_hold_case: Option<String>, // Referencia del caso de la retención legal
+    pub legal_hold_by: Option<Pubkey>, // Quién colocó la retención
+    pub legal_hold_at: Option<i64>,  // Timestamp de la retención
+}
+
+impl Order {
+    pub fn is_native(&self) -> bool {
+        self.mint == native_mint::ID
+    }
+
+    /// Si el escrow de la orden tiene fondos que se puedan retener
+    pub fn holds_escrow(&self) -> bool {
+        matches!(
+            self.status,
+            OrderStatus::Funded | OrderStatus::PaymentConfirmed | OrderStatus::Disputed
+        ) || (self.status == OrderStatus::Open && self.prefunded)
+    }
+
+    /// Las órdenes bajo retención legal no se liquidan hasta que se levante
+    pub fn check_not_frozen(&self) -> Result<()> {
+        require!(self.status != OrderStatus::Frozen, ErrorCode::OrderFrozen);
+        Ok(())
+    }
+
+    /// Si el seller depositó su parte de la comisión al pre-fondear
+    pub fn prefunds_fee(&self) -> bool {
+        self.prefunded && self.fee_bearer != FeeBearer::Buyer
+    }
+
+    /// Comisión total de la orden (maker + taker)
+    pub fn fee_bps(&self) -> u64 {
+        self.maker_fee_bps + self.taker_fee_bps
+    }
+
+    /// Tarifas de (seller, buyer) según quién es maker y quién taker
+    pub fn party_fee_bps(&self) -> (u64, u64) {
+        match self.side {
+            OrderSide::Sell => (self.maker_fee_bps, self.taker_fee_bps),
+            OrderSide::Buy => (self.taker_fee_bps, self.maker_fee_bps),
+        }
+    }
+
+    /// Parte de la comisión que el seller deposita en escrow además del monto
+    pub fn seller_fee(&self) -> u64 {
+        self.fee_bearer.seller_share(self.fee_amount)
+    }
+
+    /// Fijar la comisión antes del depósito y devolver cuánto debe depositar el seller
+    pub fn quote_deposit(&mut self, supported_mint: &SupportedMint) -> Result<u64> {
+        if self.fee_bearer != FeeBearer::Buyer {
+            self.fee_amount = supported_mint.fee_amount(self.amount, self.fee_bps())?;
+        }
+        Ok(self.amount.checked_add(self.seller_fee()).ok_or(ErrorCode::MathOverflow)?)
+    }
+
+    /// Comisión total a cobrar del escrow al liquidar
+    /// Si la paga el buyer se calcula sobre lo recibido en escrow (neto de transfer-fee)
+    pub fn total_fee(&self, supported_mint: &SupportedMint) -> Result<u64> {
+        match self.fee_bearer {
+            FeeBearer::Buyer => supported_mint.fee_amount(self.escrowed_amount, self.fee_bps()),
+            FeeBearer::Seller | FeeBearer::Split => Ok(self.fee_amount),
+        }
+    }
+
+    /// Reparto del escrow (seller, buyer, comisión) según el fallo de una disputa
+    pub fn dispute_payouts(&self, resolution: DisputeResolution, total_fee: u64) -> Result<(u64, u64, u64)> {
+        match resolution {
+            DisputeResolution::FavorBuyer => {
+                let buyer_amount = self
+                    .escrowed_amount
+                    .checked_sub(total_fee)
+                    .ok_or(ErrorCode::MathOverflow)?;
+                Ok((0, buyer_amount, total_fee))
+            },
+            // Como en cancel_order: el seller recupera todo lo depositado y no se cobra comisión
+            DisputeResolution::FavorSeller => Ok((self.escrowed_amount, 0, 0)),
+            // 50/50; cada parte absorbe su porción de la comisión
+            // (la del seller ya se depositó en escrow, la del buyer se descuenta de su mitad)
+            DisputeResolution::Split => {
+                let seller_fee = self.seller_fee();
+                let principal = self
+                    .escrowed_amount
+                    .checked_sub(seller_fee)
+                    .ok_or(ErrorCode::MathOverflow)?;
+                let seller_amount = principal / 2;
+                let buyer_half = principal - seller_amount;
+                let buyer_fee = total_fee.saturating_sub(seller_fee).min(buyer_half);
+                Ok((seller_amount, buyer_half - buyer_fee, seller_fee + buyer_fee))
+            },
+        }
+    }
+
+    /// Usuario que creó la orden
+    pub fn maker(&self) -> Pubkey {
+        match self.side {
+            OrderSide::Sell => self.seller,
+            OrderSide::Buy => self.buyer.unwrap_or_default(),
+        }
+    }
+
+    /// Usuario que aceptó o llenó la orden (None mientras siga abierta)
+    pub fn taker(&self) -> Option<Pubkey> {
+        self.accepted_at.map(|_| match self.side {
+            OrderSide::Sell => self.buyer.unwrap_or_default(),
+            OrderSide::Buy => self.seller,
+        })
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Dispute {
+    pub order: Pubkey,               // Orden en disputa
+    pub initiator: Pubkey,           // Quien inició la disputa
+    #[max_len(500)]
+    pub reason: String,              // Razón de la disputa
+    #[max_len(1000)]
+    pub evidence: String,            // Evidencia (URLs, hashes, etc.)
+    pub status: DisputeStatus,       // Estado de la disputa
+    pub resolver: Option<Pubkey>,    // Quien resolvió la disputa
+    pub resolution: Option<DisputeResolution>, // Resolución
+    #[max_len(1000)]
+    pub resolution_notes: Option<String>, // Notas de la resolución
+    pub created_at: i64,             // Timestamp de creación
+    pub resolved_at: Option<i64>,    // Timestamp de resolución
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct SupportedMint {
+    pub mint: Pubkey,                // Mint del token (native mint para SOL)
+    pub decimals: u8,                // Decimales del mint
+    pub min_trade_amount: u64,       // Mínimo por orden en unidades del token
+    pub max_trade_amount: u64,       // Máximo por orden en unidades del token
+    pub fee_bps_override: Option<u64>, // Comisión específica del mint (sustituye la del esquema)
+    pub min_fee: u64,                // Comisión mínima en unidades del token
+    pub max_fee: Option<u64>,        // Tope de comisión en unidades del token
+    pub enabled: bool,               // Si se permiten nuevas operaciones
+    pub total_volume: u64,           // Volumen liquidado en unidades del token
+    pub total_volume_mxn: u64,       // Volumen liquidado en MXN
+    pub total_fees: u64,             // Comisiones cobradas en unidades del token
+    pub total_transactions: u64,     // Liquidaciones de este mint
+    pub trading_paused: bool,        // Circuit breaker del guardián para este mint
+}
+
+impl SupportedMint {
+    pub fn is_native(&self) -> bool {
+        self.mint == native_mint::ID
+    }
+
+    /// Sumar una liquidación a los contadores del mint
+    pub fn record_settlement(&mut self, amount: u64, amount_mxn: u64, fee_amount: u64) -> Result<()> {
+        self.total_volume = self.total_volume.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+        self.total_volume_mxn = self.total_volume_mxn.checked_add(amount_mxn).ok_or(ErrorCode::MathOverflow)?;
+        self.total_fees = self.total_fees.checked_add(fee_amount).ok_or(ErrorCode::MathOverflow)?;
+        self.total_transactions = self.total_transactions.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Validar que el mint esté habilitado y sin pausa del guardián
+    pub fn check_trading(&self) -> Result<()> {
+        require!(self.enabled, ErrorCode::MintDisabled);
+        require!(!self.trading_paused, ErrorCode::MintTradingPaused);
+        Ok(())
+    }
+
+    /// Validar que el mint admita operaciones y el monto esté dentro de sus límites
+    pub fn check_trade_amount(&self, amount: u64) -> Result<()> {
+        self.check_trading()?;
+        require!(
+            amount >= self.min_trade_amount && amount <= self.max_trade_amount,
+            ErrorCode::AmountOutsideMintLimits
+        );
+        Ok(())
+    }
+
+    /// Comisión sobre un monto: tarifa en bps acotada por el mínimo y el tope del mint
+    /// La comisión específica del mint, si existe, tiene prioridad sobre la del esquema
+    /// Única fuente del cálculo de comisiones en órdenes, disputas y trades
+    pub fn fee_amount(&self, amount: u64, fee_bps: u64) -> Result<u64> {
+        let fee_bps = self.fee_bps_override.unwrap_or(fee_bps);
+        if fee_bps == 0 {
+            return Ok(0);
+        }
+        let fee = (amount as u128)
+            .checked_mul(fee_bps as u128)
+            .map(|v| v / 10_000)
+            .and_then(|v| u64::try_from(v).ok())
+            .ok_or(ErrorCode::MathOverflow)?;
+        let fee = fee.max(self.min_fee);
+        let fee = self.max_fee.map_or(fee, |max_fee| fee.min(max_fee));
+        Ok(fee.min(amount))
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ReferenceRate {
+    pub mint: Pubkey,                // Mint cotizado
+    pub rate_mxn: u64,               // MXN (6 decimales) por unidad entera del token
+    pub updated_at: i64,             // Timestamp de la última actualización
+    pub max_staleness_seconds: i64,  // Antigüedad máxima aceptada
+}
+
+impl ReferenceRate {
+    /// Rechazar precios implícitos fuera de la banda permitida
+    pub fn check_price(
+        &self,
+        amount: u64,
+        amount_mxn: u64,
+        decimals: u8,
+        max_deviation_bps: u64,
+        now: i64,
+    ) -> Result<()> {
+        let implied_price = (amount_mxn as u128) * 10u128.pow(decimals as u32) / (amount as u128);
+        self.check_unit_price(implied_price, max_deviation_bps, now)
+    }
+
+    /// Rechazar un precio por unidad entera del token (MXN con 6 decimales) fuera de la banda
+    pub fn check_unit_price(&self, implied_price: u128, max_deviation_bps: u64, now: i64) -> Result<()> {
+        require!(
+            self.rate_mxn > 0 && now - self.updated_at <= self.max_staleness_seconds,
+            ErrorCode::StaleReferenceRate
+        );
+
+        let deviation_bps = implied_price.abs_diff(self.rate_mxn as u128) * 10_000 / (self.rate_mxn as u128);
+
+        if deviation_bps > max_deviation_bps as u128 {
+            msg!(
+                "Implied price {} deviates {} bps from reference {}",
+                implied_price,
+                deviation_bps,
+                self.rate_mxn
+            );
+            return Err(error!(ErrorCode::PriceDeviationExceeded).with_values((deviation_bps, max_deviation_bps)));
+        }
+
+        Ok(())
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PriceFeed {
+    #[max_len(32)]
+    pub feed_id: String,             // Identificador del feed (ej. "SOL/MXN")
+    pub mint: Pubkey,                // Mint cotizado
+    pub updater: Pubkey,             // Quien puede publicar precios
+    pub price_mxn: u64,              // MXN (6 decimales) por unidad entera del token
+    pub updated_at: i64,             // Timestamp de la última publicación
+}
+
+impl PriceFeed {
+    /// Precio vigente con la prima aplicada; falla si el feed está desactualizado
+    pub fn current_price(&self, premium_bps: i32, now: i64) -> Result<u64> {
+        require!(self.price_mxn > 0, ErrorCode::StalePriceFeed);
+        require!(
+            now - self.updated_at <= PRICE_FEED_MAX_AGE_SECONDS,
+            ErrorCode::StalePriceFeed
+        );
+
+        let adjusted = (self.price_mxn as i128) * (10_000 + premium_bps as i128) / 10_000;
+        u64::try_from(adjusted).map_err(|_| error!(ErrorCode::MathOverflow))
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Ad {
+    #[max_len(50)]
+    pub ad_id: String,               // ID único del anuncio
+    pub merchant: Pubkey,            // Merchant que vende la crypto
+    pub mint: Pubkey,                // Mint del token (native mint para SOL)
+    pub decimals: u8,                // Decimales del mint
+    pub price_mxn: u64,              // Precio en MXN por unidad entera del token
+    pub min_trade_mxn: u64,          // Monto mínimo por trade en MXN
+    pub max_trade_mxn: u64,          // Monto máximo por trade en MXN
+    #[max_len(3)]
+    pub payment_methods: Vec<PaymentMethod>, // Métodos de pago aceptados
+    pub vault: Pubkey,               // Bóveda PDA del inventario
+    pub vault_token_account: Pubkey, // Cuenta de tokens de la bóveda (wSOL para SOL)
+    pub available_amount: u64,       // Inventario libre
+    pub locked_amount: u64,          // Inventario comprometido en trades abiertos
+    pub trade_count: u64,            // Total de trades abiertos contra el anuncio
+    pub is_active: bool,             // Si acepta nuevos trades
+    pub created_at: i64,             // Timestamp de creación
+}
+
+impl Ad {
+    pub fn is_native(&self) -> bool {
+        self.mint == native_mint::ID
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Trade {
+    pub ad: Pubkey,                  // Anuncio del que sale el inventario
+    pub trade_index: u64,            // Índice del trade dentro del anuncio
+    pub merchant: Pubkey,            // Merchant (vendedor)
+    pub buyer: Pubkey,               // Comprador de crypto
+    pub amount: u64,                 // Cantidad en tokens/SOL
+    pub amount_mxn: u64,             // Cantidad en MXN (con decimales)
+    pub payment_method: PaymentMethod, // Método de pago elegido
+    pub maker_fee_bps: u64,          // Tarifa del merchant fijada al abrir
+    pub taker_fee_bps: u64,          // Tarifa del buyer fijada al abrir
+    pub fee_bearer: FeeBearer,       // Quién paga la comisión (default de la plataforma)
+    pub fee_amount: u64,             // Comisión fijada al abrir
+    pub status: TradeStatus,         // Estado del trade
+    #[max_len(100)]
+    pub stp_transaction_id: Option<String>, // ID de transacción STP
+    pub created_at: i64,             // Timestamp de apertura
+    pub payment_confirmed_at: Option<i64>, // Timestamp de confirmación de pago
+    pub completed_at: Option<i64>,   // Timestamp de liberación o cancelación
+}
+
+impl Trade {
+    /// Inventario comprometido: el monto más la parte de la comisión que aporta el merchant
+    pub fn locked_amount(&self) -> Result<u64> {
+        Ok(self
+            .amount
+            .checked_add(self.fee_bearer.seller_share(self.fee_amount))
+            .ok_or(ErrorCode::MathOverflow)?)
+    }
+}
+
+/// Treasury de la plataforma: recibe las comisiones en SOL (lamports de esta cuenta)
+/// y es dueño de una ATA por mint para las comisiones en tokens
+#[account]
+#[derive(InitSpace)]
+pub struct Treasury {
+    #[max_len(MAX_TREASURY_ADMINS)]
+    pub admins: Vec<Pubkey>,         // Administradores que aprueban retiros
+    pub threshold: u8,               // Aprobaciones requeridas (M de N)
+    pub withdrawal_count: u64,       // Retiros propuestos (seed del siguiente)
+}
+
+impl Treasury {
+    /// Validar el conjunto de administradores y el umbral M-de-N
+    pub fn validate_admins(admins: &[Pubkey], threshold: u8) -> Result<()> {
+        require!(
+            is_valid_admin_set(admins, threshold, MAX_TREASURY_ADMINS),
+            ErrorCode::InvalidTreasuryAdmins
+        );
+        Ok(())
+    }
+
+    pub fn check_admin(&self, key: &Pubkey) -> Result<()> {
+        require!(self.admins.contains(key), ErrorCode::NotTreasuryAdmin);
+        Ok(())
+    }
+}
+
+/// Retiro del treasury pendiente de aprobaciones
+#[account]
+#[derive(InitSpace)]
+pub struct TreasuryWithdrawal {
+    pub index: u64,                  // Número de propuesta
+    pub mint: Pubkey,                // Native mint para SOL
+    pub amount: u64,                 // Monto a retirar
+    pub recipient: Pubkey,           // Wallet destino
+    pub proposer: Pubkey,            // Administrador que la propuso
+    #[max_len(MAX_TREASURY_ADMINS)]
+    pub approvals: Vec<Pubkey>,      // Administradores que aprobaron
+    pub created_at: i64,             // Timestamp de la propuesta
+    pub executed_at: Option<i64>,    // Timestamp de ejecución
+}
+
+/// Consejo de administración: aprueba en M-de-N las operaciones privilegiadas
+#[account]
+#[derive(InitSpace)]
+pub struct AdminCouncil {
+    #[max_len(MAX_COUNCIL_MEMBERS)]
+    pub members: Vec<Pubkey>,        // Miembros con voto
+    pub threshold: u8,               // Aprobaciones requeridas (M de N)
+    pub proposal_count: u64,         // Propuestas creadas (seed de la siguiente)
+}
+
+impl AdminCouncil {
+    pub fn check_member(&self, key: &Pubkey) -> Result<()> {
+        require!(self.members.contains(key), ErrorCode::NotCouncilMember);
+        Ok(())
+    }
+
+    /// Aprobaciones de quienes siguen siendo miembros
+    pub fn approval_count(&self, approvals: &[Pubkey]) -> usize {
+        approvals.iter().filter(|member| self.members.contains(member)).count()
+    }
+}
+
+/// Propuesta del consejo con su acción serializada, aprobaciones y vencimiento
+#[account]
+#[derive(InitSpace)]
+pub struct AdminProposal {
+    pub index: u64,                  // Número de propuesta
+    pub proposer: Pubkey,            // Miembro que la propuso
+    pub action: AdminAction,         // Acción a ejecutar
+    #[max_len(MAX_COUNCIL_MEMBERS)]
+    pub approvals: Vec<Pubkey>,      // Miembros que aprobaron
+    pub created_at: i64,             // Timestamp de la propuesta
+    pub expires_at: i64,             // Ya no se aprueba ni ejecuta desde este momento
+    pub executed_at: Option<i64>,    // Timestamp de ejecución
+}
+
+impl AdminProposal {
+    /// Verificar que la propuesta siga pendiente y vigente
+    pub fn check_pending(&self, now: i64) -> Result<()> {
+        require!(self.executed_at.is_none(), ErrorCode::ProposalAlreadyExecuted);
+        require!(now < self.expires_at, ErrorCode::ProposalExpired);
+        Ok(())
+    }
+
+    /// Marcar como ejecutada una propuesta aprobada que se aplica con su propia instrucción
+    pub fn consume(&mut self, council: &AdminCouncil, now: i64) -> Result<()> {
+        self.check_pending(now)?;
+        require!(
+            council.approval_count(&self.approvals) >= council.threshold as usize,
+            ErrorCode::ApprovalThresholdNotMet
+        );
+        self.executed_at = Some(now);
+        Ok(())
+    }
+}
+
+/// Cambio sensible aprobado por el consejo, en espera de su timelock
+#[account]
+#[derive(InitSpace)]
+pub struct PendingChange {
+    pub proposal: Pubkey,            // Propuesta que lo originó (seed)
+    pub action: AdminAction,         // Acción a aplicar
+    pub scheduled_by: Pubkey,        // Miembro que lo programó (recupera la renta)
+    pub scheduled_at: i64,           // Timestamp de programación
+    pub eta: i64,                    // Desde cuándo puede ejecutarse
+}
+
+/// Dirección bloqueada (sanciones o fraude); su existencia impide operar
+#[account]
+#[derive(InitSpace)]
+pub struct BlockedAddress {
+    pub address: Pubkey,             // Wallet bloqueada (seed)
+    pub reason_code: u16,            // Código del motivo
+    pub blocked_by: Pubkey,          // Quién la bloqueó
+    pub blocked_at: i64,             // Timestamp del bloqueo
+}
+
+/// Recompensas de un referidor en un mint
+/// Con SOL los lamports acumulados se guardan en la propia cuenta; con tokens en el vault del mint
+#[account]
+#[derive(InitSpace)]
+pub struct ReferralAccount {
+    pub referrer: Pubkey,            // Wallet del referidor
+    pub mint: Pubkey,                // Mint de las recompensas (native mint para SOL)
+    pub claimable: u64,              // Pendiente de cobrar
+    pub total_earned: u64,           // Total acreditado
+    pub total_claimed: u64,          // Total cobrado
+    pub settlements: u64,            // Liquidaciones de referidos que generaron recompensa
+    pub last_claimed_at: Option<i64>, // Último cobro
+}
+
+impl ReferralAccount {
+    /// Acreditar una recompensa (la cuenta se crea vacía al primer pago)
+    pub fn credit(&mut self, referrer: Pubkey, mint: Pubkey, amount: u64) -> Result<()> {
+        self.referrer = referrer;
+        self.mint = mint;
+        self.claimable = self.claimable.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+        self.total_earned = self.total_earned.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+        self.settlements = self.settlements.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+}
+
+/// Recompensa de un referidor en una liquidación
+pub struct ReferralReward<'a, 'info> {
+    pub account: &'a mut Account<'info, ReferralAccount>,
+    pub referrer: Pubkey,
+    pub amount: u64,
+}
+
+// ============================================================================
+// FUNCIONES AUXILIARES
+// ============================================================================
+
+/// Equivalente en MXN de la comisión, proporcional al monto liquidado
+pub fn fee_amount_mxn(amount_mxn: u64, fee_amount: u64, amount: u64) -> Result<u64> {
+    if amount == 0 {
+        return Ok(0);
+    }
+    (amount_mxn as u128)
+        .checked_mul(fee_amount as u128)
+        .and_then(|v| v.checked_div(amount as u128))
+        .and_then(|v| u64::try_from(v).ok())
+        .ok_or(error!(ErrorCode::MathOverflow))
+}
+
+/// Convertir una cantidad del token a MXN dado un precio por unidad entera
+fn quote_amount_mxn(amount: u64, price_mxn: u64, decimals: u8) -> Result<u64> {
+    (amount as u128)
+        .checked_mul(price_mxn as u128)
+        .map(|v| v / 10u128.pow(decimals as u32))
+        .and_then(|v| u64::try_from(v).ok())
+        .ok_or_else(|| error!(ErrorCode::MathOverflow))
+}
+
+/// Depositar fondos del usuario en una cuenta de tokens del programa
+/// SOL se envuelve (wrapped SOL) desde la wallet; regresa el monto que llegó al destino
+fn deposit_tokens<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    depositor: &Signer<'info>,
+    depositor_token_account: Option<&InterfaceAccount<'info, TokenAccount>>,
+    destination: &mut InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    system_program: &Program<'info, System>,
+    amount: u64,
+) -> Result<u64> {
+    if mint.key() != native_mint::ID {
+        let Some(depositor_token_account) = depositor_token_account else {
+            return err!(ErrorCode::MissingTokenAccounts);
+        };
+        return transfer_tokens(
+            token_program,
+            depositor_token_account,
+            destination,
+            mint,
+            depositor.to_account_info(),
+            &[],
+            amount,
+        );
+    }
+
+    let balance_before = destination.amount;
+
+    let transfer_ix = anchor_lang::solana_program::system_instruction::transfer(
+        &depositor.key(),
+        &destination.key(),
+        amount,
+    );
+
+    anchor_lang::solana_program::program::invoke(
+        &transfer_ix,
+        &[
+            depositor.to_account_info(),
+            destination.to_account_info(),
+            system_program.to_account_info(),
+        ],
+    )?;
+
+    // Reflejar los lamports recibidos como saldo de wSOL
+    let cpi_ctx = CpiContext::new(
+        token_program.to_account_info(),
+        SyncNative {
+            account: destination.to_account_info(),
+        },
+    );
+
+    token_interface::sync_native(cpi_ctx)?;
+
+    destination.reload()?;
+    destination
+        .amount
+        .checked_sub(balance_before)
+        .ok_or_else(|| error!(ErrorCode::MathOverflow))
+}
+
+/// Transferir lamports desde un PDA del sistema (escrow o vault) firmando con sus seeds
+fn transfer_lamports_signed<'info>(
+    from: &SystemAccount<'info>,
+    to: AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    signer_seeds: &[&[&[u8]]],
+    lamports: u64,
+) -> Result<()> {
+    let transfer_ix = anchor_lang::solana_program::system_instruction::transfer(
+        &from.key(),
+        &to.key(),
+        lamports,
+    );
+
+    anchor_lang::solana_program::program::invoke_signed(
+        &transfer_ix,
+        &[
+            from.to_account_info(),
+            to,
+            system_program.to_account_info(),
+        ],
+        signer_seeds,
+    )?;
+    Ok(())
+}
+
+/// Repartir el saldo del escrow de una orden entre sus destinatarios
+/// Cada pago indica la wallet (SOL) y la cuenta de tokens (SPL) del destinatario.
+/// Con SOL la cuenta wSOL se cierra al PDA del escrow (unwrap) y se paga en lamports;
+/// el remanente (renta de la cuenta wSOL) se entrega a `rent_recipient`
+#[allow(clippy::too_many_arguments)]
+fn pay_out_escrow<'info>(
+    escrow: &SystemAccount<'info>,
+    escrow_token_account: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
+    system_program: &Program<'info, System>,
+    rent_recipient: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    payouts: &[(AccountInfo<'info>, Option<&InterfaceAccount<'info, TokenAccount>>, u64)],
+) -> Result<()> {
+    if mint.key() != native_mint::ID {
+        return pay_out_tokens(escrow, escrow_token_account, mint, token_program, signer_seeds, payouts);
+    }
+
+    // Unwrap: cerrar la cuenta wSOL libera sus lamports al PDA del escrow
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        CloseAccount {
+            account: escrow_token_account.to_account_info(),
+            destination: escrow.to_account_info(),
+            authority: escrow.to_account_info(),
+        },
+        signer_seeds,
+    );
+
+    token_interface::close_account(cpi_ctx)?;
+
+    for (wallet, _, lamports) in payouts {
+        transfer_lamports_signed(escrow, wallet.clone(), system_program, signer_seeds, *lamports)?;
+    }
+
+    // El escrow queda vacío: la renta de la cuenta wSOL regresa al seller
+    transfer_lamports_signed(escrow, rent_recipient, system_program, signer_seeds, escrow.lamports())
+}
+
+/// Repartir inventario de la bóveda de un anuncio entre sus destinatarios
+/// Como `pay_out_escrow`, pero la cuenta wSOL de la bóveda persiste: con SOL el total pasa
+/// a una cuenta wSOL temporal que se cierra al PDA de la bóveda (unwrap) y se paga en lamports;
+/// la renta de la cuenta temporal regresa a `rent_payer`, que la fondeó
+#[allow(clippy::too_many_arguments)]
+fn pay_out_vault<'info>(
+    vault: &SystemAccount<'info>,
+    vault_token_account: &InterfaceAccount<'info, TokenAccount>,
+    unwrap_token_account: Option<&InterfaceAccount<'info, TokenAccount>>,
+    mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
+    system_program: &Program<'info, System>,
+    rent_payer: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    payouts: &[(AccountInfo<'info>, Option<&InterfaceAccount<'info, TokenAccount>>, u64)],
+) -> Result<()> {
+    if mint.key() != native_mint::ID {
+        return pay_out_tokens(vault, vault_token_account, mint, token_program, signer_seeds, payouts);
+    }
+
+    let Some(unwrap_token_account) = unwrap_token_account else {
+        return err!(ErrorCode::MissingTokenAccounts);
+    };
+    let rent = unwrap_token_account.to_account_info().lamports();
+    let total = payouts
+        .iter()
+        .try_fold(0u64, |total, (_, _, amount)| total.checked_add(*amount))
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    pay_out_tokens(
+        vault,
+        vault_token_account,
+        mint,
+        token_program,
+        signer_seeds,
+        &[(unwrap_token_account.to_account_info(), Some(unwrap_token_account), total)],
+    )?;
+
+    // Unwrap: cerrar la cuenta temporal libera el total y su renta al PDA de la bóveda
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        CloseAccount {
+            account: unwrap_token_account.to_account_info(),
+            destination: vault.to_account_info(),
+            authority: vault.to_account_info(),
+        },
+        signer_seeds,
+    );
+
+    token_interface::close_account(cpi_ctx)?;
+
+    for (wallet, _, lamports) in payouts {
+        transfer_lamports_signed(vault, wallet.clone(), system_program, signer_seeds, *lamports)?;
+    }
+
+    transfer_lamports_signed(vault, rent_payer, system_program, signer_seeds, rent)
+}
+
+/// Transferir tokens desde la cuenta de un PDA a la cuenta de tokens de cada destinatario
+fn pay_out_tokens<'info>(
+    authority: &SystemAccount<'info>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
+    signer_seeds: &[&[&[u8]]],
+    payouts: &[(AccountInfo<'info>, Option<&InterfaceAccount<'info, TokenAccount>>, u64)],
+) -> Result<()> {
+    for (_, token_account, amount) in payouts {
+        let Some(token_account) = token_account else {
+            return err!(ErrorCode::MissingTokenAccounts);
+        };
 
-    #[account(
-        mut,
-        seeds = [b"user_profile", seller.key().as_ref()],
-        bump
-    )]
-    pub seller_profile: Account<'info, UserProfile>,
+        let cpi_accounts = TransferChecked {
+            from: from.to_account_info(),
+            mint: mint.to_account_info(),
+            to: token_account.to_account_info(),
+            authority: authority.to_account_info(),
+        };
 
-    #[account(mut)]
-    pub seller: Signer<'info>,
+        let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer_seeds);
 
-    pub system_program: Program<'info, System>,
+        token_interface::transfer_checked(cpi_ctx, *amount, mint.decimals)?;
+    }
+    Ok(())
 }
 
-#[derive(Accounts)]
-pub struct AcceptOrder<'info> {
-    #[account(mut)]
-    pub order: Account<'info, Order>,
+/// Si la entrada del blocklist de una dirección existe
+fn is_blocked(entry: &AccountInfo) -> bool {
+    entry.owner == &crate::ID && !entry.data_is_empty()
+}
 
-    #[account(
-        mut,
-        seeds = [b"user_profile", buyer.key().as_ref()],
-        bump
-    )]
-    pub buyer_profile: Account<'info, UserProfile>,
+/// Rechazar la operación si la dirección está en el blocklist
+fn check_not_blocked(entry: &AccountInfo) -> Result<()> {
+    require!(!is_blocked(entry), ErrorCode::AddressBlocked);
+    Ok(())
+}
 
-    #[account(mut)]
-    pub buyer: Signer<'info>,
+/// Crear una cuenta PDA de este programa pagada por `payer`
+/// Como `init` de Anchor: si la dirección ya tiene lamports se completa la renta y se asigna
+fn create_program_pda<'info>(
+    payer: &Signer<'info>,
+    target: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    signer_seeds: &[&[&[u8]]],
+    space: usize,
+) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(space);
+    let current_lamports = target.lamports();
+
+    if current_lamports == 0 {
+        let cpi_ctx = CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            anchor_lang::system_program::CreateAccount {
+                from: payer.to_account_info(),
+                to: target.clone(),
+            },
+            signer_seeds,
+        );
+        return anchor_lang::system_program::create_account(cpi_ctx, rent, space as u64, &crate::ID);
+    }
+
+    if rent > current_lamports {
+        let cpi_ctx = CpiContext::new(
+            system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: payer.to_account_info(),
+                to: target.clone(),
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_ctx, rent - current_lamports)?;
+    }
+
+    let cpi_ctx = CpiContext::new_with_signer(
+        system_program.to_account_info(),
+        anchor_lang::system_program::Allocate { account_to_allocate: target.clone() },
+        signer_seeds,
+    );
+    anchor_lang::system_program::allocate(cpi_ctx, space as u64)?;
+
+    let cpi_ctx = CpiContext::new_with_signer(
+        system_program.to_account_info(),
+        anchor_lang::system_program::Assign { account_to_assign: target.clone() },
+        signer_seeds,
+    );
+    anchor_lang::system_program::assign(cpi_ctx, &crate::ID)
 }
 
-#[derive(Accounts)]
-pub struct DepositToEscrowNative<'info> {
-    #[account(mut)]
-    pub order: Account<'info, Order>,
+/// Registrar un cambio de estado de un usuario con su motivo, quién lo hizo y cuándo
+/// Una suspensión no reemplaza un baneo; para levantarlo hay que reactivar primero
+fn change_user_status(
+    profile: &mut UserProfile,
+    status: UserStatus,
+    duration_seconds: Option<i64>,
+    reason_code: u16,
+    actor: Pubkey,
+    now: i64,
+) -> Result<()> {
+    UserProfile::validate_status(status, duration_seconds)?;
+    require!(
+        !(status == UserStatus::Suspended && profile.status == UserStatus::Banned),
+        ErrorCode::UserBanned
+    );
+
+    profile.status = status;
+    profile.is_active = status == UserStatus::Active;
+    profile.suspended_until = duration_seconds.map(|duration| now + duration);
+    profile.status_reason = reason_code;
+    profile.status_actor = Some(actor);
+    profile.status_updated_at = now;
+
+    emit!(UserStatusChanged {
+        user: profile.owner,
+        status,
+        suspended_until: profile.suspended_until,
+        reason_code,
+        actor,
+        timestamp: now,
+    });
+    Ok(())
+}
 
-    #[account(
-        mut,
-        seeds = [b"escrow", order.key().as_ref()],
-        bump
-    )]
-    pub escrow: SystemAccount<'info>,
+/// Validar un conjunto de administradores M-de-N: sin duplicados y con 1 <= M <= N <= máximo
+fn is_valid_admin_set(admins: &[Pubkey], threshold: u8, max_len: usize) -> bool {
+    !admins.is_empty()
+        && admins.len() <= max_len
+        && threshold > 0
+        && threshold as usize <= admins.len()
+        && admins.iter().enumerate().all(|(i, admin)| !admins[..i].contains(admin))
+}
 
-    #[account(mut)]
-    pub seller: Signer<'info>,
+/// Aplicar un cambio de rol (alta, baja o umbral) a un conjunto M-de-N
+fn apply_role_change(
+    admins: &mut Vec<Pubkey>,
+    threshold: &mut u8,
+    max_len: usize,
+    action: &AdminAction,
+) -> Result<()> {
+    let mut new_admins = admins.clone();
+    let mut new_threshold = *threshold;
+    match *action {
+        AdminAction::GrantRole { account, .. } => {
+            require!(!new_admins.contains(&account), ErrorCode::InvalidRoleChange);
+            new_admins.push(account);
+        }
+        AdminAction::RevokeRole { account, .. } => {
+            require!(new_admins.contains(&account), ErrorCode::InvalidRoleChange);
+            new_admins.retain(|admin| *admin != account);
+        }
+        AdminAction::SetThreshold { threshold, .. } => new_threshold = threshold,
+        _ => return err!(ErrorCode::InvalidRoleChange),
+    }
+    require!(
+        is_valid_admin_set(&new_admins, new_threshold, max_len),
+        ErrorCode::InvalidRoleChange
+    );
+
+    *admins = new_admins;
+    *threshold = new_threshold;
+    Ok(())
+}
 
-    pub system_program: Program<'info, System>,
+/// Con el consejo activo, las altas de mints, tasas y feeds requieren su propuesta aprobada,
+/// que se consume al aplicarla; sin consejo las hace la autoridad directamente
+fn consume_setup_approval(
+    platform: &Platform,
+    council: Option<&Account<AdminCouncil>>,
+    proposal: Option<&mut Account<AdminProposal>>,
+    executed_by: Pubkey,
+    approved: impl FnOnce(&AdminAction) -> bool,
+) -> Result<()> {
+    let Some(council_key) = platform.admin_council else {
+        return Ok(());
+    };
+    let (Some(council), Some(proposal)) = (council, proposal) else {
+        return err!(ErrorCode::CouncilGoverned);
+    };
+    require!(council.key() == council_key, ErrorCode::CouncilGoverned);
+    require!(approved(&proposal.action), ErrorCode::SetupNotApproved);
+
+    let now = Clock::get()?.unix_timestamp;
+    proposal.consume(council, now)?;
+
+    emit!(AdminActionExecuted {
+        proposal: proposal.key(),
+        index: proposal.index,
+        action: proposal.action.clone(),
+        executed_by,
+        timestamp: now,
+    });
+    Ok(())
 }
 
-#[derive(Accounts)]
-pub struct DepositToEscrowSPL<'info> {
-    #[account(mut)]
-    pub order: Account<'info, Order>,
+/// Aplicar un cambio de configuración o de roles aprobado por el consejo
+#[allow(clippy::too_many_arguments)]
+fn apply_config_change(
+    action: &AdminAction,
+    platform: &mut Platform,
+    council: &mut AdminCouncil,
+    treasury: Option<&mut Treasury>,
+    fee_schedule: Option<&mut FeeSchedule>,
+    supported_mint: Option<&mut SupportedMint>,
+    authority: Pubkey,
+    now: i64,
+) -> Result<()> {
+    match action.clone() {
+        AdminAction::SetMaxPriceDeviation { max_deviation_bps } => {
+            platform.max_price_deviation_bps = max_deviation_bps;
+            emit!(MaxPriceDeviationUpdated {
+                max_price_deviation_bps: max_deviation_bps,
+                authority,
+                timestamp: now,
+            });
+        }
+        AdminAction::SetTradingLimits { level, limits } => {
+            platform.kyc_limits[level as usize] = limits;
+            emit!(TradingLimitsUpdated {
+                level,
+                limits,
+                authority,
+                timestamp: now,
+            });
+        }
+        AdminAction::SetDefaultFeeBearer { fee_bearer } => {
+            platform.default_fee_bearer = fee_bearer;
+            emit!(DefaultFeeBearerUpdated {
+                fee_bearer,
+                authority,
+                timestamp: now,
+            });
+        }
+        AdminAction::SetReferralShare { share_bps } => {
+            platform.referral_share_bps = share_bps;
+            emit!(ReferralShareUpdated {
+                referral_share_bps: share_bps,
+                authority,
+                timestamp: now,
+            });
+        }
+        AdminAction::GrantRole { role: AdminRole::Operator, account } => {
+            let previous_operator = platform.authority;
+            platform.authority = account;
+            emit!(OperatorUpdated {
+                previous_operator,
+                operator: account,
+                authority,
+                timestamp: now,
+            });
+        }
+        AdminAction::GrantRole { role: AdminRole::Guardian, account } => {
+            platform.guardian = account;
+            emit!(GuardianUpdated {
+                guardian: account,
+                authority,
+                timestamp: now,
+            });
+        }
+        AdminAction::GrantRole { role: AdminRole::Compliance, account } => {
+            platform.compliance = account;
+            emit!(ComplianceOfficerUpdated {
+                compliance: account,
+                authority,
+                timestamp: now,
+            });
+        }
+        AdminAction::GrantRole { role: AdminRole::CouncilMember, .. }
+        | AdminAction::RevokeRole { role: AdminRole::CouncilMember, .. }
+        | AdminAction::SetThreshold { role: AdminRole::CouncilMember, .. } => {
+            apply_role_change(&mut council.members, &mut council.threshold, MAX_COUNCIL_MEMBERS, action)?;
+        }
+        AdminAction::GrantRole { role: AdminRole::TreasuryAdmin, .. }
+        | AdminAction::RevokeRole { role: AdminRole::TreasuryAdmin, .. }
+        | AdminAction::SetThreshold { role: AdminRole::TreasuryAdmin, .. } => {
+            let treasury = treasury.ok_or(ErrorCode::MissingAdminActionAccount)?;
+            apply_role_change(&mut treasury.admins, &mut treasury.threshold, MAX_TREASURY_ADMINS, action)?;
+        }
+        // Operador, guardián y cumplimiento son de una sola cuenta: solo se reemplazan
+        AdminAction::RevokeRole { .. } | AdminAction::SetThreshold { .. } => {
+            return err!(ErrorCode::InvalidRoleChange);
+        }
+        AdminAction::SetFeeSchedule { tiers } => {
+            let fee_schedule = fee_schedule.ok_or(ErrorCode::MissingAdminActionAccount)?;
+            fee_schedule.tiers = tiers;
+            fee_schedule.updated_at = now;
+            emit!(FeeScheduleUpdated {
+                tiers: fee_schedule.tiers.clone(),
+                authority,
+                timestamp: now,
+            });
+        }
+        AdminAction::SetTimelockDelay { delay_seconds } => {
+            let previous_delay_seconds = platform.timelock_delay_seconds;
+            platform.timelock_delay_seconds = delay_seconds;
+            emit!(TimelockDelayUpdated {
+                previous_delay_seconds,
+                delay_seconds,
+                authority,
+                timestamp: now,
+            });
+        }
+        AdminAction::UpdateMint {
+            mint,
+            min_trade_amount,
+            max_trade_amount,
+            fee_bps_override,
+            min_fee,
+            max_fee,
+            enabled,
+        } => {
+            let supported_mint = supported_mint
+                .filter(|supported_mint| supported_mint.mint == mint)
+                .ok_or(ErrorCode::MissingAdminActionAccount)?;
+            supported_mint.min_trade_amount = min_trade_amount;
+            supported_mint.max_trade_amount = max_trade_amount;
+            supported_mint.fee_bps_override = fee_bps_override;
+            supported_mint.min_fee = min_fee;
+            supported_mint.max_fee = max_fee;
+            supported_mint.enabled = enabled;
+            emit!(MintConfigUpdated {
+                mint,
+                decimals: supported_mint.decimals,
+                min_trade_amount,
+                max_trade_amount,
+                fee_bps_override,
+                min_fee,
+                max_fee,
+                enabled,
+                authority,
+                timestamp: now,
+            });
+        }
+        AdminAction::WithdrawTreasury { .. }
+        | AdminAction::SeizeFrozenFunds { .. }
+        | AdminAction::RegisterMint { .. }
+        | AdminAction::InitializeReferenceRate { .. }
+        | AdminAction::InitializePriceFeed { .. } => {
+            return err!(ErrorCode::InvalidAdminAction);
+        }
+    }
+    Ok(())
+}
 
-    #[account(
-        mut,
-        seeds = [b"escrow", order.key().as_ref()],
-        bump
-    )]
-    pub escrow: SystemAccount<'info>,
+/// Pagar un retiro del treasury: SOL desde la cuenta Treasury (conservando su renta)
+/// o tokens desde su ATA hacia una cuenta del destinatario
+#[allow(clippy::too_many_arguments)]
+fn pay_from_treasury<'info>(
+    treasury: &Account<'info, Treasury>,
+    treasury_bump: u8,
+    mint_key: Pubkey,
+    amount: u64,
+    recipient: &AccountInfo<'info>,
+    treasury_token_account: Option<&InterfaceAccount<'info, TokenAccount>>,
+    recipient_token_account: Option<&mut InterfaceAccount<'info, TokenAccount>>,
+    mint: Option<&InterfaceAccount<'info, Mint>>,
+    token_program: Option<&Interface<'info, TokenInterface>>,
+) -> Result<()> {
+    if mint_key == native_mint::ID {
+        let rent = Rent::get()?.minimum_balance(treasury.to_account_info().data_len());
+        require!(
+            treasury.get_lamports().saturating_sub(rent) >= amount,
+            ErrorCode::InsufficientTreasuryBalance
+        );
+        treasury.sub_lamports(amount)?;
+        recipient.add_lamports(amount)?;
+        return Ok(());
+    }
 
-    #[account(mut)]
-    pub seller_token_account: Account<'info, TokenAccount>,
+    let (Some(treasury_token_account), Some(recipient_token_account), Some(mint), Some(token_program)) =
+        (treasury_token_account, recipient_token_account, mint, token_program)
+    else {
+        return err!(ErrorCode::MissingTokenAccounts);
+    };
+    require!(
+        mint.key() == mint_key && recipient_token_account.mint == mint_key,
+        ErrorCode::InvalidMint
+    );
+    require!(
+        recipient_token_account.owner == recipient.key()
+            && treasury_token_account.key()
+                == get_associated_token_address_with_program_id(&treasury.key(), &mint_key, &token_program.key()),
+        ErrorCode::InvalidRecipientTokenAccount
+    );
+    require!(
+        treasury_token_account.amount >= amount,
+        ErrorCode::InsufficientTreasuryBalance
+    );
+
+    let treasury_seeds = &[b"treasury".as_ref(), &[treasury_bump]];
+    transfer_tokens(
+        token_program,
+        treasury_token_account,
+        recipient_token_account,
+        mint,
+        treasury.to_account_info(),
+        &[&treasury_seeds[..]],
+        amount,
+    )?;
+    Ok(())
+}
 
-    #[account(mut)]
-    pub escrow_token_account: Account<'info, TokenAccount>,
+/// Transferir tokens (Token o Token-2022) con transfer_checked
+/// Regresa el monto que llegó al destino, neto de la extensión transfer-fee
+fn transfer_tokens<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    to: &mut InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    authority: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    amount: u64,
+) -> Result<u64> {
+    let balance_before = to.amount;
+
+    let cpi_accounts = TransferChecked {
+        from: from.to_account_info(),
+        mint: mint.to_account_info(),
+        to: to.to_account_info(),
+        authority,
+    };
+
+    let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer_seeds);
+
+    token_interface::transfer_checked(cpi_ctx, amount, mint.decimals)?;
+
+    to.reload()?;
+    to.amount
+        .checked_sub(balance_before)
+        .ok_or_else(|| error!(ErrorCode::MathOverflow))
+}
 
-    #[account(mut)]
-    pub seller: Signer<'info>,
+/// Calcular las recompensas de referidos de una liquidación
+/// Cada participante con referidor aporta según su tarifa; si ambos comparten
+/// referidor, la recompensa completa se acredita en la primera cuenta
+fn referral_rewards<'a, 'info>(
+    platform: &Platform,
+    fee_amount: u64,
+    parties: [(&UserProfile, u64, Option<&'a mut Account<'info, ReferralAccount>>); 2],
+) -> Result<Vec<ReferralReward<'a, 'info>>> {
+    let total_fee_bps = parties[0].1 + parties[1].1;
+    let mut rewards: Vec<ReferralReward> = Vec::with_capacity(2);
+
+    for (profile, party_fee_bps, account) in parties {
+        let Some(referrer) = profile.referrer else {
+            require!(account.is_none(), ErrorCode::InvalidReferralAccount);
+            continue;
+        };
+        let amount = platform.referral_reward(fee_amount, party_fee_bps, total_fee_bps)?;
 
-    pub token_program: Program<'info, Token>,
+        match (rewards.iter_mut().find(|reward| reward.referrer == referrer), account) {
+            (Some(reward), None) => reward.amount += amount,
+            (None, Some(account)) => rewards.push(ReferralReward { account, referrer, amount }),
+            _ => return err!(ErrorCode::InvalidReferralAccount),
+        }
+    }
+
+    rewards.retain(|reward| reward.amount > 0);
+    Ok(rewards)
 }
 
-#[derive(Accounts)]
-pub struct ConfirmFiatPayment<'info> {
-    #[account(mut)]
-    pub order: Account<'info, Order>,
+/// Pagar y acreditar las recompensas de referidos de una liquidación
+/// `pay` mueve la recompensa a su destino y regresa lo que realmente llegó (neto de transfer-fee)
+fn credit_referral_rewards<'info>(
+    rewards: Vec<ReferralReward<'_, 'info>>,
+    mint: Pubkey,
+    settlement: Pubkey,
+    mut pay: impl FnMut(&Account<'info, ReferralAccount>, u64) -> Result<u64>,
+) -> Result<()> {
+    for reward in rewards {
+        let credited = pay(reward.account, reward.amount)?;
+        reward.account.credit(reward.referrer, mint, credited)?;
+
+        emit!(ReferralRewardAccrued {
+            referral_account: reward.account.key(),
+            referrer: reward.referrer,
+            mint,
+            settlement,
+            amount: credited,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+    }
+    Ok(())
+}
 
-    #[account(mut)]
-    pub buyer: Signer<'info>,
+// ============================================================================
+// ENUMS
+// ============================================================================
+
+/// Nivel KYC del perfil, convertido desde kyc_nft::VerificationLevel al leer el KycRecord
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum VerificationLevel {
+    Basic,      // Verificación básica (email, teléfono)
+    Standard,   // Verificación estándar (+ ID)
+    Enhanced,   // Verificación mejorada (+ prueba de domicilio)
+    Premium,    // Verificación premium (+ biometría)
 }
 
-#[derive(Accounts)]
-pub struct ReleaseFundsNative<'info> {
-    #[account(mut)]
-    pub order: Account<'info, Order>,
+impl From<kyc_nft::VerificationLevel> for VerificationLevel {
+    fn from(level: kyc_nft::VerificationLevel) -> Self {
+        match level {
+            kyc_nft::VerificationLevel::Basic => VerificationLevel::Basic,
+            kyc_nft::VerificationLevel::Standard => VerificationLevel::Standard,
+            kyc_nft::VerificationLevel::Enhanced => VerificationLevel::Enhanced,
+            kyc_nft::VerificationLevel::Premium => VerificationLevel::Premium,
+        }
+    }
+}
 
-    #[account(
-        mut,
-        seeds = [b"escrow", order.key().as_ref()],
-        bump
-    )]
-    pub escrow: SystemAccount<'info>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum PaymentMethod {
+    STP,        // Sistema de Transferencias y Pagos
+    SPEI,       // Sistema de Pagos Electrónicos Interbancarios
+    Cash,       // Efectivo en punto físico
+}
 
-    #[account(
-        seeds = [b"platform"],
-        bump
-    )]
-    pub platform: Account<'info, Platform>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum OrderSide {
+    Sell,       // El maker vende crypto, un buyer acepta
+    Buy,        // El maker compra crypto, un seller llena la orden
+}
 
-    /// CHECK: Buyer que recibe los fondos
-    #[account(mut)]
-    pub buyer: AccountInfo<'info>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum FeeBearer {
+    Buyer,      // Se descuenta de lo que recibe el buyer
+    Seller,     // El seller la deposita además del monto
+    Split,      // Mitad y mitad
+}
 
-    /// CHECK: Treasury de la plataforma
-    #[account(mut)]
-    pub treasury: AccountInfo<'info>,
+impl FeeBearer {
+    /// Parte de la comisión que aporta el seller
+    pub fn seller_share(&self, fee_amount: u64) -> u64 {
+        match self {
+            FeeBearer::Buyer => 0,
+            FeeBearer::Seller => fee_amount,
+            FeeBearer::Split => fee_amount / 2,
+        }
+    }
+}
 
-    #[account(
-        mut,
-        seeds = [b"user_profile", order.seller.as_ref()],
-        bump
-    )]
-    pub seller_profile: Account<'info, UserProfile>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum UserStatus {
+    Active,     // Puede operar
+    Suspended,  // Sin operaciones nuevas hasta `suspended_until`
+    Banned,     // Sin operaciones nuevas hasta que se reactive
+}
 
-    #[account(
-        mut,
-        seeds = [b"user_profile", order.buyer.unwrap().as_ref()],
-        bump
-    )]
-    pub buyer_profile: Account<'info, UserProfile>,
+/// Roles que el consejo puede otorgar o revocar
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum AdminRole {
+    Operator,       // Autoridad de la plataforma (oráculo y árbitro)
+    CouncilMember,  // Miembro del consejo de administración
+    TreasuryAdmin,  // Administrador del treasury
+    Guardian,       // Controla los circuit breakers
+    Compliance,     // Suspende, banea y reactiva usuarios
+}
 
-    pub authority: Signer<'info>,
+impl AdminRole {
+    /// Roles M-de-N con altas, bajas y umbral; el resto son de una sola cuenta
+    pub fn is_admin_set(&self) -> bool {
+        matches!(self, AdminRole::CouncilMember | AdminRole::TreasuryAdmin)
+    }
 }
 
-#[derive(Accounts)]
-pub struct ReleaseFundsSPL<'info> {
-    #[account(mut)]
-    pub order: Account<'info, Order>,
+/// Acción privilegiada que el consejo aprueba antes de ejecutarse
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub enum AdminAction {
+    SetMaxPriceDeviation { max_deviation_bps: u64 },
+    SetTradingLimits { level: VerificationLevel, limits: TradingLimits },
+    SetDefaultFeeBearer { fee_bearer: FeeBearer },
+    SetReferralShare { share_bps: u64 },
+    SetFeeSchedule {
+        #[max_len(MAX_FEE_TIERS)]
+        tiers: Vec<FeeTier>,
+    },
+    SetTimelockDelay { delay_seconds: i64 },
+    GrantRole { role: AdminRole, account: Pubkey },
+    RevokeRole { role: AdminRole, account: Pubkey },
+    SetThreshold { role: AdminRole, threshold: u8 },
+    WithdrawTreasury { mint: Pubkey, amount: u64, recipient: Pubkey }, // Native mint para SOL
+    SeizeFrozenFunds { order: Pubkey, destination: Pubkey }, // Se aplica con transfer_frozen_funds
+    UpdateMint {
+        mint: Pubkey,
+        min_trade_amount: u64,
+        max_trade_amount: u64,
+        fee_bps_override: Option<u64>,
+        min_fee: u64,
+        max_fee: Option<u64>,
+        enabled: bool,
+    },
+    // Altas que se aplican con register_mint, initialize_reference_rate e initialize_price_feed
+    RegisterMint {
+        mint: Pubkey,
+        min_trade_amount: u64,
+        max_trade_amount: u64,
+        fee_bps_override: Option<u64>,
+        min_fee: u64,
+        max_fee: Option<u64>,
+    },
+    InitializeReferenceRate { mint: Pubkey, max_staleness_seconds: i64 },
+    InitializePriceFeed { price_feed: Pubkey, mint: Pubkey, updater: Pubkey }, // PDA del feed_id
+}
 
-    #[account(
-        mut,
-        seeds = [b"escrow", order.key().as_ref()],
-        bump
-    )]
-    pub escrow: SystemAccount<'info>,
+impl AdminAction {
+    /// Validar los parámetros de la acción (los cambios de rol se validan al ejecutar)
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            AdminAction::SetMaxPriceDeviation { max_deviation_bps } => {
+                require!(
+                    *max_deviation_bps > 0 && *max_deviation_bps <= 10_000,
+                    ErrorCode::InvalidAmount
+                );
+            }
+            AdminAction::SetTradingLimits { limits, .. } => limits.validate()?,
+            AdminAction::SetReferralShare { share_bps } => {
+                require!(*share_bps <= MAX_REFERRAL_SHARE_BPS, ErrorCode::InvalidAmount);
+            }
+            AdminAction::RevokeRole { role, .. } | AdminAction::SetThreshold { role, .. } if !role.is_admin_set() => {
+                return err!(ErrorCode::InvalidRoleChange);
+            }
+            AdminAction::SetFeeSchedule { tiers } => FeeSchedule::validate_tiers(tiers)?,
+            AdminAction::SetTimelockDelay { delay_seconds } => {
+                require!(
+                    (MIN_TIMELOCK_DELAY_SECONDS..=MAX_TIMELOCK_DELAY_SECONDS).contains(delay_seconds),
+                    ErrorCode::InvalidTimelockDelay
+                );
+            }
+            AdminAction::WithdrawTreasury { amount, .. } => {
+                require!(*amount > 0, ErrorCode::InvalidAmount);
+            }
+            AdminAction::UpdateMint { min_trade_amount, max_trade_amount, fee_bps_override, min_fee, max_fee, .. }
+            | AdminAction::RegisterMint { min_trade_amount, max_trade_amount, fee_bps_override, min_fee, max_fee, .. } => {
+                require!(
+                    *min_trade_amount > 0 && min_trade_amount <= max_trade_amount,
+                    ErrorCode::InvalidTradeLimits
+                );
+                require!(fee_bps_override.is_none_or(|fee| fee <= 10_000), ErrorCode::InvalidAmount);
+                require!(max_fee.is_none_or(|max_fee| *min_fee <= max_fee), ErrorCode::InvalidFeeSchedule);
+            }
+            AdminAction::InitializeReferenceRate { max_staleness_seconds, .. } => {
+                require!(*max_staleness_seconds > 0, ErrorCode::InvalidAmount);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
 
-    #[account(
-        seeds = [b"platform"],
-        bump
-    )]
-    pub platform: Account<'info, Platform>,
+    /// Cambios que afectan a los usuarios o a quién controla la plataforma:
+    /// se programan con timelock en vez de aplicarse al ejecutar la propuesta
+    /// (las altas no tocan mints, tasas ni feeds existentes)
+    pub fn is_timelocked(&self) -> bool {
+        !matches!(
+            self,
+            AdminAction::SetReferralShare { .. }
+                | AdminAction::WithdrawTreasury { .. }
+                | AdminAction::RegisterMint { .. }
+                | AdminAction::InitializeReferenceRate { .. }
+                | AdminAction::InitializePriceFeed { .. }
+        )
+    }
+}
 
-    #[account(mut)]
-    pub escrow_token_account: Account<'info, TokenAccount>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum OrderStatus {
+    Open,              // Orden creada, esperando buyer
+    Accepted,          // Buyer aceptó la orden
+    Funded,            // Seller depositó crypto en escrow
+    PaymentConfirmed,  // Buyer confirmó pago fiat
+    Completed,         // Transacción completada
+    Cancelled,         // Orden cancelada
+    Disputed,          // En disputa
+    PartialRefund,     // Reembolso parcial (split)
+    Frozen,            // Escrow congelado por retención legal
+    Seized,            // Fondos transferidos por orden judicial
+}
 
-    #[account(mut)]
-    pub buyer_token_account: Account<'info, TokenAccount>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum DisputeStatus {
+    Open,
+    Resolved,
+}
 
-    #[account(mut)]
-    pub treasury_token_account: Account<'info, TokenAccount>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub enum DisputeResolution {
+    FavorBuyer,   // Fondos van al buyer
+    FavorSeller,  // Fondos regresan al seller
+    Split,        // División 50/50
+}
 
-    #[account(
-        mut,
-        seeds = [b"user_profile", order.seller.as_ref()],
-        bump
-    )]
-    pub seller_profile: Account<'info, UserProfile>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum TradeStatus {
+    Open,              // Inventario reservado, esperando pago fiat
+    PaymentConfirmed,  // Buyer confirmó pago fiat
+    Completed,         // Fondos liberados al buyer
+    Cancelled,         // Monto devuelto al anuncio
+}
 
-    #[account(
-        mut,
-        seeds = [b"user_profile", order.buyer.unwrap().as_ref()],
-        bump
-    )]
-    pub buyer_profile: Account<'info, UserProfile>,
+// ============================================================================
+// EVENTOS
+// ============================================================================
 
-    pub authority: Signer<'info>,
+#[event]
+pub struct PlatformInitialized {
+    pub platform: Pubkey,
+    pub authority: Pubkey,
+    pub treasury: Pubkey,
+    pub fee_bps: u64,
+    pub max_price_deviation_bps: u64,
+    pub timestamp: i64,
+}
 
-    pub token_program: Program<'info, Token>,
+#[event]
+pub struct MaxPriceDeviationUpdated {
+    pub max_price_deviation_bps: u64,
+    pub authority: Pubkey,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct OpenDispute<'info> {
-    #[account(
-        init,
-        payer = initiator,
-        space = 8 + Dispute::INIT_SPACE,
-        seeds = [b"dispute", order.key().as_ref()],
-        bump
-    )]
-    pub dispute: Account<'info, Dispute>,
+#[event]
+pub struct DefaultFeeBearerUpdated {
+    pub fee_bearer: FeeBearer,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
 
-    #[account(mut)]
-    pub order: Account<'info, Order>,
+#[event]
+pub struct ReferralShareUpdated {
+    pub referral_share_bps: u64,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
 
-    #[account(
-        mut,
-        seeds = [b"user_profile", order.seller.as_ref()],
-        bump
-    )]
-    pub seller_profile: Account<'info, UserProfile>,
+#[event]
+pub struct TradingLimitsUpdated {
+    pub level: VerificationLevel,
+    pub limits: TradingLimits,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
 
-    #[account(
-        mut,
-        seeds = [b"user_profile", order.buyer.unwrap().as_ref()],
-        bump
-    )]
-    pub buyer_profile: Account<'info, UserProfile>,
+/// Alta o modificación de un mint soportado
+#[event]
+pub struct MintConfigUpdated {
+    pub mint: Pubkey,
+    pub decimals: u8,
+    pub min_trade_amount: u64,
+    pub max_trade_amount: u64,
+    pub fee_bps_override: Option<u64>,
+    pub min_fee: u64,
+    pub max_fee: Option<u64>,
+    pub enabled: bool,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
 
-    #[account(mut)]
-    pub initiator: Signer<'info>,
+#[event]
+pub struct FeeScheduleUpdated {
+    pub tiers: Vec<FeeTier>,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
 
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct TimelockDelayUpdated {
+    pub previous_delay_seconds: i64,
+    pub delay_seconds: i64,
+    pub authority: Pubkey,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct ResolveDispute<'info> {
-    #[account(mut)]
-    pub dispute: Account<'info, Dispute>,
+#[event]
+pub struct UserProfileCreated {
+    pub profile: Pubkey,
+    pub owner: Pubkey,
+    pub kyc_level: VerificationLevel,
+    pub kyc_nft_mint: Option<Pubkey>,
+    pub referrer: Option<Pubkey>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OrderCreated {
+    pub order: Pubkey,
+    pub order_id: String,
+    pub side: OrderSide,
+    pub maker: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub amount_mxn: u64,             // Cotizado contra el feed en órdenes flotantes
+    pub payment_method: PaymentMethod,
+    pub premium_bps: Option<i32>,
+    pub prefunded: bool,
+    pub fee_bearer: FeeBearer,
+    pub timestamp: i64,
+}
+
+/// Orden de venta aceptada por un buyer u orden de compra llenada por un seller
+#[event]
+pub struct OrderAccepted {
+    pub order: Pubkey,
+    pub order_id: String,
+    pub seller: Pubkey,
+    pub buyer: Pubkey,
+    pub taker: Pubkey,               // Quien tomó la orden del maker
+    pub amount: u64,
+    pub amount_mxn: u64,
+    pub maker_fee_bps: u64,
+    pub taker_fee_bps: u64,
+    pub timestamp: i64,
+}
 
-    #[account(mut)]
-    pub order: Account<'info, Order>,
+#[event]
+pub struct OrderFunded {
+    pub order: Pubkey,
+    pub order_id: String,
+    pub seller: Pubkey,
+    pub buyer: Option<Pubkey>,       // None en órdenes de venta pre-fondeadas
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub escrowed_amount: u64,        // Neto de comisiones de transferencia del token
+    pub timestamp: i64,
+}
 
-    #[account(
-        seeds = [b"platform"],
-        bump
-    )]
-    pub platform: Account<'info, Platform>,
+#[event]
+pub struct FiatPaymentConfirmed {
+    pub order: Pubkey,
+    pub order_id: String,
+    pub buyer: Pubkey,
+    pub stp_transaction_id: String,
+    pub timestamp: i64,
+}
 
-    pub resolver: Signer<'info>,
+#[event]
+pub struct FundsReleased {
+    pub order: Pubkey,
+    pub order_id: String,
+    pub seller: Pubkey,
+    pub buyer: Pubkey,
+    pub mint: Pubkey,
+    pub amount_mxn: u64,
+    pub buyer_amount: u64,
+    pub fee_amount: u64,
+    pub fee_bps: u64,
+    pub fee_bearer: FeeBearer,
+    pub referral_amount: u64,        // Parte de la comisión para referidores
+    pub released_by: Pubkey,         // Oráculo o cualquiera después del timeout
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct CancelOrderNative<'info> {
-    #[account(mut)]
-    pub order: Account<'info, Order>,
+#[event]
+pub struct OrderCancelled {
+    pub order: Pubkey,
+    pub order_id: String,
+    pub cancelled_by: Pubkey,
+    pub refunded_to: Option<Pubkey>, // None si la orden no tenía fondos
+    pub refunded_amount: u64,
+    pub timestamp: i64,
+}
 
-    #[account(
-        mut,
-        seeds = [b"escrow", order.key().as_ref()],
-        bump
-    )]
-    pub escrow: SystemAccount<'info>,
+#[event]
+pub struct DisputeOpened {
+    pub order: Pubkey,
+    pub order_id: String,
+    pub dispute: Pubkey,
+    pub initiator: Pubkey,
+    pub reason: String,
+    pub evidence: String,
+    pub deposit_lamports: u64,
+    pub timestamp: i64,
+}
 
-    #[account(mut)]
-    pub seller: Signer<'info>,
+#[event]
+pub struct DisputeResolved {
+    pub order: Pubkey,
+    pub order_id: String,
+    pub dispute: Pubkey,
+    pub resolver: Pubkey,
+    pub resolution: DisputeResolution,
+    pub resolution_notes: String,
+    pub seller_amount: u64,
+    pub buyer_amount: u64,
+    pub fee_amount: u64,
+    pub referral_amount: u64,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct CancelOrderSPL<'info> {
-    #[account(mut)]
-    pub order: Account<'info, Order>,
+/// Liquidación 50/50 de los fondos de una orden en disputa
+#[event]
+pub struct DisputeSplitSettled {
+    pub order: Pubkey,
+    pub order_id: String,
+    pub seller: Pubkey,
+    pub buyer: Pubkey,
+    pub resolver: Pubkey,
+    pub mint: Pubkey,
+    pub seller_amount: u64,
+    pub buyer_amount: u64,
+    pub fee_amount: u64,
+    pub referral_amount: u64,
+    pub timestamp: i64,
+}
 
-    #[account(
-        mut,
-        seeds = [b"escrow", order.key().as_ref()],
-        bump
-    )]
-    pub escrow: SystemAccount<'info>,
+#[event]
+pub struct OracleStatusUpdated {
+    pub order: Pubkey,
+    pub order_id: String,
+    pub oracle: Pubkey,
+    pub confirmed: bool,
+    pub timestamp: i64,
+}
 
-    #[account(mut)]
-    pub escrow_token_account: Account<'info, TokenAccount>,
+#[event]
+pub struct ReferenceRateInitialized {
+    pub mint: Pubkey,
+    pub max_staleness_seconds: i64,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
 
-    #[account(mut)]
-    pub seller_token_account: Account<'info, TokenAccount>,
+#[event]
+pub struct ReferenceRateUpdated {
+    pub mint: Pubkey,
+    pub rate_mxn: u64,
+    pub oracle: Pubkey,
+    pub timestamp: i64,
+}
 
-    #[account(mut)]
-    pub seller: Signer<'info>,
+#[event]
+pub struct PriceFeedInitialized {
+    pub price_feed: Pubkey,
+    pub feed_id: String,
+    pub mint: Pubkey,
+    pub updater: Pubkey,
+    pub timestamp: i64,
+}
 
-    pub token_program: Program<'info, Token>,
+#[event]
+pub struct PriceFeedUpdated {
+    pub price_feed: Pubkey,
+    pub feed_id: String,
+    pub mint: Pubkey,
+    pub price_mxn: u64,
+    pub updater: Pubkey,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct ResolveDisputeSplitNative<'info> {
-    #[account(mut)]
-    pub order: Account<'info, Order>,
+#[event]
+pub struct AdCreated {
+    pub ad: Pubkey,
+    pub ad_id: String,
+    pub merchant: Pubkey,
+    pub mint: Pubkey,
+    pub price_mxn: u64,
+    pub min_trade_mxn: u64,
+    pub max_trade_mxn: u64,
+    pub timestamp: i64,
+}
 
-    #[account(
-        mut,
-        seeds = [b"escrow", order.key().as_ref()],
-        bump
-    )]
-    pub escrow: SystemAccount<'info>,
+#[event]
+pub struct AdUpdated {
+    pub ad: Pubkey,
+    pub ad_id: String,
+    pub merchant: Pubkey,
+    pub price_mxn: u64,
+    pub min_trade_mxn: u64,
+    pub max_trade_mxn: u64,
+    pub is_active: bool,
+    pub timestamp: i64,
+}
 
-    #[account(
-        seeds = [b"platform"],
-        bump
-    )]
-    pub platform: Account<'info, Platform>,
+/// Depósito o retiro de inventario libre de un anuncio
+#[event]
+pub struct AdInventoryChanged {
+    pub ad: Pubkey,
+    pub ad_id: String,
+    pub merchant: Pubkey,
+    pub deposited: u64,
+    pub withdrawn: u64,
+    pub available_amount: u64,       // Inventario libre después del movimiento
+    pub timestamp: i64,
+}
 
-    /// CHECK: Seller que recibe mitad de fondos
-    #[account(mut)]
-    pub seller: AccountInfo<'info>,
+#[event]
+pub struct TradeOpened {
+    pub trade: Pubkey,
+    pub ad: Pubkey,
+    pub trade_index: u64,
+    pub merchant: Pubkey,
+    pub buyer: Pubkey,
+    pub amount: u64,
+    pub amount_mxn: u64,
+    pub payment_method: PaymentMethod,
+    pub maker_fee_bps: u64,
+    pub taker_fee_bps: u64,
+    pub fee_bearer: FeeBearer,
+    pub fee_amount: u64,
+    pub timestamp: i64,
+}
 
-    /// CHECK: Buyer que recibe mitad de fondos
-    #[account(mut)]
-    pub buyer: AccountInfo<'info>,
+#[event]
+pub struct TradePaymentConfirmed {
+    pub trade: Pubkey,
+    pub ad: Pubkey,
+    pub trade_index: u64,
+    pub buyer: Pubkey,
+    pub stp_transaction_id: String,
+    pub timestamp: i64,
+}
 
-    /// CHECK: Treasury de la plataforma
-    #[account(mut)]
-    pub treasury: AccountInfo<'info>,
+#[event]
+pub struct TradeReleased {
+    pub trade: Pubkey,
+    pub ad: Pubkey,
+    pub trade_index: u64,
+    pub merchant: Pubkey,
+    pub buyer: Pubkey,
+    pub mint: Pubkey,
+    pub amount_mxn: u64,
+    pub buyer_amount: u64,
+    pub fee_amount: u64,
+    pub fee_bps: u64,
+    pub referral_amount: u64,
+    pub released_by: Pubkey,
+    pub timestamp: i64,
+}
 
-    pub resolver: Signer<'info>,
+#[event]
+pub struct TradeCancelled {
+    pub trade: Pubkey,
+    pub ad: Pubkey,
+    pub trade_index: u64,
+    pub cancelled_by: Pubkey,
+    pub returned_amount: u64,        // Monto que regresa al inventario del anuncio
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct ResolveDisputeSplitSPL<'info> {
-    #[account(mut)]
-    pub order: Account<'info, Order>,
+#[event]
+pub struct TreasuryInitialized {
+    pub treasury: Pubkey,
+    pub admins: Vec<Pubkey>,
+    pub threshold: u8,
+    pub timestamp: i64,
+}
 
-    #[account(
-        mut,
-        seeds = [b"escrow", order.key().as_ref()],
-        bump
-    )]
-    pub escrow: SystemAccount<'info>,
+#[event]
+pub struct TreasuryWithdrawalProposed {
+    pub withdrawal: Pubkey,
+    pub index: u64,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub recipient: Pubkey,
+    pub proposer: Pubkey,
+    pub timestamp: i64,
+}
 
-    #[account(
-        seeds = [b"platform"],
-        bump
-    )]
-    pub platform: Account<'info, Platform>,
+#[event]
+pub struct TreasuryWithdrawalApproved {
+    pub withdrawal: Pubkey,
+    pub index: u64,
+    pub admin: Pubkey,
+    pub approvals: u8,
+    pub timestamp: i64,
+}
 
-    #[account(mut)]
-    pub escrow_token_account: Account<'info, TokenAccount>,
+#[event]
+pub struct TreasuryWithdrawn {
+    pub withdrawal: Pubkey,
+    pub index: u64,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub recipient: Pubkey,
+    pub executed_by: Pubkey,
+    pub timestamp: i64,
+}
 
-    #[account(mut)]
-    pub seller_token_account: Account<'info, TokenAccount>,
+#[event]
+pub struct AdminCouncilInitialized {
+    pub council: Pubkey,
+    pub members: Vec<Pubkey>,
+    pub threshold: u8,
+    pub timestamp: i64,
+}
 
-    #[account(mut)]
-    pub buyer_token_account: Account<'info, TokenAccount>,
+#[event]
+pub struct AdminActionProposed {
+    pub proposal: Pubkey,
+    pub index: u64,
+    pub action: AdminAction,
+    pub proposer: Pubkey,
+    pub expires_at: i64,
+    pub timestamp: i64,
+}
 
-    #[account(mut)]
-    pub treasury_token_account: Account<'info, TokenAccount>,
+#[event]
+pub struct AdminActionApproved {
+    pub proposal: Pubkey,
+    pub index: u64,
+    pub member: Pubkey,
+    pub approvals: u8,
+    pub timestamp: i64,
+}
 
-    pub resolver: Signer<'info>,
+#[event]
+pub struct AdminActionExecuted {
+    pub proposal: Pubkey,
+    pub index: u64,
+    pub action: AdminAction,
+    pub executed_by: Pubkey,
+    pub timestamp: i64,
+}
 
-    pub token_program: Program<'info, Token>,
+/// Rotación del operador (`platform.authority`: oráculo, moderación y setup)
+#[event]
+pub struct OperatorUpdated {
+    pub previous_operator: Pubkey,
+    pub operator: Pubkey,
+    pub authority: Pubkey,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct UpdateOracleStatus<'info> {
-    #[account(mut)]
-    pub order: Account<'info, Order>,
+#[event]
+pub struct GuardianUpdated {
+    pub guardian: Pubkey,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
 
-    #[account(
-        seeds = [b"platform"],
-        bump
-    )]
-    pub platform: Account<'info, Platform>,
+#[event]
+pub struct CircuitBreakerUpdated {
+    pub paused_operations: u16,
+    pub guardian: Pubkey,
+    pub timestamp: i64,
+}
 
-    pub oracle: Signer<'info>,
+#[event]
+pub struct MintCircuitBreakerUpdated {
+    pub mint: Pubkey,
+    pub trading_paused: bool,
+    pub guardian: Pubkey,
+    pub timestamp: i64,
 }
 
-// ============================================================================
-// ESTRUCTURAS DE DATOS
-// ============================================================================
+#[event]
+pub struct ChangeScheduled {
+    pub pending_change: Pubkey,
+    pub proposal: Pubkey,
+    pub action: AdminAction,
+    pub eta: i64,
+    pub timestamp: i64,
+}
 
-#[account]
-#[derive(InitSpace)]
-pub struct Platform {
-    pub authority: Pubkey,          // Autoridad de la plataforma
-    pub treasury: Pubkey,            // Cuenta para recibir comisiones
-    pub fee_bps: u64,                // Comisión en basis points (100 = 1%)
-    pub total_volume: u64,           // Volumen total procesado
-    pub total_transactions: u64,     // Total de transacciones
-    pub is_active: bool,             // Estado de la plataforma
+#[event]
+pub struct ChangeExecuted {
+    pub pending_change: Pubkey,
+    pub proposal: Pubkey,
+    pub action: AdminAction,
+    pub timestamp: i64,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct UserProfile {
-    pub owner: Pubkey,               // Wallet del usuario
-    pub kyc_verified: bool,          // Si el usuario pasó KYC
-    pub kyc_nft_mint: Option<Pubkey>, // NFT de verificación KYC
-    pub total_trades: u64,           // Total de trades
-    pub successful_trades: u64,      // Trades exitosos
-    pub disputed_trades: u64,        // Trades en disputa
-    pub is_active: bool,             // Si el usuario está activo
-    pub created_at: i64,             // Timestamp de creación
+#[event]
+pub struct ChangeCancelled {
+    pub pending_change: Pubkey,
+    pub proposal: Pubkey,
+    pub cancelled_by: Pubkey,
+    pub timestamp: i64,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Order {
-    #[max_len(50)]
-    pub order_id: String,            // ID único de la orden
-    pub seller: Pubkey,              // Vendedor de crypto
-    pub buyer: Option<Pubkey>,       // Comprador de crypto
-    pub amount: u64,                 // Cantidad en tokens/SOL
-    pub amount_mxn: u64,             // Cantidad en MXN (con decimales)
-    pub token_type: TokenType,       // Tipo de token (SOL, USDC, USDT)
-    pub payment_method: PaymentMethod, // Método de pago
-    pub status: OrderStatus,         // Estado de la orden
-    #[max_len(100)]
-    pub stp_reference: String,       // Referencia STP para el pago
-    #[max_len(100)]
-    pub stp_transaction_id: Option<String>, // ID de transacción STP
-    pub stp_oracle_confirmed: bool,  // Si el oráculo confirmó el pago STP
-    pub escrow: Pubkey,              // Cuenta escrow
-    pub created_at: i64,             // Timestamp de creación
-    pub accepted_at: Option<i64>,    // Timestamp de aceptación
-    pub funded_at: Option<i64>,      // Timestamp de fondeo
-    pub payment_confirmed_at: Option<i64>, // Timestamp de confirmación de pago
-    pub completed_at: Option<i64>,   // Timestamp de completado
+/// Suspensión, baneo o reactivación de un usuario
+#[event]
+pub struct UserStatusChanged {
+    pub user: Pubkey,
+    pub status: UserStatus,
+    pub suspended_until: Option<i64>,
+    pub reason_code: u16,
+    pub actor: Pubkey,
+    pub timestamp: i64,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Dispute {
-    pub order: Pubkey,               // Orden en disputa
-    pub initiator: Pubkey,           // Quien inició la disputa
-    #[max_len(500)]
-    pub reason: String,              // Razón de la disputa
-    #[max_len(1000)]
-    pub evidence: String,            // Evidencia (URLs, hashes, etc.)
-    pub status: DisputeStatus,       // Estado de la disputa
-    pub resolver: Option<Pubkey>,    // Quien resolvió la disputa
-    pub resolution: Option<DisputeResolution>, // Resolución
-    #[max_len(1000)]
-    pub resolution_notes: Option<String>, // Notas de la resolución
-    pub created_at: i64,             // Timestamp de creación
-    pub resolved_at: Option<i64>,    // Timestamp de resolución
+#[event]
+pub struct ComplianceOfficerUpdated {
+    pub compliance: Pubkey,
+    pub authority: Pubkey,
+    pub timestamp: i64,
 }
 
-// ============================================================================
-// ENUMS
-// ============================================================================
+/// Dirección agregada al blocklist
+#[event]
+pub struct BlockedAddressAdded {
+    pub address: Pubkey,
+    pub reason_code: u16,
+    pub blocked_by: Pubkey,
+    pub timestamp: i64,
+}
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
-pub enum TokenType {
-    SOL,
-    USDC,
-    USDT,
+#[event]
+pub struct BlockedAddressRemoved {
+    pub address: Pubkey,
+    pub unblocked_by: Pubkey,
+    pub timestamp: i64,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
-pub enum PaymentMethod {
-    STP,        // Sistema de Transferencias y Pagos
-    SPEI,       // Sistema de Pagos Electrónicos Interbancarios
-    Cash,       // Efectivo en punto físico
+/// Escrow de una orden congelado por retención legal
+#[event]
+pub struct LegalHoldPlaced {
+    pub order: Pubkey,
+    pub order_id: String,
+    pub case_reference: String,
+    pub placed_by: Pubkey,
+    pub timestamp: i64,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
-pub enum OrderStatus {
-    Open,              // Orden creada, esperando buyer
-    Accepted,          // Buyer aceptó la orden
-    Funded,            // Seller depositó crypto en escrow
-    PaymentConfirmed,  // Buyer confirmó pago fiat
-    Completed,         // Transacción completada
-    Cancelled,         // Orden cancelada
-    Disputed,          // En disputa
-    PartialRefund,     // Reembolso parcial (split)
+#[event]
+pub struct LegalHoldLifted {
+    pub order: Pubkey,
+    pub order_id: String,
+    pub case_reference: String,
+    pub restored_status: OrderStatus,
+    pub lifted_by: Pubkey,
+    pub timestamp: i64,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
-pub enum DisputeStatus {
-    Open,
-    Resolved,
+/// Fondos congelados transferidos por orden judicial
+#[event]
+pub struct FrozenFundsTransferred {
+    pub order: Pubkey,
+    pub order_id: String,
+    pub case_reference: String,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub pending_change: Pubkey,      // Aprobación del consejo que autorizó la transferencia
+    pub authority: Pubkey,
+    pub timestamp: i64,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
-pub enum DisputeResolution {
-    FavorBuyer,   // Fondos van al buyer
-    FavorSeller,  // Fondos regresan al seller
-    Split,        // División 50/50
+/// Recompensa acreditada a un referidor por una orden o trade liquidado
+#[event]
+pub struct ReferralRewardAccrued {
+    pub referral_account: Pubkey,
+    pub referrer: Pubkey,
+    pub mint: Pubkey,
+    pub settlement: Pubkey,          // Orden o trade
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReferralRewardsClaimed {
+    pub referral_account: Pubkey,
+    pub referrer: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
 }
 
 // ============================================================================
@@ -1104,7 +7042,7 @@ pub enum ErrorCode {
     #[msg("Invalid amount")]
     InvalidAmount,
 
-    #[msg("Amount exceeds maximum limit of 9,000 MXN")]
+    #[msg("Amount exceeds the per-trade limit for the KYC level")]
     ExceedsMaxLimit,
 
     #[msg("KYC verification required")]
@@ -1136,4 +7074,187 @@ pub enum ErrorCode {
 
     #[msg("Dispute already resolved")]
     DisputeAlreadyResolved,
+
+    #[msg("Token accounts are required for SPL orders")]
+    MissingTokenAccounts,
+
+    #[msg("Escrow token account is not owned by the escrow")]
+    InvalidEscrowTokenAccount,
+
+    #[msg("Token account does not belong to the recipient")]
+    InvalidRecipientTokenAccount,
+
+    #[msg("Invalid min/max trade limits")]
+    InvalidTradeLimits,
+
+    #[msg("Invalid payment methods")]
+    InvalidPaymentMethods,
+
+    #[msg("Ad is not active")]
+    AdNotActive,
+
+    #[msg("Trade amount is outside the ad limits")]
+    TradeOutsideAdLimits,
+
+    #[msg("Insufficient ad inventory")]
+    InsufficientInventory,
+
+    #[msg("Math overflow")]
+    MathOverflow,
+
+    #[msg("Operation not allowed for this order side")]
+    InvalidOrderSide,
+
+    #[msg("Price feed account is required for floating orders")]
+    MissingPriceFeed,
+
+    #[msg("Reference rate account is required for floating orders")]
+    MissingReferenceRate,
+
+    #[msg("Taker profile is required to release the order's volume")]
+    MissingTakerProfile,
+
+    #[msg("Price feed is stale")]
+    StalePriceFeed,
+
+    #[msg("Premium is outside the allowed range")]
+    InvalidPremium,
+
+    #[msg("Price exceeds the maximum accepted by the buyer")]
+    SlippageExceeded,
+
+    #[msg("Mint is not supported or does not match the order")]
+    InvalidMint,
+
+    #[msg("Mint is disabled")]
+    MintDisabled,
+
+    #[msg("Amount is outside the mint limits")]
+    AmountOutsideMintLimits,
+
+    #[msg("Reference rate is stale")]
+    StaleReferenceRate,
+
+    #[msg("Order price deviates too much from the reference rate")]
+    PriceDeviationExceeded,
+
+    #[msg("Daily MXN volume limit exceeded")]
+    DailyVolumeLimitExceeded,
+
+    #[msg("Monthly MXN volume limit exceeded")]
+    MonthlyVolumeLimitExceeded,
+
+    #[msg("KYC record is not a valid kyc_nft record for this user")]
+    InvalidKycRecord,
+
+    #[msg("KYC verification has expired")]
+    KycExpired,
+
+    #[msg("Invalid fee schedule")]
+    InvalidFeeSchedule,
+
+    #[msg("Fee schedule account is required to prefund the seller's share of the fee")]
+    MissingFeeSchedule,
+
+    #[msg("Referral account does not match the participant's referrer")]
+    InvalidReferralAccount,
+
+    #[msg("No referral rewards to claim")]
+    NoReferralRewards,
+
+    #[msg("Invalid treasury admins or threshold")]
+    InvalidTreasuryAdmins,
+
+    #[msg("Signer is not a treasury admin")]
+    NotTreasuryAdmin,
+
+    #[msg("Signer already approved")]
+    AlreadyApproved,
+
+    #[msg("Not enough approvals")]
+    ApprovalThresholdNotMet,
+
+    #[msg("Withdrawal already executed")]
+    WithdrawalAlreadyExecuted,
+
+    #[msg("Insufficient treasury balance")]
+    InsufficientTreasuryBalance,
+
+    #[msg("Invalid council members or threshold")]
+    InvalidCouncilMembers,
+
+    #[msg("Signer is not a council member")]
+    NotCouncilMember,
+
+    #[msg("Proposal lifetime out of range")]
+    InvalidProposalLifetime,
+
+    #[msg("Proposal has expired")]
+    ProposalExpired,
+
+    #[msg("Proposal already executed")]
+    ProposalAlreadyExecuted,
+
+    #[msg("Invalid role change")]
+    InvalidRoleChange,
+
+    #[msg("Missing or mismatched account for admin action")]
+    MissingAdminActionAccount,
+
+    #[msg("Platform configuration is governed by the admin council")]
+    CouncilGoverned,
+
+    #[msg("Admin action cannot be applied this way")]
+    InvalidAdminAction,
+
+    #[msg("Timelock delay out of range")]
+    InvalidTimelockDelay,
+
+    #[msg("Change is still in its timelock delay")]
+    ChangeStillLocked,
+
+    #[msg("Operation is paused by the guardian")]
+    OperationPaused,
+
+    #[msg("Trading in this mint is paused by the guardian")]
+    MintTradingPaused,
+
+    #[msg("Unknown pause flags")]
+    InvalidPauseFlags,
+
+    #[msg("User is suspended")]
+    UserSuspended,
+
+    #[msg("User is banned")]
+    UserBanned,
+
+    #[msg("Suspensions need a duration within the allowed range")]
+    InvalidSuspension,
+
+    #[msg("Order is under legal hold")]
+    OrderFrozen,
+
+    #[msg("Order is not under legal hold")]
+    OrderNotFrozen,
+
+    #[msg("Order has no escrowed funds to hold")]
+    OrderNotHoldable,
+
+    #[msg("Transfer does not match a council-approved court order")]
+    CourtTransferNotApproved,
+
+    #[msg("Case reference must be between 1 and 64 characters")]
+    InvalidCaseReference,
+
+    #[msg("Address is on the sanctions blocklist")]
+    AddressBlocked,
+
+    #[msg("Blocklist batch must list 1 to 10 addresses with one account each")]
+    InvalidBlocklistBatch,
+
+    #[msg("Account is not the blocklist entry of the address")]
+    InvalidBlockedAddressAccount,
+
+    #[msg("Setup does not match a council-approved proposal")]
+    SetupNotApproved,
 }
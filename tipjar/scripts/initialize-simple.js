@@ -91,7 +91,6 @@ async function main() {
   const keys = [
     { pubkey: platformPDA, isSigner: false, isWritable: true },           // platform
     { pubkey: walletKeypair.publicKey, isSigner: true, isWritable: true }, // authority
-    { pubkey: walletKeypair.publicKey, isSigner: false, isWritable: false }, // treasury (same as authority)
     { pubkey: SystemProgram.programId, isSigner: false, isWritable: false }, // system_program
   ];
 
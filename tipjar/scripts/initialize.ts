@@ -22,8 +22,11 @@ async function main() {
 
   console.log("Platform PDA:", platformPDA.toString());
 
-  // Treasury (usar la misma wallet por ahora)
-  const treasury = provider.wallet.publicKey;
+  // Treasury: PDA del programa (la wallet es su único administrador por ahora)
+  const [treasuryPDA] = PublicKey.findProgramAddressSync(
+    [Buffer.from("treasury")],
+    program.programId
+  );
 
   try {
     // Check if platform already exists
@@ -50,7 +53,17 @@ async function main() {
       .accounts({
         platform: platformPDA,
         authority: provider.wallet.publicKey,
-        treasury: treasury,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    // Initialize treasury (1 de 1)
+    await program.methods
+      .initializeTreasury([provider.wallet.publicKey], 1)
+      .accounts({
+        treasury: treasuryPDA,
+        platform: platformPDA,
+        authority: provider.wallet.publicKey,
         systemProgram: SystemProgram.programId,
       })
       .rpc();
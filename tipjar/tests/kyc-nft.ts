@@ -0,0 +1,256 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { KycNft } from "../target/types/kyc_nft";
+import { PublicKey, Keypair, SystemProgram, SYSVAR_RENT_PUBKEY } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  createMint,
+  getAssociatedTokenAddressSync,
+} from "@solana/spl-token";
+import { createHash } from "crypto";
+import { assert } from "chai";
+
+describe("KYC NFT", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.KycNft as Program<KycNft>;
+  const authority = provider.wallet as anchor.Wallet;
+
+  const METADATA_PROGRAM_ID = new PublicKey(
+    "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s"
+  );
+
+  // Usuarios con KycRecord precargado en el validador (tests/fixtures/kyc)
+  const kycUser = (name: string) => Keypair.fromSeed(Buffer.alloc(32, name));
+
+  const kycRecordPda = (user: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("kyc_record"), user.toBuffer()],
+      program.programId
+    )[0];
+
+  const getKycStatus = (user: PublicKey) =>
+    program.methods
+      .getKycStatus()
+      .accounts({
+        kycRecord: kycRecordPda(user),
+        user,
+      })
+      .view();
+
+  describe("get_kyc_status", () => {
+    it("Debe reportar un KYC vigente", async () => {
+      const seller = kycUser("seller");
+      const status = await getKycStatus(seller.publicKey);
+
+      assert.equal(status.user.toString(), seller.publicKey.toString());
+      assert.isTrue(status.valid);
+      assert.deepEqual(status.reason, { valid: {} });
+      assert.isNotNull(status.verificationLevel);
+      assert.isNotNull(status.mint);
+      assert.isFalse(status.revoked);
+      assert.isNull(status.revokedAt);
+
+      console.log("✅ KYC vigente:", Object.keys(status.verificationLevel)[0]);
+    });
+
+    it("Debe reportar un KYC expirado", async () => {
+      const expiredUser = kycUser("expired");
+      const status = await getKycStatus(expiredUser.publicKey);
+
+      assert.isFalse(status.valid);
+      assert.deepEqual(status.reason, { expired: {} });
+      assert.isNotNull(status.expiresAt);
+      assert.isBelow(status.expiresAt.toNumber(), Math.floor(Date.now() / 1000));
+      assert.isFalse(status.revoked);
+
+      console.log("✅ KYC expirado reportado sin fallar");
+    });
+
+    it("Debe reportar un KYC revocado", async () => {
+      const revokedUser = kycUser("revoked");
+      const status = await getKycStatus(revokedUser.publicKey);
+
+      assert.isFalse(status.valid);
+      assert.deepEqual(status.reason, { revoked: {} });
+      assert.isTrue(status.revoked);
+      assert.isNotNull(status.revokedAt);
+
+      console.log("✅ KYC revocado reportado sin fallar");
+    });
+
+    it("Debe reportar un usuario sin KYC emitido", async () => {
+      const status = await getKycStatus(Keypair.generate().publicKey);
+
+      assert.isFalse(status.valid);
+      assert.deepEqual(status.reason, { notIssued: {} });
+      assert.isNull(status.verificationLevel);
+      assert.isNull(status.mint);
+
+      console.log("✅ Usuario sin KYC reportado sin fallar");
+    });
+  });
+
+  describe("Eventos", () => {
+    // Usuario nuevo: emitir, renovar y revocar su KYC en orden
+    const user = Keypair.generate();
+    const mint = Keypair.generate();
+    let kycAuthorityPda: PublicKey;
+    let collectionMint: PublicKey;
+
+    // Decodificar los eventos Anchor emitidos en una transacción confirmada
+    const parseEvents = async (signature: string) => {
+      const tx = await provider.connection.getTransaction(signature, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const parser = new anchor.EventParser(program.programId, program.coder);
+      return [...parser.parseLogs(tx.meta.logMessages)];
+    };
+
+    const metadataPda = (mint: PublicKey, ...extra: Buffer[]) =>
+      PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("metadata"),
+          METADATA_PROGRAM_ID.toBuffer(),
+          mint.toBuffer(),
+          ...extra,
+        ],
+        METADATA_PROGRAM_ID
+      )[0];
+
+    before(async () => {
+      [kycAuthorityPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("authority")],
+        program.programId
+      );
+
+      collectionMint = await createMint(
+        provider.connection,
+        authority.payer,
+        authority.publicKey,
+        null,
+        0
+      );
+
+      await program.methods
+        .initialize()
+        .accounts({
+          kycAuthority: kycAuthorityPda,
+          collectionMint,
+          authority: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    });
+
+    it("Debe emitir KycIssued con usuario, mint y emisor", async () => {
+      const signature = await program.methods
+        .issueKycNft(
+          "Test Eventos",
+          "TEST-EVENTOS",
+          { premium: {} },
+          "https://kyc.example/eventos.json"
+        )
+        .accounts({
+          kycAuthority: kycAuthorityPda,
+          kycRecord: kycRecordPda(user.publicKey),
+          user: user.publicKey,
+          mint: mint.publicKey,
+          tokenAccount: getAssociatedTokenAddressSync(
+            mint.publicKey,
+            user.publicKey
+          ),
+          metadata: metadataPda(mint.publicKey),
+          masterEdition: metadataPda(mint.publicKey, Buffer.from("edition")),
+          collectionMint,
+          authority: authority.publicKey,
+          payer: authority.publicKey,
+          rent: SYSVAR_RENT_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          metadataProgram: METADATA_PROGRAM_ID,
+        })
+        .signers([mint])
+        .rpc({ commitment: "confirmed" });
+
+      const events = await parseEvents(signature);
+      const issued = events.find((event) => event.name === "kycIssued");
+      const record = await program.account.kycRecord.fetch(
+        kycRecordPda(user.publicKey)
+      );
+
+      assert.isDefined(issued);
+      assert.equal(issued.data.user.toString(), user.publicKey.toString());
+      assert.equal(issued.data.mint.toString(), mint.publicKey.toString());
+      assert.deepEqual(issued.data.verificationLevel, { premium: {} });
+      assert.equal(issued.data.issuer.toString(), authority.publicKey.toString());
+      assert.equal(issued.data.issuedAt.toNumber(), record.issuedAt.toNumber());
+      assert.isNull(issued.data.expiresAt);
+
+      console.log("✅ KycIssued emitido");
+    });
+
+    it("Debe emitir KycRenewed con la expiración anterior y la nueva", async () => {
+      const newExpiration = new anchor.BN(Math.floor(Date.now() / 1000) + 365 * 86400);
+
+      const signature = await program.methods
+        .renewKycNft(newExpiration)
+        .accounts({
+          kycAuthority: kycAuthorityPda,
+          kycRecord: kycRecordPda(user.publicKey),
+          authority: authority.publicKey,
+        })
+        .rpc({ commitment: "confirmed" });
+
+      const events = await parseEvents(signature);
+      const renewed = events.find((event) => event.name === "kycRenewed");
+      const record = await program.account.kycRecord.fetch(
+        kycRecordPda(user.publicKey)
+      );
+
+      assert.isDefined(renewed);
+      assert.equal(renewed.data.user.toString(), user.publicKey.toString());
+      assert.equal(renewed.data.mint.toString(), mint.publicKey.toString());
+      assert.isNull(renewed.data.oldExpiresAt);
+      assert.equal(renewed.data.newExpiresAt.toNumber(), newExpiration.toNumber());
+      assert.equal(renewed.data.renewedBy.toString(), authority.publicKey.toString());
+      assert.equal(renewed.data.renewedAt.toNumber(), record.lastUpdated.toNumber());
+
+      console.log("✅ KycRenewed emitido");
+    });
+
+    it("Debe emitir KycRevoked con el hash de la razón", async () => {
+      const reason = "Documento apócrifo";
+
+      const signature = await program.methods
+        .revokeKycNft(reason)
+        .accounts({
+          kycAuthority: kycAuthorityPda,
+          kycRecord: kycRecordPda(user.publicKey),
+          authority: authority.publicKey,
+        })
+        .rpc({ commitment: "confirmed" });
+
+      const events = await parseEvents(signature);
+      const revoked = events.find((event) => event.name === "kycRevoked");
+      const record = await program.account.kycRecord.fetch(
+        kycRecordPda(user.publicKey)
+      );
+      const expectedHash = createHash("sha256").update(reason, "utf8").digest();
+
+      assert.isDefined(revoked);
+      assert.equal(revoked.data.user.toString(), user.publicKey.toString());
+      assert.equal(revoked.data.mint.toString(), mint.publicKey.toString());
+      assert.deepEqual(Buffer.from(revoked.data.reasonHash), expectedHash);
+      assert.equal(revoked.data.revoker.toString(), authority.publicKey.toString());
+      assert.equal(revoked.data.revokedAt.toNumber(), record.revokedAt.toNumber());
+      assert.equal(record.revocationReason, reason);
+
+      console.log("✅ KycRevoked emitido");
+    });
+  });
+});
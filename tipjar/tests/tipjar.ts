@@ -5,14 +5,25 @@ import {
   PublicKey,
   Keypair,
   SystemProgram,
+  SYSVAR_CLOCK_PUBKEY,
+  Transaction,
+  sendAndConfirmTransaction,
   LAMPORTS_PER_SOL
 } from "@solana/web3.js";
 import {
   TOKEN_PROGRAM_ID,
+  TOKEN_2022_PROGRAM_ID,
+  NATIVE_MINT,
+  ExtensionType,
+  getMintLen,
+  createInitializeMintInstruction,
+  createInitializeTransferFeeConfigInstruction,
   createMint,
   createAccount,
   mintTo,
   getAccount,
+  getAssociatedTokenAddressSync,
+  createAssociatedTokenAccountIdempotentInstruction,
 } from "@solana/spl-token";
 import { assert } from "chai";
 
@@ -25,13 +36,96 @@ describe("P2P Payment System Tests", () => {
 
   // Test accounts
   const authority = provider.wallet as anchor.Wallet;
-  const treasury = Keypair.generate();
-  const seller = Keypair.generate();
-  const buyer = Keypair.generate();
+  // Treasury: PDA del programa (SOL) y sus ATAs (tokens)
+  const [treasuryPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("treasury")],
+    program.programId
+  );
+  const treasuryAdminB = Keypair.generate();
+  const treasuryAdminC = Keypair.generate();
+  // Consejo de administración (2 de 3 con la autoridad)
+  const [councilPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("admin_council")],
+    program.programId
+  );
+  const memberB = Keypair.generate();
+  const memberC = Keypair.generate();
+  const createTreasuryAta = async (mint: PublicKey, tokenProgram = TOKEN_PROGRAM_ID) => {
+    const ata = getAssociatedTokenAddressSync(mint, treasuryPda, true, tokenProgram);
+    await sendAndConfirmTransaction(
+      provider.connection,
+      new Transaction().add(
+        createAssociatedTokenAccountIdempotentInstruction(
+          authority.publicKey,
+          ata,
+          treasuryPda,
+          mint,
+          tokenProgram
+        )
+      ),
+      [authority.payer]
+    );
+    return ata;
+  };
+
+  const executeAccounts = (proposal: PublicKey) => ({
+    proposal,
+    council: councilPda,
+    platform: platformPda,
+    executor: authority.publicKey,
+    pendingChange: null,
+    treasury: null,
+    recipient: null,
+    treasuryTokenAccount: null,
+    recipientTokenAccount: null,
+    mint: null,
+    tokenProgram: null,
+    systemProgram: SystemProgram.programId,
+  });
+
+  // Proponer (autoridad) y aprobar (miembro B) una acción del consejo; devuelve el PDA de la propuesta
+  const proposeAndApprove = async (action: any) => {
+    const council = await program.account.adminCouncil.fetch(councilPda);
+    const [proposalPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("admin_proposal"), council.proposalCount.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+
+    await program.methods
+      .proposeAdminAction(action, null)
+      .accounts({
+        proposal: proposalPda,
+        council: councilPda,
+        proposer: authority.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    await program.methods
+      .approveAdminAction()
+      .accounts({
+        proposal: proposalPda,
+        council: councilPda,
+        member: memberB.publicKey,
+      })
+      .signers([memberB])
+      .rpc();
+
+    return proposalPda;
+  };
+  // Usuarios con KycRecord precargado en el validador (tests/fixtures/kyc)
+  const kycUser = (name: string) => Keypair.fromSeed(Buffer.alloc(32, name));
+  const seller = kycUser("seller");
+  const buyer = kycUser("buyer");
   const arbiter = authority;
 
   // PDAs
   let platformPda: PublicKey;
+  let solMintPda: PublicKey;
+  let usdcMintPda: PublicKey;
+  let solRatePda: PublicKey;
+  let usdcRatePda: PublicKey;
+  let feeSchedulePda: PublicKey;
   let sellerProfilePda: PublicKey;
   let buyerProfilePda: PublicKey;
 
@@ -49,6 +143,31 @@ describe("P2P Payment System Tests", () => {
   let sellerTokenAccount: PublicKey;
   let buyerTokenAccount: PublicKey;
   let escrowTokenAccount: PublicKey;
+
+  // KycRecord emitido por el programa kyc_nft
+  const KYC_NFT_PROGRAM_ID = new PublicKey("KYC1111111111111111111111111111111111111111");
+  const kycRecordPda = (user: PublicKey) =>
+    PublicKey.findProgramAddressSync([Buffer.from("kyc_record"), user.toBuffer()], KYC_NFT_PROGRAM_ID)[0];
+
+  const blockedAddressPda = (address: PublicKey) =>
+    PublicKey.findProgramAddressSync([Buffer.from("blocked_address"), address.toBuffer()], program.programId)[0];
+
+  // Cuenta wSOL temporal con la que la bóveda de un anuncio desenvuelve el SOL
+  const adUnwrapPda = (ad: PublicKey) =>
+    PublicKey.findProgramAddressSync([Buffer.from("ad_unwrap"), ad.toBuffer()], program.programId)[0];
+
+  // DailyStats del día UTC actual según el reloj del cluster
+  const dailyStatsPda = async () => {
+    const clock = await provider.connection.getAccountInfo(SYSVAR_CLOCK_PUBKEY);
+    const day = clock.data.readBigInt64LE(32) / BigInt(86_400);
+    const seed = Buffer.alloc(8);
+    seed.writeBigInt64LE(day);
+    return PublicKey.findProgramAddressSync([Buffer.from("daily_stats"), seed], program.programId)[0];
+  };
+
+  // Cuenta wSOL del escrow o de la bóveda de un anuncio: el programa envuelve y desenvuelve el SOL
+  const createWsolEscrowAccount = (escrow: PublicKey) =>
+    createAccount(provider.connection, authority.payer, NATIVE_MINT, escrow, Keypair.generate());
   let treasuryTokenAccount: PublicKey;
 
   before(async () => {
@@ -64,7 +183,7 @@ describe("P2P Payment System Tests", () => {
     );
 
     await provider.connection.confirmTransaction(
-      await provider.connection.requestAirdrop(treasury.publicKey, airdropAmount)
+      await provider.connection.requestAirdrop(treasuryAdminB.publicKey, airdropAmount)
     );
 
     // Derive PDAs
@@ -73,6 +192,11 @@ describe("P2P Payment System Tests", () => {
       program.programId
     );
 
+    [feeSchedulePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("fee_schedule")],
+      program.programId
+    );
+
     [sellerProfilePda] = PublicKey.findProgramAddressSync(
       [Buffer.from("user_profile"), seller.publicKey.toBuffer()],
       program.programId
@@ -112,6 +236,26 @@ describe("P2P Payment System Tests", () => {
       6 // USDC has 6 decimals
     );
 
+    [solMintPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("supported_mint"), NATIVE_MINT.toBuffer()],
+      program.programId
+    );
+
+    [usdcMintPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("supported_mint"), usdcMint.toBuffer()],
+      program.programId
+    );
+
+    [solRatePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("reference_rate"), NATIVE_MINT.toBuffer()],
+      program.programId
+    );
+
+    [usdcRatePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("reference_rate"), usdcMint.toBuffer()],
+      program.programId
+    );
+
     // Create token accounts
     sellerTokenAccount = await createAccount(
       provider.connection,
@@ -127,12 +271,7 @@ describe("P2P Payment System Tests", () => {
       buyer.publicKey
     );
 
-    treasuryTokenAccount = await createAccount(
-      provider.connection,
-      authority.payer,
-      usdcMint,
-      treasury.publicKey
-    );
+    treasuryTokenAccount = await createTreasuryAta(usdcMint);
 
     // Mint USDC to seller (1000 USDC)
     await mintTo(
@@ -158,7 +297,6 @@ describe("P2P Payment System Tests", () => {
         .accounts({
           platform: platformPda,
           authority: authority.publicKey,
-          treasury: treasury.publicKey,
           systemProgram: SystemProgram.programId,
         })
         .rpc();
@@ -166,21 +304,137 @@ describe("P2P Payment System Tests", () => {
       const platform = await program.account.platform.fetch(platformPda);
 
       assert.equal(platform.authority.toString(), authority.publicKey.toString());
-      assert.equal(platform.treasury.toString(), treasury.publicKey.toString());
+      assert.equal(platform.treasury.toString(), treasuryPda.toString());
       assert.equal(platform.feeBps.toNumber(), 50); // 0.5%
       assert.equal(platform.isActive, true);
 
       console.log("✅ Plataforma inicializada con comisión:", platform.feeBps.toNumber(), "bps");
     });
+
+    it("Debe crear el treasury con aprobación 2 de 3", async () => {
+      await program.methods
+        .initializeTreasury(
+          [authority.publicKey, treasuryAdminB.publicKey, treasuryAdminC.publicKey],
+          2
+        )
+        .accounts({
+          treasury: treasuryPda,
+          platform: platformPda,
+          authority: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      const treasury = await program.account.treasury.fetch(treasuryPda);
+
+      assert.equal(treasury.admins.length, 3);
+      assert.equal(treasury.threshold, 2);
+      assert.equal(treasury.withdrawalCount.toNumber(), 0);
+
+      console.log("✅ Treasury creado:", treasuryPda.toString());
+    });
+
+    it("Debe registrar SOL y USDC como mints soportados", async () => {
+      const mints: [PublicKey, PublicKey, BN, BN][] = [
+        [solMintPda, NATIVE_MINT, new BN(0.01 * LAMPORTS_PER_SOL), new BN(100 * LAMPORTS_PER_SOL)],
+        [usdcMintPda, usdcMint, new BN(1_000_000), new BN(10_000 * 1_000_000)],
+      ];
+
+      for (const [supportedMintPda, mint, min, max] of mints) {
+        await program.methods
+          .registerMint(min, max, null, new BN(0), null)
+          .accounts({
+            supportedMint: supportedMintPda,
+            mint,
+            platform: platformPda,
+            authority: authority.publicKey,
+            proposal: null,
+            council: null,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+      }
+
+      const usdcConfig = await program.account.supportedMint.fetch(usdcMintPda);
+
+      assert.equal(usdcConfig.mint.toString(), usdcMint.toString());
+      assert.equal(usdcConfig.decimals, 6);
+      assert.equal(usdcConfig.enabled, true);
+      assert.isNull(usdcConfig.feeBpsOverride);
+
+      console.log("✅ Mints registrados: SOL y USDC");
+    });
+
+    it("Debe crear el esquema de tarifas con la comisión base", async () => {
+      await program.methods
+        .initializeFeeSchedule()
+        .accounts({
+          feeSchedule: feeSchedulePda,
+          platform: platformPda,
+          authority: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      const feeSchedule = await program.account.feeSchedule.fetch(feeSchedulePda);
+
+      // 0.5% repartido entre maker y taker
+      assert.equal(feeSchedule.tiers.length, 1);
+      assert.equal(feeSchedule.tiers[0].makerFeeBps.toNumber(), 25);
+      assert.equal(feeSchedule.tiers[0].takerFeeBps.toNumber(), 25);
+
+      console.log("✅ Esquema de tarifas inicializado");
+    });
+
+    it("Debe publicar tasas de referencia para SOL y USDC", async () => {
+      const rates: [PublicKey, PublicKey, BN][] = [
+        [solRatePda, solMintPda, new BN(4_000_000_000)], // 4,000 MXN por SOL
+        [usdcRatePda, usdcMintPda, new BN(20_000_000)], // 20 MXN por USDC
+      ];
+
+      for (const [ratePda, supportedMintPda, rate] of rates) {
+        await program.methods
+          .initializeReferenceRate(new BN(3600))
+          .accounts({
+            referenceRate: ratePda,
+            supportedMint: supportedMintPda,
+            platform: platformPda,
+            authority: authority.publicKey,
+            proposal: null,
+            council: null,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+
+        await program.methods
+          .updateReferenceRate(rate)
+          .accounts({
+            referenceRate: ratePda,
+            platform: platformPda,
+            oracle: authority.publicKey,
+          })
+          .rpc();
+      }
+
+      const platform = await program.account.platform.fetch(platformPda);
+      const solRate = await program.account.referenceRate.fetch(solRatePda);
+
+      assert.equal(platform.maxPriceDeviationBps.toNumber(), 1000);
+      assert.equal(solRate.rateMxn.toNumber(), 4_000_000_000);
+
+      console.log("✅ Tasas de referencia publicadas");
+    });
   });
 
   describe("2. Creación de Perfiles de Usuario", () => {
     it("Debe crear perfil de seller con KYC verificado", async () => {
       await program.methods
-        .createUserProfile(true, null)
+        .createUserProfile()
         .accounts({
           userProfile: sellerProfilePda,
           user: seller.publicKey,
+          kycRecord: kycRecordPda(seller.publicKey),
+          userBlocked: blockedAddressPda(seller.publicKey),
           systemProgram: SystemProgram.programId,
         })
         .signers([seller])
@@ -190,6 +444,7 @@ describe("P2P Payment System Tests", () => {
 
       assert.equal(profile.owner.toString(), seller.publicKey.toString());
       assert.equal(profile.kycVerified, true);
+      assert.deepEqual(profile.kycLevel, { enhanced: {} }); // Leído del KycRecord
       assert.equal(profile.totalTrades.toNumber(), 0);
 
       console.log("✅ Perfil de seller creado con KYC");
@@ -197,10 +452,12 @@ describe("P2P Payment System Tests", () => {
 
     it("Debe crear perfil de buyer con KYC verificado", async () => {
       await program.methods
-        .createUserProfile(true, null)
+        .createUserProfile()
         .accounts({
           userProfile: buyerProfilePda,
           user: buyer.publicKey,
+          kycRecord: kycRecordPda(buyer.publicKey),
+          userBlocked: blockedAddressPda(buyer.publicKey),
           systemProgram: SystemProgram.programId,
         })
         .signers([buyer])
@@ -218,6 +475,11 @@ describe("P2P Payment System Tests", () => {
   describe("3. Flujo Completo P2P con SOL", () => {
     const amount = new BN(0.5 * LAMPORTS_PER_SOL); // 0.5 SOL
     const amountMxn = new BN(2_000_000_000); // 2,000 MXN (6 decimals)
+    let escrowWsolAccount: PublicKey;
+
+    before(async () => {
+      escrowWsolAccount = await createWsolEscrowAccount(escrowPda1);
+    });
 
     it("Debe crear orden de venta de SOL", async () => {
       await program.methods
@@ -225,15 +487,23 @@ describe("P2P Payment System Tests", () => {
           orderId1,
           amount,
           amountMxn,
-          { sol: {} },
           { stp: {} },
-          "STP_REF_001"
+          "STP_REF_001",
+          false,
+          { sell: {} },
+          null,
+          null
         )
         .accounts({
           order: orderPda1,
           escrow: escrowPda1,
-          sellerProfile: sellerProfilePda,
-          seller: seller.publicKey,
+          makerProfile: sellerProfilePda,
+          maker: seller.publicKey,
+          kycRecord: kycRecordPda(seller.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          platform: platformPda,
+          supportedMint: solMintPda,
+          referenceRate: solRatePda,
           systemProgram: SystemProgram.programId,
         })
         .signers([seller])
@@ -252,11 +522,18 @@ describe("P2P Payment System Tests", () => {
 
     it("Buyer debe aceptar la orden", async () => {
       await program.methods
-        .acceptOrder()
+        .acceptOrder(null)
         .accounts({
           order: orderPda1,
           buyerProfile: buyerProfilePda,
+          makerProfile: sellerProfilePda,
+          feeSchedule: feeSchedulePda,
           buyer: buyer.publicKey,
+          kycRecord: kycRecordPda(buyer.publicKey),
+          buyerBlocked: blockedAddressPda(buyer.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          supportedMint: solMintPda,
+          platform: platformPda,
         })
         .signers([buyer])
         .rpc();
@@ -269,25 +546,30 @@ describe("P2P Payment System Tests", () => {
       console.log("✅ Orden aceptada por buyer");
     });
 
-    it("Seller debe depositar SOL en escrow", async () => {
-      const escrowBalanceBefore = await provider.connection.getBalance(escrowPda1);
-
+    it("Seller debe depositar SOL en escrow como wSOL", async () => {
       await program.methods
-        .depositToEscrowNative()
+        .depositToEscrow()
         .accounts({
+          platform: platformPda,
           order: orderPda1,
           escrow: escrowPda1,
+          supportedMint: solMintPda,
+          sellerTokenAccount: null,
+          escrowTokenAccount: escrowWsolAccount,
           seller: seller.publicKey,
+          mint: NATIVE_MINT,
+          tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
         })
         .signers([seller])
         .rpc();
 
       const order = await program.account.order.fetch(orderPda1);
-      const escrowBalanceAfter = await provider.connection.getBalance(escrowPda1);
+      const escrowAccount = await getAccount(provider.connection, escrowWsolAccount);
 
       assert.deepEqual(order.status, { funded: {} });
-      assert.equal(escrowBalanceAfter - escrowBalanceBefore, amount.toNumber());
+      assert.equal(escrowAccount.amount.toString(), amount.toString());
+      assert.equal(order.escrowedAmount.toString(), amount.toString());
 
       console.log("✅ SOL depositado en escrow:", amount.toNumber() / LAMPORTS_PER_SOL, "SOL");
     });
@@ -329,25 +611,37 @@ describe("P2P Payment System Tests", () => {
 
     it("Debe liberar fondos al buyer", async () => {
       const buyerBalanceBefore = await provider.connection.getBalance(buyer.publicKey);
-      const treasuryBalanceBefore = await provider.connection.getBalance(treasury.publicKey);
+      const treasuryBalanceBefore = await provider.connection.getBalance(treasuryPda);
 
       await program.methods
-        .releaseFundsNative()
+        .releaseFunds()
         .accounts({
+          dailyStats: await dailyStatsPda(),
           order: orderPda1,
           escrow: escrowPda1,
           platform: platformPda,
+          supportedMint: solMintPda,
+          escrowTokenAccount: escrowWsolAccount,
+          seller: seller.publicKey,
           buyer: buyer.publicKey,
-          treasury: treasury.publicKey,
+          treasury: treasuryPda,
+          buyerTokenAccount: null,
+          treasuryTokenAccount: null,
           sellerProfile: sellerProfilePda,
           buyerProfile: buyerProfilePda,
           authority: authority.publicKey,
+          mint: NATIVE_MINT,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
         })
         .rpc();
 
+      // El SOL se paga desenvuelto y la cuenta wSOL del escrow se cierra
+      assert.isNull(await provider.connection.getAccountInfo(escrowWsolAccount));
+
       const order = await program.account.order.fetch(orderPda1);
       const buyerBalanceAfter = await provider.connection.getBalance(buyer.publicKey);
-      const treasuryBalanceAfter = await provider.connection.getBalance(treasury.publicKey);
+      const treasuryBalanceAfter = await provider.connection.getBalance(treasuryPda);
 
       const fee = amount.toNumber() * 50 / 10000; // 0.5%
       const buyerAmount = amount.toNumber() - fee;
@@ -401,15 +695,23 @@ describe("P2P Payment System Tests", () => {
           orderId2,
           amount,
           amountMxn,
-          { usdc: {} },
           { stp: {} },
-          "STP_REF_002"
+          "STP_REF_002",
+          false,
+          { sell: {} },
+          null,
+          null
         )
         .accounts({
           order: orderPda2,
           escrow: escrowPda2,
-          sellerProfile: sellerProfilePda,
-          seller: seller.publicKey,
+          makerProfile: sellerProfilePda,
+          maker: seller.publicKey,
+          kycRecord: kycRecordPda(seller.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          platform: platformPda,
+          supportedMint: usdcMintPda,
+          referenceRate: usdcRatePda,
           systemProgram: SystemProgram.programId,
         })
         .signers([seller])
@@ -423,11 +725,18 @@ describe("P2P Payment System Tests", () => {
 
     it("Buyer debe aceptar orden USDC", async () => {
       await program.methods
-        .acceptOrder()
+        .acceptOrder(null)
         .accounts({
           order: orderPda2,
           buyerProfile: buyerProfilePda,
+          makerProfile: sellerProfilePda,
+          feeSchedule: feeSchedulePda,
           buyer: buyer.publicKey,
+          kycRecord: kycRecordPda(buyer.publicKey),
+          buyerBlocked: blockedAddressPda(buyer.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          supportedMint: usdcMintPda,
+          platform: platformPda,
         })
         .signers([buyer])
         .rpc();
@@ -439,12 +748,16 @@ describe("P2P Payment System Tests", () => {
       await program.methods
         .depositToEscrowSpl()
         .accounts({
+          platform: platformPda,
           order: orderPda2,
           escrow: escrowPda2,
+          supportedMint: usdcMintPda,
           sellerTokenAccount: sellerTokenAccount,
           escrowTokenAccount: escrowTokenAccount,
           seller: seller.publicKey,
+          mint: usdcMint,
           tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
         })
         .signers([seller])
         .rpc();
@@ -452,6 +765,9 @@ describe("P2P Payment System Tests", () => {
       const escrowAccount = await getAccount(provider.connection, escrowTokenAccount);
       assert.equal(escrowAccount.amount.toString(), amount.toString());
 
+      const order = await program.account.order.fetch(orderPda2);
+      assert.equal(order.escrowedAmount.toString(), amount.toString());
+
       console.log("✅ USDC depositado en escrow:", amount.toNumber() / 1_000_000);
     });
 
@@ -479,16 +795,23 @@ describe("P2P Payment System Tests", () => {
       await program.methods
         .releaseFundsSpl()
         .accounts({
+          dailyStats: await dailyStatsPda(),
           order: orderPda2,
           escrow: escrowPda2,
           platform: platformPda,
+          supportedMint: usdcMintPda,
           escrowTokenAccount: escrowTokenAccount,
+          seller: seller.publicKey,
+          buyer: buyer.publicKey,
+          treasury: treasuryPda,
           buyerTokenAccount: buyerTokenAccount,
           treasuryTokenAccount: treasuryTokenAccount,
           sellerProfile: sellerProfilePda,
           buyerProfile: buyerProfilePda,
           authority: authority.publicKey,
+          mint: usdcMint,
           tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
         })
         .rpc();
 
@@ -514,6 +837,7 @@ describe("P2P Payment System Tests", () => {
     let orderPda: PublicKey;
     let escrowPda: PublicKey;
     let disputePda: PublicKey;
+    let escrowWsolAccount: PublicKey;
     const amount = new BN(1 * LAMPORTS_PER_SOL);
 
     before(async () => {
@@ -537,37 +861,60 @@ describe("P2P Payment System Tests", () => {
         .createOrder(
           orderId,
           amount,
-          new BN(2_000_000_000),
-          { sol: {} },
+          new BN(4_000_000_000),
           { stp: {} },
-          "STP_REF_003"
+          "STP_REF_003",
+          false,
+          { sell: {} },
+          null,
+          null
         )
         .accounts({
           order: orderPda,
           escrow: escrowPda,
-          sellerProfile: sellerProfilePda,
-          seller: seller.publicKey,
+          makerProfile: sellerProfilePda,
+          maker: seller.publicKey,
+          kycRecord: kycRecordPda(seller.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          platform: platformPda,
+          supportedMint: solMintPda,
+          referenceRate: solRatePda,
           systemProgram: SystemProgram.programId,
         })
         .signers([seller])
         .rpc();
 
       await program.methods
-        .acceptOrder()
+        .acceptOrder(null)
         .accounts({
           order: orderPda,
           buyerProfile: buyerProfilePda,
+          makerProfile: sellerProfilePda,
+          feeSchedule: feeSchedulePda,
           buyer: buyer.publicKey,
+          kycRecord: kycRecordPda(buyer.publicKey),
+          buyerBlocked: blockedAddressPda(buyer.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          supportedMint: solMintPda,
+          platform: platformPda,
         })
         .signers([buyer])
         .rpc();
 
+      escrowWsolAccount = await createWsolEscrowAccount(escrowPda);
+
       await program.methods
-        .depositToEscrowNative()
+        .depositToEscrow()
         .accounts({
+          platform: platformPda,
           order: orderPda,
           escrow: escrowPda,
+          supportedMint: solMintPda,
+          sellerTokenAccount: null,
+          escrowTokenAccount: escrowWsolAccount,
           seller: seller.publicKey,
+          mint: NATIVE_MINT,
+          tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
         })
         .signers([seller])
@@ -581,6 +928,8 @@ describe("P2P Payment System Tests", () => {
           "https://evidence.com/proof123"
         )
         .accounts({
+          platform: platformPda,
+          dailyStats: await dailyStatsPda(),
           dispute: disputePda,
           order: orderPda,
           sellerProfile: sellerProfilePda,
@@ -605,16 +954,29 @@ describe("P2P Payment System Tests", () => {
       const sellerBalanceBefore = await provider.connection.getBalance(seller.publicKey);
       const buyerBalanceBefore = await provider.connection.getBalance(buyer.publicKey);
 
+      const wsolRent = (await provider.connection.getAccountInfo(escrowWsolAccount)).lamports - amount.toNumber();
+
       await program.methods
-        .resolveDisputeSplitNative()
+        .resolveDisputeSplit()
         .accounts({
+          dailyStats: await dailyStatsPda(),
           order: orderPda,
           escrow: escrowPda,
           platform: platformPda,
+          supportedMint: solMintPda,
+          escrowTokenAccount: escrowWsolAccount,
           seller: seller.publicKey,
           buyer: buyer.publicKey,
-          treasury: treasury.publicKey,
+          treasury: treasuryPda,
+          sellerTokenAccount: null,
+          buyerTokenAccount: null,
+          treasuryTokenAccount: null,
+          sellerProfile: sellerProfilePda,
+          buyerProfile: buyerProfilePda,
           resolver: authority.publicKey,
+          mint: NATIVE_MINT,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
         })
         .rpc();
 
@@ -624,24 +986,236 @@ describe("P2P Payment System Tests", () => {
 
       assert.deepEqual(order.status, { partialRefund: {} });
 
+      // El monto se divide 50/50 y el buyer absorbe la comisión (fee bearer por defecto)
       const fee = amount.toNumber() * 50 / 10000;
-      const remaining = amount.toNumber() - fee;
-      const halfAmount = Math.floor(remaining / 2);
+      const sellerAmount = Math.floor(amount.toNumber() / 2);
+      const buyerAmount = amount.toNumber() - sellerAmount - fee;
 
+      // El seller también recupera la renta de la cuenta wSOL del escrow
       assert.approximately(
         sellerBalanceAfter - sellerBalanceBefore,
-        halfAmount,
+        sellerAmount + wsolRent,
         1000
       );
       assert.approximately(
         buyerBalanceAfter - buyerBalanceBefore,
-        halfAmount,
+        buyerAmount,
         1000
       );
 
       console.log("✅ Disputa resuelta con split 50/50");
-      console.log("   Seller recibió:", halfAmount / LAMPORTS_PER_SOL, "SOL");
-      console.log("   Buyer recibió:", halfAmount / LAMPORTS_PER_SOL, "SOL");
+      console.log("   Seller recibió:", sellerAmount / LAMPORTS_PER_SOL, "SOL");
+      console.log("   Buyer recibió:", buyerAmount / LAMPORTS_PER_SOL, "SOL");
+    });
+
+    // Crear, aceptar, fondear y disputar una orden de SOL del seller
+    const openDisputedOrder = async (disputedOrderId: string, stpRef: string) => {
+      const [disputedOrderPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("order"), Buffer.from(disputedOrderId)],
+        program.programId
+      );
+      const [disputedEscrowPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), disputedOrderPda.toBuffer()],
+        program.programId
+      );
+      const [disputedDisputePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("dispute"), disputedOrderPda.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .createOrder(
+          disputedOrderId,
+          new BN(0.5 * LAMPORTS_PER_SOL),
+          new BN(2_000_000_000),
+          { stp: {} },
+          stpRef,
+          false,
+          { sell: {} },
+          null,
+          null
+        )
+        .accounts({
+          order: disputedOrderPda,
+          escrow: disputedEscrowPda,
+          makerProfile: sellerProfilePda,
+          maker: seller.publicKey,
+          kycRecord: kycRecordPda(seller.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          platform: platformPda,
+          supportedMint: solMintPda,
+          referenceRate: solRatePda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([seller])
+        .rpc();
+
+      await program.methods
+        .acceptOrder(null)
+        .accounts({
+          order: disputedOrderPda,
+          buyerProfile: buyerProfilePda,
+          makerProfile: sellerProfilePda,
+          feeSchedule: feeSchedulePda,
+          buyer: buyer.publicKey,
+          kycRecord: kycRecordPda(buyer.publicKey),
+          buyerBlocked: blockedAddressPda(buyer.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          supportedMint: solMintPda,
+          platform: platformPda,
+        })
+        .signers([buyer])
+        .rpc();
+
+      const disputedEscrowWsol = await createWsolEscrowAccount(disputedEscrowPda);
+
+      await program.methods
+        .depositToEscrow()
+        .accounts({
+          platform: platformPda,
+          order: disputedOrderPda,
+          escrow: disputedEscrowPda,
+          supportedMint: solMintPda,
+          sellerTokenAccount: null,
+          escrowTokenAccount: disputedEscrowWsol,
+          seller: seller.publicKey,
+          mint: NATIVE_MINT,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([seller])
+        .rpc();
+
+      await program.methods
+        .openDispute("Pago fiat en disputa", "https://evidence.com/" + stpRef)
+        .accounts({
+          platform: platformPda,
+          dailyStats: await dailyStatsPda(),
+          dispute: disputedDisputePda,
+          order: disputedOrderPda,
+          sellerProfile: sellerProfilePda,
+          buyerProfile: buyerProfilePda,
+          initiator: buyer.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyer])
+        .rpc();
+
+      return {
+        order: disputedOrderPda,
+        escrow: disputedEscrowPda,
+        dispute: disputedDisputePda,
+        escrowTokenAccount: disputedEscrowWsol,
+      };
+    };
+
+    const resolveDispute = async (
+      disputed: Awaited<ReturnType<typeof openDisputedOrder>>,
+      resolution: any,
+      escrowTokenAccount = disputed.escrowTokenAccount
+    ) =>
+      program.methods
+        .resolveDispute(resolution, "Resuelta con evidencia del STP")
+        .accounts({
+          dispute: disputed.dispute,
+          dailyStats: await dailyStatsPda(),
+          order: disputed.order,
+          escrow: disputed.escrow,
+          platform: platformPda,
+          supportedMint: solMintPda,
+          escrowTokenAccount,
+          seller: seller.publicKey,
+          buyer: buyer.publicKey,
+          treasury: treasuryPda,
+          sellerTokenAccount: null,
+          buyerTokenAccount: null,
+          treasuryTokenAccount: null,
+          sellerProfile: sellerProfilePda,
+          buyerProfile: buyerProfilePda,
+          resolver: authority.publicKey,
+          mint: NATIVE_MINT,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+    it("Debe pagar al buyer al resolver a su favor", async () => {
+      const disputed = await openDisputedOrder("ORDER_003_FAVOR_BUYER", "STP_REF_003_FB");
+      const disputedAmount = 0.5 * LAMPORTS_PER_SOL;
+
+      const sellerBalanceBefore = await provider.connection.getBalance(seller.publicKey);
+      const buyerBalanceBefore = await provider.connection.getBalance(buyer.publicKey);
+      const treasuryBalanceBefore = await provider.connection.getBalance(treasuryPda);
+      const wsolRent =
+        (await provider.connection.getAccountInfo(disputed.escrowTokenAccount)).lamports - disputedAmount;
+
+      await resolveDispute(disputed, { favorBuyer: {} });
+
+      const order = await program.account.order.fetch(disputed.order);
+      const dispute = await program.account.dispute.fetch(disputed.dispute);
+
+      assert.deepEqual(order.status, { completed: {} });
+      assert.isNotNull(order.completedAt);
+      assert.deepEqual(dispute.status, { resolved: {} });
+
+      // El buyer recibe el monto menos la comisión (fee bearer por defecto) y el treasury la comisión
+      const fee = disputedAmount * 50 / 10000;
+      assert.equal(order.feeAmount.toNumber(), fee);
+      assert.approximately(
+        (await provider.connection.getBalance(buyer.publicKey)) - buyerBalanceBefore,
+        disputedAmount - fee,
+        1000
+      );
+      assert.equal(
+        (await provider.connection.getBalance(treasuryPda)) - treasuryBalanceBefore,
+        fee
+      );
+      // Al seller solo le regresa la renta de la cuenta wSOL del escrow
+      assert.approximately(
+        (await provider.connection.getBalance(seller.publicKey)) - sellerBalanceBefore,
+        wsolRent,
+        1000
+      );
+      assert.isNull(await provider.connection.getAccountInfo(disputed.escrowTokenAccount));
+
+      console.log("✅ Disputa a favor del buyer liquidada desde el escrow");
+    });
+
+    it("Debe reembolsar al seller al resolver a su favor", async () => {
+      const disputed = await openDisputedOrder("ORDER_003_FAVOR_SELLER", "STP_REF_003_FS");
+      const disputedAmount = 0.5 * LAMPORTS_PER_SOL;
+
+      const sellerBalanceBefore = await provider.connection.getBalance(seller.publicKey);
+      const buyerBalanceBefore = await provider.connection.getBalance(buyer.publicKey);
+      const wsolRent =
+        (await provider.connection.getAccountInfo(disputed.escrowTokenAccount)).lamports - disputedAmount;
+
+      await resolveDispute(disputed, { favorSeller: {} });
+
+      const order = await program.account.order.fetch(disputed.order);
+
+      assert.deepEqual(order.status, { cancelled: {} });
+      assert.equal(order.feeAmount.toNumber(), 0);
+      assert.approximately(
+        (await provider.connection.getBalance(seller.publicKey)) - sellerBalanceBefore,
+        disputedAmount + wsolRent,
+        1000
+      );
+      assert.equal(await provider.connection.getBalance(buyer.publicKey), buyerBalanceBefore);
+
+      // La orden ya se reembolsó: aun con una cuenta wSOL nueva no se resuelve otra vez
+      try {
+        await resolveDispute(
+          disputed,
+          { favorSeller: {} },
+          await createWsolEscrowAccount(disputed.escrow)
+        );
+        assert.fail("Debería haber fallado por estado de la orden");
+      } catch (error) {
+        assert.include(error.toString(), "InvalidOrderStatus");
+      }
+
+      console.log("✅ Disputa a favor del seller reembolsada desde el escrow");
     });
   });
 
@@ -650,6 +1224,7 @@ describe("P2P Payment System Tests", () => {
     let orderPda: PublicKey;
     let escrowPda: PublicKey;
     const amount = new BN(0.3 * LAMPORTS_PER_SOL);
+    let escrowWsolAccount: PublicKey;
 
     before(async () => {
       [orderPda] = PublicKey.findProgramAddressSync(
@@ -666,52 +1241,113 @@ describe("P2P Payment System Tests", () => {
         .createOrder(
           orderId,
           amount,
-          new BN(1_000_000_000),
-          { sol: {} },
+          new BN(1_200_000_000),
           { stp: {} },
-          "STP_REF_004"
+          "STP_REF_004",
+          false,
+          { sell: {} },
+          null,
+          null
         )
         .accounts({
           order: orderPda,
           escrow: escrowPda,
-          sellerProfile: sellerProfilePda,
-          seller: seller.publicKey,
+          makerProfile: sellerProfilePda,
+          maker: seller.publicKey,
+          kycRecord: kycRecordPda(seller.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          platform: platformPda,
+          supportedMint: solMintPda,
+          referenceRate: solRatePda,
           systemProgram: SystemProgram.programId,
         })
         .signers([seller])
         .rpc();
 
       await program.methods
-        .acceptOrder()
+        .acceptOrder(null)
         .accounts({
           order: orderPda,
           buyerProfile: buyerProfilePda,
+          makerProfile: sellerProfilePda,
+          feeSchedule: feeSchedulePda,
           buyer: buyer.publicKey,
+          kycRecord: kycRecordPda(buyer.publicKey),
+          buyerBlocked: blockedAddressPda(buyer.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          supportedMint: solMintPda,
+          platform: platformPda,
         })
         .signers([buyer])
         .rpc();
 
+      escrowWsolAccount = await createWsolEscrowAccount(escrowPda);
+
       await program.methods
         .depositToEscrowNative()
         .accounts({
+          platform: platformPda,
           order: orderPda,
           escrow: escrowPda,
+          supportedMint: solMintPda,
+          sellerTokenAccount: null,
+          escrowTokenAccount: escrowWsolAccount,
           seller: seller.publicKey,
+          mint: NATIVE_MINT,
+          tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
         })
         .signers([seller])
         .rpc();
     });
 
+    it("Las instrucciones de compatibilidad validan el tipo de activo", async () => {
+      try {
+        await program.methods
+          .cancelOrderSpl()
+          .accounts({
+            dailyStats: await dailyStatsPda(),
+            order: orderPda,
+            escrow: escrowPda,
+            escrowTokenAccount: escrowWsolAccount,
+            sellerTokenAccount: null,
+            makerProfile: sellerProfilePda,
+            takerProfile: buyerProfilePda,
+            seller: seller.publicKey,
+            mint: NATIVE_MINT,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([seller])
+          .rpc();
+
+        assert.fail("Debería haber fallado por tipo de activo");
+      } catch (error) {
+        assert.include(error.toString(), "InvalidTokenType");
+        console.log("✅ cancel_order_spl rechaza órdenes de SOL");
+      }
+    });
+
     it("Seller debe poder cancelar orden y recuperar fondos", async () => {
       const sellerBalanceBefore = await provider.connection.getBalance(seller.publicKey);
+      const sellerVolumeBefore = (await program.account.userProfile.fetch(sellerProfilePda)).dailyVolumeMxn;
+      const buyerVolumeBefore = (await program.account.userProfile.fetch(buyerProfilePda)).dailyVolumeMxn;
+      const wsolRent = (await provider.connection.getAccountInfo(escrowWsolAccount)).lamports - amount.toNumber();
 
       await program.methods
         .cancelOrderNative()
         .accounts({
+          dailyStats: await dailyStatsPda(),
           order: orderPda,
           escrow: escrowPda,
+          escrowTokenAccount: escrowWsolAccount,
+          sellerTokenAccount: null,
+          makerProfile: sellerProfilePda,
+          takerProfile: buyerProfilePda,
           seller: seller.publicKey,
+          mint: NATIVE_MINT,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
         })
         .signers([seller])
         .rpc();
@@ -722,11 +1358,53 @@ describe("P2P Payment System Tests", () => {
       assert.deepEqual(order.status, { cancelled: {} });
       assert.approximately(
         sellerBalanceAfter - sellerBalanceBefore,
-        amount.toNumber(),
+        amount.toNumber() + wsolRent,
         10000 // Mayor margen por fees de tx
       );
 
-      console.log("✅ Orden cancelada y fondos devueltos al seller");
+      // El volumen registrado al crear y aceptar deja de contar para los límites
+      const sellerProfile = await program.account.userProfile.fetch(sellerProfilePda);
+      const buyerProfile = await program.account.userProfile.fetch(buyerProfilePda);
+      assert.equal(
+        sellerProfile.dailyVolumeMxn.toString(),
+        sellerVolumeBefore.sub(order.makerVolumeMxn).toString()
+      );
+      assert.equal(
+        buyerProfile.dailyVolumeMxn.toString(),
+        buyerVolumeBefore.sub(order.amountMxn).toString()
+      );
+
+      console.log("✅ Orden cancelada y fondos devueltos al seller");
+    });
+
+    it("No debe reembolsar dos veces una orden cancelada", async () => {
+      // Una cuenta wSOL nueva para el escrow pasa las restricciones de cuentas
+      const newEscrowWsolAccount = await createWsolEscrowAccount(escrowPda);
+
+      try {
+        await program.methods
+          .cancelOrderNative()
+          .accounts({
+            dailyStats: await dailyStatsPda(),
+            order: orderPda,
+            escrow: escrowPda,
+            escrowTokenAccount: newEscrowWsolAccount,
+            sellerTokenAccount: null,
+            makerProfile: sellerProfilePda,
+            takerProfile: buyerProfilePda,
+            seller: seller.publicKey,
+            mint: NATIVE_MINT,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([seller])
+          .rpc();
+
+        assert.fail("Debería haber fallado por estado de la orden");
+      } catch (error) {
+        assert.include(error.toString(), "InvalidOrderStatus");
+        console.log("✅ Una orden cancelada no se reembolsa otra vez");
+      }
     });
   });
 
@@ -749,15 +1427,23 @@ describe("P2P Payment System Tests", () => {
             orderId,
             new BN(1 * LAMPORTS_PER_SOL),
             new BN(10_000_000_000), // 10,000 MXN (excede límite)
-            { sol: {} },
             { stp: {} },
-            "STP_REF_LIMIT"
+            "STP_REF_LIMIT",
+            false,
+            { sell: {} },
+            null,
+            null
           )
           .accounts({
             order: orderPda,
             escrow: escrowPda,
-            sellerProfile: sellerProfilePda,
-            seller: seller.publicKey,
+            makerProfile: sellerProfilePda,
+            maker: seller.publicKey,
+            kycRecord: kycRecordPda(seller.publicKey),
+            makerBlocked: blockedAddressPda(seller.publicKey),
+            platform: platformPda,
+            supportedMint: solMintPda,
+            referenceRate: solRatePda,
             systemProgram: SystemProgram.programId,
           })
           .signers([seller])
@@ -770,6 +1456,53 @@ describe("P2P Payment System Tests", () => {
       }
     });
 
+    it("Debe rechazar orden con precio fuera de la banda de referencia", async () => {
+      const orderId = "ORDER_OFFMARKET";
+      const [orderPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("order"), Buffer.from(orderId)],
+        program.programId
+      );
+
+      const [escrowPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), orderPda.toBuffer()],
+        program.programId
+      );
+
+      try {
+        await program.methods
+          .createOrder(
+            orderId,
+            new BN(1 * LAMPORTS_PER_SOL),
+            new BN(1_000_000), // 1 SOL por 1 MXN
+            { stp: {} },
+            "STP_REF_OFFMARKET",
+            false,
+            { sell: {} },
+            null,
+            null
+          )
+          .accounts({
+            order: orderPda,
+            escrow: escrowPda,
+            makerProfile: sellerProfilePda,
+            maker: seller.publicKey,
+            kycRecord: kycRecordPda(seller.publicKey),
+            makerBlocked: blockedAddressPda(seller.publicKey),
+            platform: platformPda,
+            supportedMint: solMintPda,
+            referenceRate: solRatePda,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([seller])
+          .rpc();
+
+        assert.fail("Debería haber fallado por desviación de precio");
+      } catch (error) {
+        assert.include(error.toString(), "PriceDeviationExceeded");
+        console.log("✅ Banda de precio validada correctamente");
+      }
+    });
+
     it("Debe rechazar usuario sin KYC", async () => {
       const noKycUser = Keypair.generate();
 
@@ -785,53 +1518,3787 @@ describe("P2P Payment System Tests", () => {
         program.programId
       );
 
+      // Sin KycRecord emitido por kyc_nft no se puede crear el perfil
+      try {
+        await program.methods
+          .createUserProfile()
+          .accounts({
+            userProfile: noKycProfilePda,
+            user: noKycUser.publicKey,
+            kycRecord: kycRecordPda(noKycUser.publicKey),
+            userBlocked: blockedAddressPda(noKycUser.publicKey),
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([noKycUser])
+          .rpc();
+
+        assert.fail("Debería haber fallado por falta de KYC");
+      } catch (error) {
+        assert.include(error.toString(), "KYCRequired");
+        console.log("✅ Validación KYC funcionando correctamente");
+      }
+    });
+
+    it("Debe rechazar KycRecord expirado o de otro usuario", async () => {
+      const expiredUser = kycUser("expired");
+
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(expiredUser.publicKey, 2 * LAMPORTS_PER_SOL)
+      );
+
+      const [expiredProfilePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("user_profile"), expiredUser.publicKey.toBuffer()],
+        program.programId
+      );
+
+      try {
+        await program.methods
+          .createUserProfile()
+          .accounts({
+            userProfile: expiredProfilePda,
+            user: expiredUser.publicKey,
+            kycRecord: kycRecordPda(expiredUser.publicKey),
+            userBlocked: blockedAddressPda(expiredUser.publicKey),
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([expiredUser])
+          .rpc();
+
+        assert.fail("Debería haber fallado por KYC expirado");
+      } catch (error) {
+        assert.include(error.toString(), "KycExpired");
+      }
+
+      // El KycRecord de otro usuario no cumple las seeds
+      try {
+        await program.methods
+          .createUserProfile()
+          .accounts({
+            userProfile: expiredProfilePda,
+            user: expiredUser.publicKey,
+            kycRecord: kycRecordPda(seller.publicKey),
+            userBlocked: blockedAddressPda(seller.publicKey),
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([expiredUser])
+          .rpc();
+
+        assert.fail("Debería haber fallado por KycRecord ajeno");
+      } catch (error) {
+        assert.include(error.toString(), "ConstraintSeeds");
+        console.log("✅ KycRecord expirado o ajeno rechazado");
+      }
+    });
+  });
+
+  describe("8. Órdenes Pre-fondeadas", () => {
+    const orderId = "ORDER_005_PREFUND";
+    let orderPda: PublicKey;
+    let escrowPda: PublicKey;
+    const amount = new BN(0.2 * LAMPORTS_PER_SOL);
+
+    before(async () => {
+      [orderPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("order"), Buffer.from(orderId)],
+        program.programId
+      );
+
+      [escrowPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), orderPda.toBuffer()],
+        program.programId
+      );
+    });
+
+    it("Seller debe fondear el escrow al crear la orden", async () => {
+      const escrowWsolAccount = await createWsolEscrowAccount(escrowPda);
+
       await program.methods
-        .createUserProfile(false, null) // Sin KYC
+        .createOrder(
+          orderId,
+          amount,
+          new BN(800_000_000),
+          { stp: {} },
+          "STP_REF_005",
+          true,
+          { sell: {} },
+          null,
+          null
+        )
         .accounts({
-          userProfile: noKycProfilePda,
-          user: noKycUser.publicKey,
+          order: orderPda,
+          escrow: escrowPda,
+          makerProfile: sellerProfilePda,
+          maker: seller.publicKey,
+          kycRecord: kycRecordPda(seller.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          makerTokenAccount: null,
+          escrowTokenAccount: escrowWsolAccount,
+          mint: NATIVE_MINT,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          platform: platformPda,
+          supportedMint: solMintPda,
+          referenceRate: solRatePda,
           systemProgram: SystemProgram.programId,
         })
-        .signers([noKycUser])
+        .signers([seller])
         .rpc();
 
-      const orderId = "ORDER_NO_KYC";
-      const [orderPda] = PublicKey.findProgramAddressSync(
-        [Buffer.from("order"), Buffer.from(orderId)],
+      const order = await program.account.order.fetch(orderPda);
+      const escrowAccount = await getAccount(provider.connection, escrowWsolAccount);
+
+      assert.deepEqual(order.status, { open: {} });
+      assert.equal(order.prefunded, true);
+      assert.equal(escrowAccount.amount.toString(), amount.toString());
+
+      console.log("✅ Orden pre-fondeada creada:", orderId);
+    });
+
+    it("Aceptar una orden pre-fondeada la lleva directo a Funded", async () => {
+      await program.methods
+        .acceptOrder(null)
+        .accounts({
+          order: orderPda,
+          buyerProfile: buyerProfilePda,
+          makerProfile: sellerProfilePda,
+          feeSchedule: feeSchedulePda,
+          buyer: buyer.publicKey,
+          kycRecord: kycRecordPda(buyer.publicKey),
+          buyerBlocked: blockedAddressPda(buyer.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          supportedMint: solMintPda,
+          platform: platformPda,
+        })
+        .signers([buyer])
+        .rpc();
+
+      const order = await program.account.order.fetch(orderPda);
+
+      assert.equal(order.buyer?.toString(), buyer.publicKey.toString());
+      assert.deepEqual(order.status, { funded: {} });
+
+      console.log("✅ Orden pre-fondeada aceptada, lista para pago fiat");
+    });
+  });
+
+  describe("9. Anuncios con Inventario", () => {
+    const adId = "AD_001_SOL";
+    let adPda: PublicKey;
+    let vaultPda: PublicKey;
+    let vaultWsolAccount: PublicKey;
+    let tradePda: PublicKey;
+    const priceMxn = new BN(4_000_000_000); // 4,000 MXN por SOL
+    const inventory = new BN(1 * LAMPORTS_PER_SOL);
+
+    before(async () => {
+      [adPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("ad"), Buffer.from(adId)],
         program.programId
       );
 
-      const [escrowPda] = PublicKey.findProgramAddressSync(
-        [Buffer.from("escrow"), orderPda.toBuffer()],
+      [vaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("ad_vault"), adPda.toBuffer()],
+        program.programId
+      );
+
+      [tradePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("trade"), adPda.toBuffer(), new BN(0).toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+    });
+
+    it("Merchant debe crear y fondear un anuncio", async () => {
+      vaultWsolAccount = await createWsolEscrowAccount(vaultPda);
+
+      await program.methods
+        .createAd(
+          adId,
+          priceMxn,
+          new BN(100_000_000), // 100 MXN mínimo
+          new BN(2_000_000_000), // 2,000 MXN máximo
+          [{ stp: {} }, { spei: {} }]
+        )
+        .accounts({
+          ad: adPda,
+          vault: vaultPda,
+          vaultTokenAccount: vaultWsolAccount,
+          supportedMint: solMintPda,
+          merchantProfile: sellerProfilePda,
+          merchantBlocked: blockedAddressPda(seller.publicKey),
+          kycRecord: kycRecordPda(seller.publicKey),
+          platform: platformPda,
+          referenceRate: solRatePda,
+          merchant: seller.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([seller])
+        .rpc();
+
+      await program.methods
+        .depositToAd(inventory)
+        .accounts({
+          platform: platformPda,
+          ad: adPda,
+          merchantTokenAccount: null,
+          vaultTokenAccount: vaultWsolAccount,
+          merchant: seller.publicKey,
+          mint: NATIVE_MINT,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([seller])
+        .rpc();
+
+      const ad = await program.account.ad.fetch(adPda);
+      const vaultAccount = await getAccount(provider.connection, vaultWsolAccount);
+
+      assert.equal(ad.availableAmount.toString(), inventory.toString());
+      assert.equal(ad.vaultTokenAccount.toBase58(), vaultWsolAccount.toBase58());
+      assert.equal(vaultAccount.amount.toString(), inventory.toString());
+      assert.equal(ad.isActive, true);
+
+      console.log("✅ Anuncio creado con inventario:", inventory.toNumber() / LAMPORTS_PER_SOL, "SOL");
+    });
+
+    it("Debe rechazar anuncios con precio fuera de la banda de referencia", async () => {
+      const offMarketAdId = "AD_OFF_MARKET";
+      const [offMarketAdPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("ad"), Buffer.from(offMarketAdId)],
+        program.programId
+      );
+      const [offMarketVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("ad_vault"), offMarketAdPda.toBuffer()],
         program.programId
       );
 
       try {
         await program.methods
-          .createOrder(
-            orderId,
-            new BN(0.1 * LAMPORTS_PER_SOL),
-            new BN(1_000_000_000),
-            { sol: {} },
-            { stp: {} },
-            "STP_REF_NOKYC"
+          .createAd(
+            offMarketAdId,
+            new BN(1_000_000), // 1 MXN por SOL
+            new BN(100_000_000),
+            new BN(2_000_000_000),
+            [{ spei: {} }]
           )
           .accounts({
-            order: orderPda,
-            escrow: escrowPda,
-            sellerProfile: noKycProfilePda,
-            seller: noKycUser.publicKey,
+            ad: offMarketAdPda,
+            vault: offMarketVaultPda,
+            vaultTokenAccount: await createWsolEscrowAccount(offMarketVaultPda),
+            supportedMint: solMintPda,
+            merchantProfile: sellerProfilePda,
+            merchantBlocked: blockedAddressPda(seller.publicKey),
+            kycRecord: kycRecordPda(seller.publicKey),
+            platform: platformPda,
+            referenceRate: solRatePda,
+            merchant: seller.publicKey,
             systemProgram: SystemProgram.programId,
           })
-          .signers([noKycUser])
+          .signers([seller])
           .rpc();
 
-        assert.fail("Debería haber fallado por falta de KYC");
+        assert.fail("Debería haber fallado por desviación de precio");
       } catch (error) {
-        assert.include(error.toString(), "KYCRequired");
-        console.log("✅ Validación KYC funcionando correctamente");
+        assert.include(error.toString(), "PriceDeviationExceeded");
+      }
+
+      const updateAdPrice = (price: BN) =>
+        program.methods
+          .updateAd(price, new BN(100_000_000), new BN(2_000_000_000), true)
+          .accounts({
+            ad: adPda,
+            merchantProfile: sellerProfilePda,
+            platform: platformPda,
+            referenceRate: solRatePda,
+            merchant: seller.publicKey,
+          })
+          .signers([seller])
+          .rpc();
+
+      // Subir el precio del anuncio al doble de la referencia tampoco está permitido
+      try {
+        await updateAdPrice(new BN(8_000_000_000));
+        assert.fail("Debería haber fallado por desviación de precio");
+      } catch (error) {
+        assert.include(error.toString(), "PriceDeviationExceeded");
+      }
+
+      await updateAdPrice(new BN(4_100_000_000));
+      await updateAdPrice(priceMxn);
+
+      const ad = await program.account.ad.fetch(adPda);
+      assert.equal(ad.priceMxn.toString(), priceMxn.toString());
+
+      console.log("✅ Precio del anuncio validado contra la banda de referencia");
+    });
+
+    it("Buyer debe abrir un trade que reserva inventario", async () => {
+      const buyerVolumeBefore = (await program.account.userProfile.fetch(buyerProfilePda)).dailyVolumeMxn;
+      const merchantVolumeBefore = (await program.account.userProfile.fetch(sellerProfilePda)).dailyVolumeMxn;
+
+      await program.methods
+        .openTrade(new BN(1_000_000_000), { spei: {} }) // 1,000 MXN
+        .accounts({
+          ad: adPda,
+          trade: tradePda,
+          supportedMint: solMintPda,
+          buyerProfile: buyerProfilePda,
+          merchantProfile: sellerProfilePda,
+          feeSchedule: feeSchedulePda,
+          platform: platformPda,
+          buyer: buyer.publicKey,
+          buyerBlocked: blockedAddressPda(buyer.publicKey),
+          kycRecord: kycRecordPda(buyer.publicKey),
+          merchantBlocked: blockedAddressPda(seller.publicKey),
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyer])
+        .rpc();
+
+      const ad = await program.account.ad.fetch(adPda);
+      const trade = await program.account.trade.fetch(tradePda);
+      const expected = 0.25 * LAMPORTS_PER_SOL; // 1,000 / 4,000
+
+      assert.equal(trade.amount.toNumber(), expected);
+      assert.equal(ad.lockedAmount.toNumber(), expected);
+      assert.equal(ad.availableAmount.toNumber(), inventory.toNumber() - expected);
+      assert.deepEqual(trade.status, { open: {} });
+
+      // El trade cuenta para los límites acumulados de ambas partes
+      const buyerProfile = await program.account.userProfile.fetch(buyerProfilePda);
+      const merchantProfile = await program.account.userProfile.fetch(sellerProfilePda);
+      assert.equal(
+        buyerProfile.dailyVolumeMxn.toString(),
+        buyerVolumeBefore.add(trade.amountMxn).toString()
+      );
+      assert.equal(
+        merchantProfile.dailyVolumeMxn.toString(),
+        merchantVolumeBefore.add(trade.amountMxn).toString()
+      );
+
+      console.log("✅ Trade abierto por", expected / LAMPORTS_PER_SOL, "SOL");
+    });
+
+    it("Debe exigir el KycRecord vigente del buyer al abrir un trade", async () => {
+      const [tradePda2] = PublicKey.findProgramAddressSync(
+        [Buffer.from("trade"), adPda.toBuffer(), new BN(1).toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+
+      try {
+        await program.methods
+          .openTrade(new BN(500_000_000), { spei: {} })
+          .accounts({
+            ad: adPda,
+            trade: tradePda2,
+            supportedMint: solMintPda,
+            buyerProfile: buyerProfilePda,
+            merchantProfile: sellerProfilePda,
+            feeSchedule: feeSchedulePda,
+            platform: platformPda,
+            buyer: buyer.publicKey,
+            buyerBlocked: blockedAddressPda(buyer.publicKey),
+            kycRecord: kycRecordPda(seller.publicKey),
+            merchantBlocked: blockedAddressPda(seller.publicKey),
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([buyer])
+          .rpc();
+
+        assert.fail("Debería haber fallado con el KycRecord de otro usuario");
+      } catch (error) {
+        assert.include(error.toString(), "ConstraintSeeds");
+        console.log("✅ KYC del buyer revalidado al abrir el trade");
       }
     });
+
+    it("Debe rechazar trades fuera de los límites del anuncio", async () => {
+      const [tradePda2] = PublicKey.findProgramAddressSync(
+        [Buffer.from("trade"), adPda.toBuffer(), new BN(1).toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+
+      try {
+        await program.methods
+          .openTrade(new BN(5_000_000_000), { spei: {} }) // 5,000 MXN (excede máximo)
+          .accounts({
+            ad: adPda,
+            trade: tradePda2,
+            supportedMint: solMintPda,
+            buyerProfile: buyerProfilePda,
+            merchantProfile: sellerProfilePda,
+            feeSchedule: feeSchedulePda,
+            platform: platformPda,
+            buyer: buyer.publicKey,
+            buyerBlocked: blockedAddressPda(buyer.publicKey),
+            kycRecord: kycRecordPda(buyer.publicKey),
+            merchantBlocked: blockedAddressPda(seller.publicKey),
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([buyer])
+          .rpc();
+
+        assert.fail("Debería haber fallado por exceder el máximo del anuncio");
+      } catch (error) {
+        assert.include(error.toString(), "TradeOutsideAdLimits");
+        console.log("✅ Límites del anuncio validados");
+      }
+    });
+
+    it("Merchant debe liberar el trade al buyer", async () => {
+      await program.methods
+        .confirmTradePayment("STP_TX_AD_0001")
+        .accounts({
+          trade: tradePda,
+          buyer: buyer.publicKey,
+        })
+        .signers([buyer])
+        .rpc();
+
+      const buyerBalanceBefore = await provider.connection.getBalance(buyer.publicKey);
+
+      await program.methods
+        .releaseTrade()
+        .accounts({
+          dailyStats: await dailyStatsPda(),
+          trade: tradePda,
+          ad: adPda,
+          vault: vaultPda,
+          platform: platformPda,
+          supportedMint: solMintPda,
+          buyer: buyer.publicKey,
+          treasury: treasuryPda,
+          vaultTokenAccount: vaultWsolAccount,
+          unwrapTokenAccount: adUnwrapPda(adPda),
+          buyerTokenAccount: null,
+          treasuryTokenAccount: null,
+          merchantProfile: sellerProfilePda,
+          buyerProfile: buyerProfilePda,
+          authority: seller.publicKey,
+          mint: NATIVE_MINT,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([seller])
+        .rpc();
+
+      const ad = await program.account.ad.fetch(adPda);
+      const trade = await program.account.trade.fetch(tradePda);
+      const buyerBalanceAfter = await provider.connection.getBalance(buyer.publicKey);
+
+      const fee = trade.amount.toNumber() * 50 / 10000;
+
+      assert.deepEqual(trade.status, { completed: {} });
+      assert.equal(ad.lockedAmount.toNumber(), 0);
+      assert.equal(buyerBalanceAfter - buyerBalanceBefore, trade.amount.toNumber() - fee);
+
+      console.log("✅ Trade liberado desde el inventario del anuncio");
+    });
+
+    it("Debe liberar un trade SPL solo a la cuenta de tokens del buyer", async () => {
+      const usdcAdId = "AD_002_USDC";
+      const [usdcAdPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("ad"), Buffer.from(usdcAdId)],
+        program.programId
+      );
+      const [usdcVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("ad_vault"), usdcAdPda.toBuffer()],
+        program.programId
+      );
+      const [usdcTradePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("trade"), usdcAdPda.toBuffer(), new BN(0).toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+      const usdcVaultTokenAccount = await createAccount(
+        provider.connection,
+        authority.payer,
+        usdcMint,
+        usdcVaultPda,
+        Keypair.generate()
+      );
+
+      await program.methods
+        .createAd(
+          usdcAdId,
+          new BN(20_000_000), // 20 MXN por USDC
+          new BN(100_000_000),
+          new BN(2_000_000_000),
+          [{ spei: {} }]
+        )
+        .accounts({
+          ad: usdcAdPda,
+          vault: usdcVaultPda,
+          vaultTokenAccount: usdcVaultTokenAccount,
+          supportedMint: usdcMintPda,
+          merchantProfile: sellerProfilePda,
+          merchantBlocked: blockedAddressPda(seller.publicKey),
+          kycRecord: kycRecordPda(seller.publicKey),
+          platform: platformPda,
+          referenceRate: usdcRatePda,
+          merchant: seller.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([seller])
+        .rpc();
+
+      await program.methods
+        .depositToAd(new BN(50 * 1_000_000))
+        .accounts({
+          platform: platformPda,
+          ad: usdcAdPda,
+          merchantTokenAccount: sellerTokenAccount,
+          vaultTokenAccount: usdcVaultTokenAccount,
+          merchant: seller.publicKey,
+          mint: usdcMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([seller])
+        .rpc();
+
+      await program.methods
+        .openTrade(new BN(500_000_000), { spei: {} }) // 500 MXN = 25 USDC
+        .accounts({
+          ad: usdcAdPda,
+          trade: usdcTradePda,
+          supportedMint: usdcMintPda,
+          buyerProfile: buyerProfilePda,
+          merchantProfile: sellerProfilePda,
+          feeSchedule: feeSchedulePda,
+          platform: platformPda,
+          buyer: buyer.publicKey,
+          buyerBlocked: blockedAddressPda(buyer.publicKey),
+          kycRecord: kycRecordPda(buyer.publicKey),
+          merchantBlocked: blockedAddressPda(seller.publicKey),
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyer])
+        .rpc();
+
+      await program.methods
+        .confirmTradePayment("STP_TX_AD_USDC_0001")
+        .accounts({
+          trade: usdcTradePda,
+          buyer: buyer.publicKey,
+        })
+        .signers([buyer])
+        .rpc();
+
+      const releaseUsdcTrade = async (recipientTokenAccount: PublicKey) =>
+        program.methods
+          .releaseTrade()
+          .accounts({
+            dailyStats: await dailyStatsPda(),
+            trade: usdcTradePda,
+            ad: usdcAdPda,
+            vault: usdcVaultPda,
+            platform: platformPda,
+            supportedMint: usdcMintPda,
+            buyer: buyer.publicKey,
+            treasury: treasuryPda,
+            vaultTokenAccount: usdcVaultTokenAccount,
+            unwrapTokenAccount: null,
+            buyerTokenAccount: recipientTokenAccount,
+            treasuryTokenAccount,
+            merchantProfile: sellerProfilePda,
+            buyerProfile: buyerProfilePda,
+            authority: authority.publicKey,
+            mint: usdcMint,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+
+      // Quien libera no puede desviar el pago a una cuenta de tokens propia
+      const thirdPartyTokenAccount = await createAccount(
+        provider.connection,
+        authority.payer,
+        usdcMint,
+        authority.publicKey,
+        Keypair.generate()
+      );
+
+      try {
+        await releaseUsdcTrade(thirdPartyTokenAccount);
+        assert.fail("Debería haber fallado con una cuenta ajena al buyer");
+      } catch (error) {
+        assert.include(error.toString(), "InvalidRecipientTokenAccount");
+      }
+
+      const buyerBalanceBefore = (await getAccount(provider.connection, buyerTokenAccount)).amount;
+      await releaseUsdcTrade(buyerTokenAccount);
+
+      const trade = await program.account.trade.fetch(usdcTradePda);
+      const buyerBalanceAfter = (await getAccount(provider.connection, buyerTokenAccount)).amount;
+
+      assert.deepEqual(trade.status, { completed: {} });
+      assert.isTrue(buyerBalanceAfter > buyerBalanceBefore);
+      assert.equal(
+        (await getAccount(provider.connection, thirdPartyTokenAccount)).amount.toString(),
+        "0"
+      );
+
+      console.log("✅ Trade SPL liberado solo a la cuenta del buyer");
+    });
+
+    const openAdTrade = async (): Promise<PublicKey> => {
+      const ad = await program.account.ad.fetch(adPda);
+      const [pendingTradePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("trade"), adPda.toBuffer(), ad.tradeCount.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+
+      await program.methods
+        .openTrade(new BN(500_000_000), { spei: {} }) // 500 MXN
+        .accounts({
+          ad: adPda,
+          trade: pendingTradePda,
+          supportedMint: solMintPda,
+          buyerProfile: buyerProfilePda,
+          merchantProfile: sellerProfilePda,
+          feeSchedule: feeSchedulePda,
+          platform: platformPda,
+          buyer: buyer.publicKey,
+          buyerBlocked: blockedAddressPda(buyer.publicKey),
+          kycRecord: kycRecordPda(buyer.publicKey),
+          merchantBlocked: blockedAddressPda(seller.publicKey),
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyer])
+        .rpc();
+
+      return pendingTradePda;
+    };
+
+    const cancelAdTrade = async (pendingTradePda: PublicKey, signer = authority.payer) => {
+      await program.methods
+        .cancelTrade()
+        .accounts({
+          dailyStats: await dailyStatsPda(),
+          trade: pendingTradePda,
+          ad: adPda,
+          buyerProfile: buyerProfilePda,
+          merchantProfile: sellerProfilePda,
+          platform: platformPda,
+          authority: signer.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers(signer === authority.payer ? [] : [signer])
+        .rpc();
+    };
+
+    it("Cancelar un trade debe liberar el volumen de ambas partes", async () => {
+      const buyerVolumeBefore = (await program.account.userProfile.fetch(buyerProfilePda)).dailyVolumeMxn;
+      const merchantVolumeBefore = (await program.account.userProfile.fetch(sellerProfilePda)).dailyVolumeMxn;
+
+      const pendingTradePda = await openAdTrade();
+      await cancelAdTrade(pendingTradePda, buyer);
+
+      const trade = await program.account.trade.fetch(pendingTradePda);
+      const buyerProfile = await program.account.userProfile.fetch(buyerProfilePda);
+      const merchantProfile = await program.account.userProfile.fetch(sellerProfilePda);
+
+      assert.deepEqual(trade.status, { cancelled: {} });
+      assert.equal(buyerProfile.dailyVolumeMxn.toString(), buyerVolumeBefore.toString());
+      assert.equal(merchantProfile.dailyVolumeMxn.toString(), merchantVolumeBefore.toString());
+
+      console.log("✅ Volumen liberado al cancelar el trade");
+    });
+
+    it("Un trade vencido debe liberar el volumen al expirar", async () => {
+      const buyerVolumeBefore = (await program.account.userProfile.fetch(buyerProfilePda)).dailyVolumeMxn;
+      const merchantVolumeBefore = (await program.account.userProfile.fetch(sellerProfilePda)).dailyVolumeMxn;
+
+      const pendingTradePda = await openAdTrade();
+
+      // Dentro de la ventana de pago el merchant no puede darlo por vencido
+      try {
+        await cancelAdTrade(pendingTradePda, seller);
+
+        assert.fail("Debería haber fallado antes de que venza el trade");
+      } catch (error) {
+        assert.include(error.toString(), "Unauthorized");
+      }
+
+      // El oráculo de la plataforma expira el trade sin pago
+      await cancelAdTrade(pendingTradePda);
+
+      const trade = await program.account.trade.fetch(pendingTradePda);
+      const buyerProfile = await program.account.userProfile.fetch(buyerProfilePda);
+      const merchantProfile = await program.account.userProfile.fetch(sellerProfilePda);
+
+      assert.deepEqual(trade.status, { cancelled: {} });
+      assert.equal(buyerProfile.dailyVolumeMxn.toString(), buyerVolumeBefore.toString());
+      assert.equal(merchantProfile.dailyVolumeMxn.toString(), merchantVolumeBefore.toString());
+
+      console.log("✅ Volumen liberado al expirar el trade");
+    });
+  });
+
+  describe("10. Órdenes de Compra", () => {
+    const orderId = "ORDER_006_BUY";
+    let orderPda: PublicKey;
+    let escrowPda: PublicKey;
+    const amount = new BN(0.4 * LAMPORTS_PER_SOL);
+
+    before(async () => {
+      [orderPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("order"), Buffer.from(orderId)],
+        program.programId
+      );
+
+      [escrowPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), orderPda.toBuffer()],
+        program.programId
+      );
+    });
+
+    it("Buyer debe publicar una orden de compra", async () => {
+      await program.methods
+        .createOrder(
+          orderId,
+          amount,
+          new BN(1_600_000_000),
+          { spei: {} },
+          "STP_REF_006",
+          false,
+          { buy: {} },
+          null,
+          null
+        )
+        .accounts({
+          order: orderPda,
+          escrow: escrowPda,
+          makerProfile: buyerProfilePda,
+          maker: buyer.publicKey,
+          kycRecord: kycRecordPda(buyer.publicKey),
+          makerBlocked: blockedAddressPda(buyer.publicKey),
+          makerTokenAccount: null,
+          escrowTokenAccount: null,
+          mint: null,
+          tokenProgram: null,
+          platform: platformPda,
+          supportedMint: solMintPda,
+          referenceRate: solRatePda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyer])
+        .rpc();
+
+      const order = await program.account.order.fetch(orderPda);
+
+      assert.deepEqual(order.side, { buy: {} });
+      assert.deepEqual(order.status, { open: {} });
+      assert.equal(order.buyer?.toString(), buyer.publicKey.toString());
+
+      console.log("✅ Orden de compra publicada:", orderId);
+    });
+
+    it("Debe rechazar accept_order sobre una orden de compra", async () => {
+      try {
+        await program.methods
+          .acceptOrder(null)
+          .accounts({
+            order: orderPda,
+            buyerProfile: sellerProfilePda,
+            makerProfile: buyerProfilePda,
+            feeSchedule: feeSchedulePda,
+            buyer: seller.publicKey,
+            kycRecord: kycRecordPda(seller.publicKey),
+            buyerBlocked: blockedAddressPda(seller.publicKey),
+            makerBlocked: blockedAddressPda(buyer.publicKey),
+            supportedMint: solMintPda,
+            platform: platformPda,
+          })
+          .signers([seller])
+          .rpc();
+
+        assert.fail("Debería haber fallado por el lado de la orden");
+      } catch (error) {
+        assert.include(error.toString(), "InvalidOrderSide");
+        console.log("✅ accept_order restringido a órdenes de venta");
+      }
+    });
+
+    it("Seller debe llenar la orden depositando en escrow", async () => {
+      const escrowWsolAccount = await createWsolEscrowAccount(escrowPda);
+
+      await program.methods
+        .fillOrder()
+        .accounts({
+          order: orderPda,
+          escrow: escrowPda,
+          supportedMint: solMintPda,
+          sellerProfile: sellerProfilePda,
+          makerProfile: buyerProfilePda,
+          feeSchedule: feeSchedulePda,
+          seller: seller.publicKey,
+          kycRecord: kycRecordPda(seller.publicKey),
+          sellerBlocked: blockedAddressPda(seller.publicKey),
+          makerBlocked: blockedAddressPda(buyer.publicKey),
+          sellerTokenAccount: null,
+          escrowTokenAccount: escrowWsolAccount,
+          mint: NATIVE_MINT,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          platform: platformPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([seller])
+        .rpc();
+
+      const order = await program.account.order.fetch(orderPda);
+      const escrowAccount = await getAccount(provider.connection, escrowWsolAccount);
+
+      assert.deepEqual(order.status, { funded: {} });
+      assert.equal(order.seller.toString(), seller.publicKey.toString());
+      assert.equal(escrowAccount.amount.toString(), amount.toString());
+
+      console.log("✅ Orden de compra llenada, lista para pago fiat");
+    });
+  });
+
+  describe("11. Órdenes con Precio Flotante", () => {
+    const feedId = "SOL/MXN";
+    const orderId = "ORDER_007_FLOAT";
+    let priceFeedPda: PublicKey;
+    let orderPda: PublicKey;
+    let escrowPda: PublicKey;
+    const amount = new BN(0.5 * LAMPORTS_PER_SOL);
+    const feedPrice = new BN(4_000_000_000); // 4,000 MXN por SOL
+
+    before(async () => {
+      [priceFeedPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("price_feed"), Buffer.from(feedId)],
+        program.programId
+      );
+
+      [orderPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("order"), Buffer.from(orderId)],
+        program.programId
+      );
+
+      [escrowPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), orderPda.toBuffer()],
+        program.programId
+      );
+
+      // Feed local escribible por la wallet de pruebas
+      await program.methods
+        .initializePriceFeed(feedId, NATIVE_MINT, authority.publicKey)
+        .accounts({
+          priceFeed: priceFeedPda,
+          platform: platformPda,
+          authority: authority.publicKey,
+          proposal: null,
+          council: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      await program.methods
+        .updatePriceFeed(feedPrice)
+        .accounts({
+          priceFeed: priceFeedPda,
+          updater: authority.publicKey,
+        })
+        .rpc();
+    });
+
+    it("Seller debe crear una orden con prima sobre el feed", async () => {
+      await program.methods
+        .createOrder(
+          orderId,
+          amount,
+          new BN(0),
+          { stp: {} },
+          "STP_REF_007",
+          false,
+          { sell: {} },
+          100, // +1% sobre el feed
+          null
+        )
+        .accounts({
+          order: orderPda,
+          escrow: escrowPda,
+          makerProfile: sellerProfilePda,
+          maker: seller.publicKey,
+          kycRecord: kycRecordPda(seller.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          makerTokenAccount: null,
+          escrowTokenAccount: null,
+          mint: null,
+          tokenProgram: null,
+          priceFeed: priceFeedPda,
+          platform: platformPda,
+          supportedMint: solMintPda,
+          referenceRate: solRatePda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([seller])
+        .rpc();
+
+      const order = await program.account.order.fetch(orderPda);
+
+      assert.equal(order.priceFeed?.toString(), priceFeedPda.toString());
+      assert.equal(order.premiumBps, 100);
+
+      console.log("✅ Orden flotante creada, cotización indicativa:", order.amountMxn.toNumber() / 1_000_000, "MXN");
+    });
+
+    it("Debe rechazar aceptar por encima del precio máximo del buyer", async () => {
+      try {
+        await program.methods
+          .acceptOrder(feedPrice) // Sin margen para la prima de 1%
+          .accounts({
+            order: orderPda,
+            buyerProfile: buyerProfilePda,
+            makerProfile: sellerProfilePda,
+            feeSchedule: feeSchedulePda,
+            buyer: buyer.publicKey,
+            kycRecord: kycRecordPda(buyer.publicKey),
+            buyerBlocked: blockedAddressPda(buyer.publicKey),
+            makerBlocked: blockedAddressPda(seller.publicKey),
+            supportedMint: solMintPda,
+            priceFeed: priceFeedPda,
+            referenceRate: solRatePda,
+            platform: platformPda,
+          })
+          .signers([buyer])
+          .rpc();
+
+        assert.fail("Debería haber fallado por slippage");
+      } catch (error) {
+        assert.include(error.toString(), "SlippageExceeded");
+        console.log("✅ Protección de slippage funcionando");
+      }
+    });
+
+    it("Debe rechazar aceptar si el precio re-cotizado sale de la banda de referencia", async () => {
+      // 4,500 + 1% de prima se desvía más del 10% de la tasa de referencia (4,000)
+      await program.methods
+        .updatePriceFeed(new BN(4_500_000_000))
+        .accounts({
+          priceFeed: priceFeedPda,
+          updater: authority.publicKey,
+        })
+        .rpc();
+
+      const acceptAccounts = {
+        order: orderPda,
+        buyerProfile: buyerProfilePda,
+        makerProfile: sellerProfilePda,
+        feeSchedule: feeSchedulePda,
+        buyer: buyer.publicKey,
+        kycRecord: kycRecordPda(buyer.publicKey),
+        buyerBlocked: blockedAddressPda(buyer.publicKey),
+        makerBlocked: blockedAddressPda(seller.publicKey),
+        supportedMint: solMintPda,
+        priceFeed: priceFeedPda,
+        platform: platformPda,
+      };
+
+      try {
+        await program.methods
+          .acceptOrder(new BN(5_000_000_000))
+          .accounts({ ...acceptAccounts, referenceRate: solRatePda })
+          .signers([buyer])
+          .rpc();
+
+        assert.fail("Debería haber fallado por desviación de precio");
+      } catch (error) {
+        assert.include(error.toString(), "PriceDeviationExceeded");
+      }
+
+      // Sin la tasa de referencia no se puede validar la cotización
+      try {
+        await program.methods
+          .acceptOrder(new BN(5_000_000_000))
+          .accounts({ ...acceptAccounts, referenceRate: null })
+          .signers([buyer])
+          .rpc();
+
+        assert.fail("Debería haber fallado sin tasa de referencia");
+      } catch (error) {
+        assert.include(error.toString(), "MissingReferenceRate");
+      }
+
+      const order = await program.account.order.fetch(orderPda);
+      assert.deepEqual(order.status, { open: {} });
+
+      console.log("✅ Cotización al aceptar validada contra la banda de referencia");
+    });
+
+    it("Debe fijar amount_mxn con el precio del feed al aceptar", async () => {
+      const newPrice = new BN(4_100_000_000); // 4,100 MXN por SOL
+
+      await program.methods
+        .updatePriceFeed(newPrice)
+        .accounts({
+          priceFeed: priceFeedPda,
+          updater: authority.publicKey,
+        })
+        .rpc();
+
+      await program.methods
+        .acceptOrder(new BN(4_200_000_000))
+        .accounts({
+          order: orderPda,
+          buyerProfile: buyerProfilePda,
+          makerProfile: sellerProfilePda,
+          feeSchedule: feeSchedulePda,
+          buyer: buyer.publicKey,
+          kycRecord: kycRecordPda(buyer.publicKey),
+          buyerBlocked: blockedAddressPda(buyer.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          supportedMint: solMintPda,
+          priceFeed: priceFeedPda,
+          referenceRate: solRatePda,
+          platform: platformPda,
+        })
+        .signers([buyer])
+        .rpc();
+
+      const order = await program.account.order.fetch(orderPda);
+      const expectedMxn = 4_141_000_000 * 0.5; // 4,100 + 1% por 0.5 SOL
+
+      assert.equal(order.amountMxn.toNumber(), expectedMxn);
+      assert.deepEqual(order.status, { accepted: {} });
+
+      console.log("✅ Precio fijado al aceptar:", expectedMxn / 1_000_000, "MXN");
+    });
+  });
+
+  describe("12. Token-2022 con Transfer Fee", () => {
+    const orderId = "ORDER_PYUSD_001";
+    const amount = new BN(100 * 1_000_000); // 100 tokens
+    const amountMxn = new BN(2_000_000_000); // 2,000 MXN
+    const transferFeeBps = 100; // 1% retenido por el mint en cada transferencia
+    const mintKeypair = Keypair.generate();
+    let supportedMintPda: PublicKey;
+    let ratePda: PublicKey;
+    let orderPda: PublicKey;
+    let escrowPda: PublicKey;
+    let sellerAccount: PublicKey;
+    let escrowAccount: PublicKey;
+    let buyerAccount: PublicKey;
+    let treasuryAccount: PublicKey;
+
+    // Comisión que retiene la extensión transfer-fee (redondeo hacia arriba)
+    const transferFee = (value: number) => Math.ceil((value * transferFeeBps) / 10000);
+
+    before(async () => {
+      const mintLen = getMintLen([ExtensionType.TransferFeeConfig]);
+      const lamports = await provider.connection.getMinimumBalanceForRentExemption(mintLen);
+
+      const tx = new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: authority.publicKey,
+          newAccountPubkey: mintKeypair.publicKey,
+          space: mintLen,
+          lamports,
+          programId: TOKEN_2022_PROGRAM_ID,
+        }),
+        createInitializeTransferFeeConfigInstruction(
+          mintKeypair.publicKey,
+          authority.publicKey,
+          authority.publicKey,
+          transferFeeBps,
+          BigInt(1_000_000 * 1_000_000),
+          TOKEN_2022_PROGRAM_ID
+        ),
+        createInitializeMintInstruction(
+          mintKeypair.publicKey,
+          6,
+          authority.publicKey,
+          null,
+          TOKEN_2022_PROGRAM_ID
+        )
+      );
+      await sendAndConfirmTransaction(provider.connection, tx, [authority.payer, mintKeypair]);
+
+      [supportedMintPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("supported_mint"), mintKeypair.publicKey.toBuffer()],
+        program.programId
+      );
+
+      [ratePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("reference_rate"), mintKeypair.publicKey.toBuffer()],
+        program.programId
+      );
+
+      [orderPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("order"), Buffer.from(orderId)],
+        program.programId
+      );
+
+      [escrowPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), orderPda.toBuffer()],
+        program.programId
+      );
+
+      const newAccount = (owner: PublicKey) =>
+        createAccount(
+          provider.connection,
+          authority.payer,
+          mintKeypair.publicKey,
+          owner,
+          Keypair.generate(),
+          undefined,
+          TOKEN_2022_PROGRAM_ID
+        );
+
+      sellerAccount = await newAccount(seller.publicKey);
+      escrowAccount = await newAccount(escrowPda);
+      buyerAccount = await newAccount(buyer.publicKey);
+      treasuryAccount = await createTreasuryAta(mintKeypair.publicKey, TOKEN_2022_PROGRAM_ID);
+
+      await mintTo(
+        provider.connection,
+        authority.payer,
+        mintKeypair.publicKey,
+        sellerAccount,
+        authority.publicKey,
+        1_000 * 1_000_000,
+        [],
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+
+      await program.methods
+        .registerMint(new BN(1_000_000), new BN(10_000 * 1_000_000), null, new BN(0), null)
+        .accounts({
+          supportedMint: supportedMintPda,
+          mint: mintKeypair.publicKey,
+          platform: platformPda,
+          authority: authority.publicKey,
+          proposal: null,
+          council: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      await program.methods
+        .initializeReferenceRate(new BN(3600))
+        .accounts({
+          referenceRate: ratePda,
+          supportedMint: supportedMintPda,
+          platform: platformPda,
+          authority: authority.publicKey,
+          proposal: null,
+          council: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      await program.methods
+        .updateReferenceRate(new BN(20_000_000)) // 20 MXN por token
+        .accounts({
+          referenceRate: ratePda,
+          platform: platformPda,
+          oracle: authority.publicKey,
+        })
+        .rpc();
+    });
+
+    it("Escrow debe registrar el monto neto recibido", async () => {
+      await program.methods
+        .createOrder(
+          orderId,
+          amount,
+          amountMxn,
+          { stp: {} },
+          "STP_REF_008",
+          true,
+          { sell: {} },
+          null,
+          null
+        )
+        .accounts({
+          order: orderPda,
+          escrow: escrowPda,
+          makerProfile: sellerProfilePda,
+          maker: seller.publicKey,
+          kycRecord: kycRecordPda(seller.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          makerTokenAccount: sellerAccount,
+          escrowTokenAccount: escrowAccount,
+          mint: mintKeypair.publicKey,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          priceFeed: null,
+          platform: platformPda,
+          supportedMint: supportedMintPda,
+          referenceRate: ratePda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([seller])
+        .rpc();
+
+      const order = await program.account.order.fetch(orderPda);
+      const expected = amount.toNumber() - transferFee(amount.toNumber());
+
+      assert.equal(order.amount.toString(), amount.toString());
+      assert.equal(order.escrowedAmount.toNumber(), expected);
+
+      console.log("✅ Escrow recibió:", expected / 1_000_000, "de", amount.toNumber() / 1_000_000, "tokens");
+    });
+
+    it("Debe liberar y cobrar comisión sobre el monto recibido", async () => {
+      await program.methods
+        .acceptOrder(null)
+        .accounts({
+          order: orderPda,
+          buyerProfile: buyerProfilePda,
+          makerProfile: sellerProfilePda,
+          feeSchedule: feeSchedulePda,
+          buyer: buyer.publicKey,
+          kycRecord: kycRecordPda(buyer.publicKey),
+          buyerBlocked: blockedAddressPda(buyer.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          supportedMint: supportedMintPda,
+          priceFeed: null,
+          platform: platformPda,
+        })
+        .signers([buyer])
+        .rpc();
+
+      await program.methods
+        .confirmFiatPayment("STP_TX_PYUSD_001")
+        .accounts({
+          order: orderPda,
+          buyer: buyer.publicKey,
+        })
+        .signers([buyer])
+        .rpc();
+
+      await program.methods
+        .releaseFundsSpl()
+        .accounts({
+          dailyStats: await dailyStatsPda(),
+          order: orderPda,
+          escrow: escrowPda,
+          platform: platformPda,
+          supportedMint: supportedMintPda,
+          escrowTokenAccount: escrowAccount,
+          seller: seller.publicKey,
+          buyer: buyer.publicKey,
+          treasury: treasuryPda,
+          buyerTokenAccount: buyerAccount,
+          treasuryTokenAccount: treasuryAccount,
+          sellerProfile: sellerProfilePda,
+          buyerProfile: buyerProfilePda,
+          authority: authority.publicKey,
+          mint: mintKeypair.publicKey,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      const escrowed = amount.toNumber() - transferFee(amount.toNumber());
+      const fee = Math.floor((escrowed * 50) / 10000);
+      const buyerAmount = escrowed - fee;
+
+      const escrowBalance = await getAccount(provider.connection, escrowAccount, undefined, TOKEN_2022_PROGRAM_ID);
+      const buyerBalance = await getAccount(provider.connection, buyerAccount, undefined, TOKEN_2022_PROGRAM_ID);
+      const treasuryBalance = await getAccount(provider.connection, treasuryAccount, undefined, TOKEN_2022_PROGRAM_ID);
+
+      assert.equal(Number(escrowBalance.amount), 0);
+      assert.equal(Number(buyerBalance.amount), buyerAmount - transferFee(buyerAmount));
+      assert.equal(Number(treasuryBalance.amount), fee - transferFee(fee));
+
+      console.log("✅ Escrow vaciado sin faltantes por transfer-fee");
+    });
+  });
+
+  describe("13. Límites por Nivel KYC y Volumen Acumulado", () => {
+    const orderId = "ORDER_VOLUME_CAP";
+    let orderPda: PublicKey;
+    let escrowPda: PublicKey;
+
+    before(async () => {
+      [orderPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("order"), Buffer.from(orderId)],
+        program.programId
+      );
+
+      [escrowPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), orderPda.toBuffer()],
+        program.programId
+      );
+    });
+
+    after(async () => {
+      await program.methods
+        .setTradingLimits({ enhanced: {} }, {
+          maxTradeMxn: new BN(9_000_000_000),
+          dailyMxn: new BN(45_000_000_000),
+          monthlyMxn: new BN(270_000_000_000),
+        })
+        .accounts({
+          platform: platformPda,
+          authority: authority.publicKey,
+        })
+        .rpc();
+    });
+
+    it("Debe acumular el volumen MXN de ambos lados del trade", async () => {
+      const sellerProfile = await program.account.userProfile.fetch(sellerProfilePda);
+      const buyerProfile = await program.account.userProfile.fetch(buyerProfilePda);
+
+      assert.isAbove(sellerProfile.dailyVolumeMxn.toNumber(), 0);
+      assert.isAbove(buyerProfile.dailyVolumeMxn.toNumber(), 0);
+      assert.isAtLeast(sellerProfile.monthlyVolumeMxn.toNumber(), sellerProfile.dailyVolumeMxn.toNumber());
+
+      console.log("✅ Volumen diario del seller:", sellerProfile.dailyVolumeMxn.toNumber() / 1_000_000, "MXN");
+    });
+
+    it("Un usuario Basic no puede operar tickets mayores a 1,000 MXN", async () => {
+      const basicUser = kycUser("basic");
+
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(basicUser.publicKey, 2 * LAMPORTS_PER_SOL)
+      );
+
+      const [basicProfilePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("user_profile"), basicUser.publicKey.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .createUserProfile()
+        .accounts({
+          userProfile: basicProfilePda,
+          user: basicUser.publicKey,
+          kycRecord: kycRecordPda(basicUser.publicKey),
+          userBlocked: blockedAddressPda(basicUser.publicKey),
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([basicUser])
+        .rpc();
+
+      const basicOrderId = "ORDER_BASIC_CAP";
+      const [basicOrderPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("order"), Buffer.from(basicOrderId)],
+        program.programId
+      );
+      const [basicEscrowPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), basicOrderPda.toBuffer()],
+        program.programId
+      );
+
+      try {
+        await program.methods
+          .createOrder(
+            basicOrderId,
+            new BN(0.5 * LAMPORTS_PER_SOL),
+            new BN(2_000_000_000), // 2,000 MXN
+            { stp: {} },
+            "STP_REF_010",
+            false,
+            { sell: {} },
+            null,
+            null
+          )
+          .accounts({
+            order: basicOrderPda,
+            escrow: basicEscrowPda,
+            makerProfile: basicProfilePda,
+            maker: basicUser.publicKey,
+            kycRecord: kycRecordPda(basicUser.publicKey),
+            makerBlocked: blockedAddressPda(basicUser.publicKey),
+            makerTokenAccount: null,
+            escrowTokenAccount: null,
+            mint: null,
+            tokenProgram: null,
+            priceFeed: null,
+            platform: platformPda,
+            supportedMint: solMintPda,
+            referenceRate: solRatePda,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([basicUser])
+          .rpc();
+
+        assert.fail("Debería haber fallado por el límite del nivel Basic");
+      } catch (error) {
+        assert.include(error.toString(), "ExceedsMaxLimit");
+        console.log("✅ Límite por trade del nivel Basic aplicado");
+      }
+    });
+
+    it("Debe rechazar límites diarios mayores al mensual", async () => {
+      try {
+        await program.methods
+          .setTradingLimits({ enhanced: {} }, {
+            maxTradeMxn: new BN(1_000_000_000),
+            dailyMxn: new BN(10_000_000_000),
+            monthlyMxn: new BN(5_000_000_000),
+          })
+          .accounts({
+            platform: platformPda,
+            authority: authority.publicKey,
+          })
+          .rpc();
+
+        assert.fail("Debería haber fallado por límites inválidos");
+      } catch (error) {
+        assert.include(error.toString(), "InvalidTradeLimits");
+        console.log("✅ Configuración de límites validada");
+      }
+    });
+
+    it("Debe rechazar órdenes que exceden el límite diario", async () => {
+      const sellerProfile = await program.account.userProfile.fetch(sellerProfilePda);
+
+      // Solo queda 1 MXN disponible en la ventana diaria del seller
+      await program.methods
+        .setTradingLimits({ enhanced: {} }, {
+          maxTradeMxn: new BN(400_000_000),
+          dailyMxn: sellerProfile.dailyVolumeMxn.add(new BN(1_000_000)),
+          monthlyMxn: new BN(270_000_000_000),
+        })
+        .accounts({
+          platform: platformPda,
+          authority: authority.publicKey,
+        })
+        .rpc();
+
+      try {
+        await program.methods
+          .createOrder(
+            orderId,
+            new BN(0.1 * LAMPORTS_PER_SOL),
+            new BN(400_000_000),
+            { stp: {} },
+            "STP_REF_009",
+            false,
+            { sell: {} },
+            null,
+            null
+          )
+          .accounts({
+            order: orderPda,
+            escrow: escrowPda,
+            makerProfile: sellerProfilePda,
+            maker: seller.publicKey,
+            kycRecord: kycRecordPda(seller.publicKey),
+            makerBlocked: blockedAddressPda(seller.publicKey),
+            makerTokenAccount: null,
+            escrowTokenAccount: null,
+            mint: null,
+            tokenProgram: null,
+            priceFeed: null,
+            platform: platformPda,
+            supportedMint: solMintPda,
+            referenceRate: solRatePda,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([seller])
+          .rpc();
+
+        assert.fail("Debería haber fallado por límite diario");
+      } catch (error) {
+        assert.include(error.toString(), "DailyVolumeLimitExceeded");
+        console.log("✅ Límite diario de volumen funcionando");
+      }
+    });
+  });
+
+  describe("14. Eventos", () => {
+    const orderId = "ORDER_EVENTS";
+    let orderPda: PublicKey;
+    let escrowPda: PublicKey;
+
+    // Decodificar los eventos Anchor emitidos en una transacción confirmada
+    const parseEvents = async (signature: string) => {
+      const tx = await provider.connection.getTransaction(signature, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const parser = new anchor.EventParser(program.programId, program.coder);
+      return [...parser.parseLogs(tx.meta.logMessages)];
+    };
+
+    before(async () => {
+      [orderPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("order"), Buffer.from(orderId)],
+        program.programId
+      );
+
+      [escrowPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), orderPda.toBuffer()],
+        program.programId
+      );
+    });
+
+    it("Debe emitir OrderCreated con montos y partes", async () => {
+      const signature = await program.methods
+        .createOrder(
+          orderId,
+          new BN(0.1 * LAMPORTS_PER_SOL),
+          new BN(400_000_000),
+          { stp: {} },
+          "STP_REF_EVENTS",
+          false,
+          { sell: {} },
+          null,
+          null
+        )
+        .accounts({
+          order: orderPda,
+          escrow: escrowPda,
+          makerProfile: sellerProfilePda,
+          maker: seller.publicKey,
+          kycRecord: kycRecordPda(seller.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          makerTokenAccount: null,
+          escrowTokenAccount: null,
+          mint: null,
+          tokenProgram: null,
+          priceFeed: null,
+          platform: platformPda,
+          supportedMint: solMintPda,
+          referenceRate: solRatePda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([seller])
+        .rpc({ commitment: "confirmed" });
+
+      const events = await parseEvents(signature);
+      const created = events.find((event) => event.name === "orderCreated");
+
+      assert.isDefined(created);
+      assert.equal(created.data.order.toString(), orderPda.toString());
+      assert.equal(created.data.orderId, orderId);
+      assert.equal(created.data.maker.toString(), seller.publicKey.toString());
+      assert.equal(created.data.mint.toString(), NATIVE_MINT.toString());
+      assert.equal(created.data.amount.toNumber(), 0.1 * LAMPORTS_PER_SOL);
+      assert.equal(created.data.amountMxn.toNumber(), 400_000_000);
+      assert.equal(created.data.prefunded, false);
+
+      console.log("✅ OrderCreated emitido");
+    });
+
+    it("Debe emitir OrderCancelled al cancelar una orden sin fondos", async () => {
+      const signature = await program.methods
+        .cancelUnfundedOrder()
+        .accounts({
+          dailyStats: await dailyStatsPda(),
+          order: orderPda,
+          makerProfile: sellerProfilePda,
+          maker: seller.publicKey,
+        })
+        .signers([seller])
+        .rpc({ commitment: "confirmed" });
+
+      const events = await parseEvents(signature);
+      const cancelled = events.find((event) => event.name === "orderCancelled");
+
+      assert.isDefined(cancelled);
+      assert.equal(cancelled.data.cancelledBy.toString(), seller.publicKey.toString());
+      assert.isNull(cancelled.data.refundedTo);
+      assert.equal(cancelled.data.refundedAmount.toNumber(), 0);
+
+      console.log("✅ OrderCancelled emitido");
+    });
+  });
+
+  describe("15. Estadísticas de la Plataforma", () => {
+    it("Debe acumular volumen y liquidaciones globales y por mint", async () => {
+      const platform = await program.account.platform.fetch(platformPda);
+      const solMint = await program.account.supportedMint.fetch(solMintPda);
+      const usdcMint = await program.account.supportedMint.fetch(usdcMintPda);
+
+      assert.isAbove(platform.totalTransactions.toNumber(), 0);
+      assert.isAbove(platform.totalVolume.toNumber(), 0);
+      assert.isAbove(solMint.totalTransactions.toNumber(), 0);
+      assert.isAbove(solMint.totalFees.toNumber(), 0);
+      assert.isAbove(usdcMint.totalTransactions.toNumber(), 0);
+      // El volumen MXN global es la suma de los mints
+      assert.isAtLeast(
+        platform.totalVolume.toNumber(),
+        solMint.totalVolumeMxn.toNumber() + usdcMint.totalVolumeMxn.toNumber()
+      );
+
+      console.log("✅ Liquidaciones totales:", platform.totalTransactions.toNumber());
+    });
+
+    it("Debe registrar el día en DailyStats", async () => {
+      const stats = await program.account.dailyStats.fetch(await dailyStatsPda());
+
+      assert.isAbove(stats.volumeMxn.toNumber(), 0);
+      assert.isAbove(stats.tradeCount.toNumber(), 0);
+      assert.isAbove(stats.feesMxn.toNumber(), 0);
+      assert.isAtLeast(stats.disputes.toNumber(), 1);
+      assert.isAtLeast(stats.cancellations.toNumber(), 1);
+
+      console.log("✅ DailyStats del día", stats.day.toNumber(), "-", stats.tradeCount.toNumber(), "liquidaciones");
+    });
+  });
+
+  describe("16. Esquema de Tarifas", () => {
+    const orderId = "ORDER_FEE_TIER";
+    let orderPda: PublicKey;
+    let escrowPda: PublicKey;
+
+    const baseTier = {
+      minVolumeMxn: new BN(0),
+      minKycLevel: { basic: {} },
+      makerFeeBps: new BN(25),
+      takerFeeBps: new BN(25),
+    };
+
+    before(async () => {
+      [orderPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("order"), Buffer.from(orderId)],
+        program.programId
+      );
+
+      [escrowPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), orderPda.toBuffer()],
+        program.programId
+      );
+    });
+
+    after(async () => {
+      await program.methods
+        .setFeeSchedule([baseTier])
+        .accounts({
+          feeSchedule: feeSchedulePda,
+          platform: platformPda,
+          authority: authority.publicKey,
+        })
+        .rpc();
+    });
+
+    it("Debe rechazar un esquema sin nivel base", async () => {
+      try {
+        await program.methods
+          .setFeeSchedule([{ ...baseTier, minVolumeMxn: new BN(1_000_000) }])
+          .accounts({
+            feeSchedule: feeSchedulePda,
+            platform: platformPda,
+            authority: authority.publicKey,
+          })
+          .rpc();
+
+        assert.fail("Debería haber fallado sin nivel base");
+      } catch (error) {
+        assert.include(error.toString(), "InvalidFeeSchedule");
+        console.log("✅ Esquema de tarifas validado");
+      }
+    });
+
+    it("Debe rechazar una comisión mínima mayor al tope del mint", async () => {
+      try {
+        await program.methods
+          .updateMint(new BN(1_000_000), new BN(10_000 * 1_000_000), null, new BN(500_000), new BN(100_000), true)
+          .accounts({
+            supportedMint: usdcMintPda,
+            platform: platformPda,
+            authority: authority.publicKey,
+          })
+          .rpc();
+
+        assert.fail("Debería haber fallado por tope menor al mínimo");
+      } catch (error) {
+        assert.include(error.toString(), "InvalidFeeSchedule");
+        console.log("✅ Mínimo y tope de comisión validados");
+      }
+    });
+
+    it("Debe fijar en la orden las tarifas del nivel por volumen al aceptar", async () => {
+      // Nivel con descuento para quien ya operó al menos 1 MXN en 30 días
+      await program.methods
+        .setFeeSchedule([
+          baseTier,
+          {
+            minVolumeMxn: new BN(1_000_000),
+            minKycLevel: { basic: {} },
+            makerFeeBps: new BN(10),
+            takerFeeBps: new BN(20),
+          },
+        ])
+        .accounts({
+          feeSchedule: feeSchedulePda,
+          platform: platformPda,
+          authority: authority.publicKey,
+        })
+        .rpc();
+
+      await program.methods
+        .createOrder(
+          orderId,
+          new BN(0.1 * LAMPORTS_PER_SOL),
+          new BN(400_000_000),
+          { stp: {} },
+          "STP_REF_FEES",
+          false,
+          { sell: {} },
+          null,
+          null
+        )
+        .accounts({
+          order: orderPda,
+          escrow: escrowPda,
+          makerProfile: sellerProfilePda,
+          maker: seller.publicKey,
+          kycRecord: kycRecordPda(seller.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          makerTokenAccount: null,
+          escrowTokenAccount: null,
+          mint: null,
+          tokenProgram: null,
+          priceFeed: null,
+          platform: platformPda,
+          supportedMint: solMintPda,
+          referenceRate: solRatePda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([seller])
+        .rpc();
+
+      await program.methods
+        .acceptOrder(null)
+        .accounts({
+          order: orderPda,
+          buyerProfile: buyerProfilePda,
+          makerProfile: sellerProfilePda,
+          feeSchedule: feeSchedulePda,
+          buyer: buyer.publicKey,
+          kycRecord: kycRecordPda(buyer.publicKey),
+          buyerBlocked: blockedAddressPda(buyer.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          supportedMint: solMintPda,
+          platform: platformPda,
+        })
+        .signers([buyer])
+        .rpc();
+
+      const order = await program.account.order.fetch(orderPda);
+
+      assert.equal(order.makerFeeBps.toNumber(), 10);
+      assert.equal(order.takerFeeBps.toNumber(), 20);
+
+      console.log("✅ Tarifas fijadas: maker", order.makerFeeBps.toNumber(), "bps, taker", order.takerFeeBps.toNumber(), "bps");
+    });
+
+    it("La comisión específica del mint debe tener prioridad sobre el esquema", async () => {
+      const overrideOrderId = "ORDER_MINT_FEE";
+      const overrideAmount = new BN(0.1 * LAMPORTS_PER_SOL);
+      const [overrideOrderPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("order"), Buffer.from(overrideOrderId)],
+        program.programId
+      );
+      const [overrideEscrowPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), overrideOrderPda.toBuffer()],
+        program.programId
+      );
+      const solLimits: [BN, BN] = [new BN(0.01 * LAMPORTS_PER_SOL), new BN(100 * LAMPORTS_PER_SOL)];
+      const updateSolMint = (feeBpsOverride: BN | null) =>
+        program.methods
+          .updateMint(...solLimits, feeBpsOverride, new BN(0), null, true)
+          .accounts({
+            supportedMint: solMintPda,
+            platform: platformPda,
+            authority: authority.publicKey,
+          })
+          .rpc();
+
+      try {
+        await updateSolMint(new BN(10_001));
+        assert.fail("Debería haber fallado por comisión mayor al 100%");
+      } catch (error) {
+        assert.include(error.toString(), "InvalidAmount");
+      }
+
+      await updateSolMint(new BN(100));
+
+      await program.methods
+        .createOrder(
+          overrideOrderId,
+          overrideAmount,
+          new BN(400_000_000),
+          { stp: {} },
+          "STP_REF_MINT_FEE",
+          false,
+          { sell: {} },
+          null,
+          { seller: {} }
+        )
+        .accounts({
+          order: overrideOrderPda,
+          escrow: overrideEscrowPda,
+          makerProfile: sellerProfilePda,
+          maker: seller.publicKey,
+          kycRecord: kycRecordPda(seller.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          makerTokenAccount: null,
+          escrowTokenAccount: null,
+          mint: null,
+          tokenProgram: null,
+          priceFeed: null,
+          platform: platformPda,
+          supportedMint: solMintPda,
+          referenceRate: solRatePda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([seller])
+        .rpc();
+
+      await program.methods
+        .acceptOrder(null)
+        .accounts({
+          order: overrideOrderPda,
+          buyerProfile: buyerProfilePda,
+          makerProfile: sellerProfilePda,
+          feeSchedule: feeSchedulePda,
+          buyer: buyer.publicKey,
+          kycRecord: kycRecordPda(buyer.publicKey),
+          buyerBlocked: blockedAddressPda(buyer.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          supportedMint: solMintPda,
+          platform: platformPda,
+        })
+        .signers([buyer])
+        .rpc();
+
+      await program.methods
+        .depositToEscrow()
+        .accounts({
+          platform: platformPda,
+          order: overrideOrderPda,
+          escrow: overrideEscrowPda,
+          supportedMint: solMintPda,
+          sellerTokenAccount: null,
+          escrowTokenAccount: await createWsolEscrowAccount(overrideEscrowPda),
+          seller: seller.publicKey,
+          mint: NATIVE_MINT,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([seller])
+        .rpc();
+
+      await updateSolMint(null);
+
+      const order = await program.account.order.fetch(overrideOrderPda);
+
+      // Se cobra el 1% del mint en vez de las tarifas del nivel (10 + 20 bps)
+      assert.equal(order.makerFeeBps.toNumber() + order.takerFeeBps.toNumber(), 30);
+      assert.equal(order.feeAmount.toNumber(), overrideAmount.toNumber() * 100 / 10000);
+
+      console.log("✅ Comisión del mint aplicada:", order.feeAmount.toNumber(), "lamports");
+    });
+  });
+
+  describe("17. Quién Paga la Comisión", () => {
+    const orderId = "ORDER_SELLER_FEE";
+    const amount = new BN(0.1 * LAMPORTS_PER_SOL);
+    const fee = amount.toNumber() * 50 / 10000;
+    let orderPda: PublicKey;
+    let escrowPda: PublicKey;
+    let escrowWsolAccount: PublicKey;
+
+    before(async () => {
+      [orderPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("order"), Buffer.from(orderId)],
+        program.programId
+      );
+
+      [escrowPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), orderPda.toBuffer()],
+        program.programId
+      );
+    });
+
+    it("Seller debe pre-fondear monto más comisión con las tarifas fijadas al crear", async () => {
+      const [prefundedOrderPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("order"), Buffer.from("ORDER_PREFUND_SELLER_FEE")],
+        program.programId
+      );
+      const [prefundedEscrowPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), prefundedOrderPda.toBuffer()],
+        program.programId
+      );
+      const prefundedEscrowAccount = await createWsolEscrowAccount(prefundedEscrowPda);
+      const createPrefundedOrder = (feeSchedule: PublicKey | null) =>
+        program.methods
+          .createOrder(
+            "ORDER_PREFUND_SELLER_FEE",
+            amount,
+            new BN(400_000_000),
+            { stp: {} },
+            "STP_REF_PREFUND_FEE",
+            true,
+            { sell: {} },
+            null,
+            { seller: {} }
+          )
+          .accounts({
+            order: prefundedOrderPda,
+            escrow: prefundedEscrowPda,
+            makerProfile: sellerProfilePda,
+            maker: seller.publicKey,
+            kycRecord: kycRecordPda(seller.publicKey),
+            makerBlocked: blockedAddressPda(seller.publicKey),
+            makerTokenAccount: null,
+            escrowTokenAccount: prefundedEscrowAccount,
+            mint: NATIVE_MINT,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            priceFeed: null,
+            feeSchedule,
+            platform: platformPda,
+            supportedMint: solMintPda,
+            referenceRate: solRatePda,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([seller])
+          .rpc();
+
+      try {
+        await createPrefundedOrder(null);
+
+        assert.fail("Debería haber fallado sin el esquema de tarifas");
+      } catch (error) {
+        assert.include(error.toString(), "MissingFeeSchedule");
+      }
+
+      await createPrefundedOrder(feeSchedulePda);
+
+      const order = await program.account.order.fetch(prefundedOrderPda);
+
+      // El taker aún no se conoce: se cotiza con la tarifa del nivel base
+      assert.equal(order.makerFeeBps.toNumber(), 25);
+      assert.equal(order.takerFeeBps.toNumber(), 25);
+      assert.equal(order.feeAmount.toNumber(), fee);
+      assert.equal(order.escrowedAmount.toNumber(), amount.toNumber() + fee);
+
+      console.log("✅ Orden pre-fondeada con monto + comisión:", order.escrowedAmount.toNumber() / LAMPORTS_PER_SOL, "SOL");
+    });
+
+    it("Seller debe depositar monto más comisión", async () => {
+      await program.methods
+        .createOrder(
+          orderId,
+          amount,
+          new BN(400_000_000),
+          { stp: {} },
+          "STP_REF_SELLER_FEE",
+          false,
+          { sell: {} },
+          null,
+          { seller: {} }
+        )
+        .accounts({
+          order: orderPda,
+          escrow: escrowPda,
+          makerProfile: sellerProfilePda,
+          maker: seller.publicKey,
+          kycRecord: kycRecordPda(seller.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          makerTokenAccount: null,
+          escrowTokenAccount: null,
+          mint: null,
+          tokenProgram: null,
+          priceFeed: null,
+          platform: platformPda,
+          supportedMint: solMintPda,
+          referenceRate: solRatePda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([seller])
+        .rpc();
+
+      await program.methods
+        .acceptOrder(null)
+        .accounts({
+          order: orderPda,
+          buyerProfile: buyerProfilePda,
+          makerProfile: sellerProfilePda,
+          feeSchedule: feeSchedulePda,
+          buyer: buyer.publicKey,
+          kycRecord: kycRecordPda(buyer.publicKey),
+          buyerBlocked: blockedAddressPda(buyer.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          supportedMint: solMintPda,
+          platform: platformPda,
+        })
+        .signers([buyer])
+        .rpc();
+
+      escrowWsolAccount = await createWsolEscrowAccount(escrowPda);
+
+      await program.methods
+        .depositToEscrow()
+        .accounts({
+          platform: platformPda,
+          order: orderPda,
+          escrow: escrowPda,
+          supportedMint: solMintPda,
+          sellerTokenAccount: null,
+          escrowTokenAccount: escrowWsolAccount,
+          seller: seller.publicKey,
+          mint: NATIVE_MINT,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([seller])
+        .rpc();
+
+      const order = await program.account.order.fetch(orderPda);
+
+      assert.deepEqual(order.feeBearer, { seller: {} });
+      assert.equal(order.feeAmount.toNumber(), fee);
+      assert.equal(order.escrowedAmount.toNumber(), amount.toNumber() + fee);
+
+      console.log("✅ Escrow fondeado con monto + comisión:", order.escrowedAmount.toNumber() / LAMPORTS_PER_SOL, "SOL");
+    });
+
+    it("Buyer debe recibir exactamente el monto de la orden", async () => {
+      await program.methods
+        .confirmFiatPayment("STP_TX_SELLER_FEE")
+        .accounts({
+          order: orderPda,
+          buyer: buyer.publicKey,
+        })
+        .signers([buyer])
+        .rpc();
+
+      const buyerBalanceBefore = await provider.connection.getBalance(buyer.publicKey);
+      const treasuryBalanceBefore = await provider.connection.getBalance(treasuryPda);
+
+      await program.methods
+        .releaseFunds()
+        .accounts({
+          dailyStats: await dailyStatsPda(),
+          order: orderPda,
+          escrow: escrowPda,
+          platform: platformPda,
+          supportedMint: solMintPda,
+          escrowTokenAccount: escrowWsolAccount,
+          seller: seller.publicKey,
+          buyer: buyer.publicKey,
+          treasury: treasuryPda,
+          buyerTokenAccount: null,
+          treasuryTokenAccount: null,
+          sellerProfile: sellerProfilePda,
+          buyerProfile: buyerProfilePda,
+          authority: authority.publicKey,
+          mint: NATIVE_MINT,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      const buyerBalanceAfter = await provider.connection.getBalance(buyer.publicKey);
+      const treasuryBalanceAfter = await provider.connection.getBalance(treasuryPda);
+
+      assert.equal(buyerBalanceAfter - buyerBalanceBefore, amount.toNumber());
+      assert.equal(treasuryBalanceAfter - treasuryBalanceBefore, fee);
+
+      console.log("✅ Buyer recibió el monto completo, comisión pagada por el seller");
+    });
+
+    it("Merchant debe aportar la comisión desde el inventario si la paga el seller", async () => {
+      const [adPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("ad"), Buffer.from("AD_001_SOL")],
+        program.programId
+      );
+      const [vaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("ad_vault"), adPda.toBuffer()],
+        program.programId
+      );
+      const setDefaultFeeBearer = (feeBearer: any) =>
+        program.methods
+          .setDefaultFeeBearer(feeBearer)
+          .accounts({
+            platform: platformPda,
+            authority: authority.publicKey,
+          })
+          .rpc();
+
+      await setDefaultFeeBearer({ seller: {} });
+
+      const adBefore = await program.account.ad.fetch(adPda);
+      const [sellerFeeTradePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("trade"), adPda.toBuffer(), adBefore.tradeCount.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+
+      await program.methods
+        .openTrade(new BN(400_000_000), { spei: {} }) // 400 MXN = 0.1 SOL
+        .accounts({
+          ad: adPda,
+          trade: sellerFeeTradePda,
+          supportedMint: solMintPda,
+          buyerProfile: buyerProfilePda,
+          merchantProfile: sellerProfilePda,
+          feeSchedule: feeSchedulePda,
+          platform: platformPda,
+          buyer: buyer.publicKey,
+          buyerBlocked: blockedAddressPda(buyer.publicKey),
+          kycRecord: kycRecordPda(buyer.publicKey),
+          merchantBlocked: blockedAddressPda(seller.publicKey),
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyer])
+        .rpc();
+
+      await setDefaultFeeBearer({ buyer: {} });
+
+      const trade = await program.account.trade.fetch(sellerFeeTradePda);
+      const adOpen = await program.account.ad.fetch(adPda);
+
+      assert.deepEqual(trade.feeBearer, { seller: {} });
+      assert.equal(trade.feeAmount.toNumber(), fee);
+      assert.equal(adOpen.lockedAmount.sub(adBefore.lockedAmount).toNumber(), amount.toNumber() + fee);
+      assert.equal(adBefore.availableAmount.sub(adOpen.availableAmount).toNumber(), amount.toNumber() + fee);
+
+      await program.methods
+        .confirmTradePayment("STP_TX_AD_SELLER_FEE")
+        .accounts({
+          trade: sellerFeeTradePda,
+          buyer: buyer.publicKey,
+        })
+        .signers([buyer])
+        .rpc();
+
+      const buyerBalanceBefore = await provider.connection.getBalance(buyer.publicKey);
+      const treasuryBalanceBefore = await provider.connection.getBalance(treasuryPda);
+
+      await program.methods
+        .releaseTrade()
+        .accounts({
+          dailyStats: await dailyStatsPda(),
+          trade: sellerFeeTradePda,
+          ad: adPda,
+          vault: vaultPda,
+          platform: platformPda,
+          supportedMint: solMintPda,
+          buyer: buyer.publicKey,
+          treasury: treasuryPda,
+          vaultTokenAccount: adBefore.vaultTokenAccount,
+          unwrapTokenAccount: adUnwrapPda(adPda),
+          buyerTokenAccount: null,
+          treasuryTokenAccount: null,
+          merchantProfile: sellerProfilePda,
+          buyerProfile: buyerProfilePda,
+          authority: seller.publicKey,
+          mint: NATIVE_MINT,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([seller])
+        .rpc();
+
+      const adAfter = await program.account.ad.fetch(adPda);
+      const buyerBalanceAfter = await provider.connection.getBalance(buyer.publicKey);
+      const treasuryBalanceAfter = await provider.connection.getBalance(treasuryPda);
+
+      assert.equal(adAfter.lockedAmount.toString(), adBefore.lockedAmount.toString());
+      assert.equal(buyerBalanceAfter - buyerBalanceBefore, amount.toNumber());
+      assert.equal(treasuryBalanceAfter - treasuryBalanceBefore, fee);
+
+      console.log("✅ Buyer recibió el monto completo del trade, comisión pagada desde el inventario");
+    });
+  });
+
+  describe("18. Programa de Referidos", () => {
+    const guest = kycUser("guest");
+    const orderId = "ORDER_REFERRAL";
+    const amount = new BN(0.1 * LAMPORTS_PER_SOL);
+    let guestProfilePda: PublicKey;
+    let orderPda: PublicKey;
+    let escrowPda: PublicKey;
+    let referralPda: PublicKey;
+
+    before(async () => {
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(guest.publicKey, 2 * LAMPORTS_PER_SOL)
+      );
+
+      [guestProfilePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("user_profile"), guest.publicKey.toBuffer()],
+        program.programId
+      );
+
+      [orderPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("order"), Buffer.from(orderId)],
+        program.programId
+      );
+
+      [escrowPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), orderPda.toBuffer()],
+        program.programId
+      );
+
+      // Recompensas en SOL del buyer, que refiere al nuevo usuario
+      [referralPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("referral"), buyer.publicKey.toBuffer(), NATIVE_MINT.toBuffer()],
+        program.programId
+      );
+    });
+
+    it("Debe registrar el referidor al crear el perfil", async () => {
+      await program.methods
+        .createUserProfile()
+        .accounts({
+          userProfile: guestProfilePda,
+          user: guest.publicKey,
+          kycRecord: kycRecordPda(guest.publicKey),
+          userBlocked: blockedAddressPda(guest.publicKey),
+          referrerProfile: buyerProfilePda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([guest])
+        .rpc();
+
+      const profile = await program.account.userProfile.fetch(guestProfilePda);
+
+      assert.equal(profile.referrer.toString(), buyer.publicKey.toString());
+
+      console.log("✅ Perfil creado con referidor:", profile.referrer.toString());
+    });
+
+    it("Debe acreditar al referidor su parte de la comisión", async () => {
+      await program.methods
+        .createOrder(
+          orderId,
+          amount,
+          new BN(400_000_000),
+          { stp: {} },
+          "STP_REF_REFERRAL",
+          false,
+          { sell: {} },
+          null,
+          null
+        )
+        .accounts({
+          order: orderPda,
+          escrow: escrowPda,
+          makerProfile: sellerProfilePda,
+          maker: seller.publicKey,
+          kycRecord: kycRecordPda(seller.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          makerTokenAccount: null,
+          escrowTokenAccount: null,
+          mint: null,
+          tokenProgram: null,
+          priceFeed: null,
+          platform: platformPda,
+          supportedMint: solMintPda,
+          referenceRate: solRatePda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([seller])
+        .rpc();
+
+      await program.methods
+        .acceptOrder(null)
+        .accounts({
+          order: orderPda,
+          buyerProfile: guestProfilePda,
+          makerProfile: sellerProfilePda,
+          feeSchedule: feeSchedulePda,
+          buyer: guest.publicKey,
+          kycRecord: kycRecordPda(guest.publicKey),
+          buyerBlocked: blockedAddressPda(guest.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          supportedMint: solMintPda,
+          platform: platformPda,
+        })
+        .signers([guest])
+        .rpc();
+
+      const escrowWsolAccount = await createWsolEscrowAccount(escrowPda);
+
+      await program.methods
+        .depositToEscrow()
+        .accounts({
+          platform: platformPda,
+          order: orderPda,
+          escrow: escrowPda,
+          supportedMint: solMintPda,
+          sellerTokenAccount: null,
+          escrowTokenAccount: escrowWsolAccount,
+          seller: seller.publicKey,
+          mint: NATIVE_MINT,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([seller])
+        .rpc();
+
+      await program.methods
+        .confirmFiatPayment("STP_TX_REFERRAL")
+        .accounts({
+          order: orderPda,
+          buyer: guest.publicKey,
+        })
+        .signers([guest])
+        .rpc();
+
+      const treasuryBalanceBefore = await provider.connection.getBalance(treasuryPda);
+
+      await program.methods
+        .releaseFunds()
+        .accounts({
+          dailyStats: await dailyStatsPda(),
+          order: orderPda,
+          escrow: escrowPda,
+          platform: platformPda,
+          supportedMint: solMintPda,
+          escrowTokenAccount: escrowWsolAccount,
+          seller: seller.publicKey,
+          buyer: guest.publicKey,
+          treasury: treasuryPda,
+          buyerTokenAccount: null,
+          treasuryTokenAccount: null,
+          sellerProfile: sellerProfilePda,
+          buyerProfile: guestProfilePda,
+          authority: authority.publicKey,
+          sellerReferral: null,
+          buyerReferral: referralPda,
+          referralVault: null,
+          mint: NATIVE_MINT,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      // El buyer (taker, 25 bps de 50) genera la mitad de la comisión; 20% es para su referidor
+      const fee = amount.toNumber() * 50 / 10000;
+      const reward = (fee / 2) * 2000 / 10000;
+
+      const referral = await program.account.referralAccount.fetch(referralPda);
+      const treasuryBalanceAfter = await provider.connection.getBalance(treasuryPda);
+
+      assert.equal(referral.referrer.toString(), buyer.publicKey.toString());
+      assert.equal(referral.mint.toString(), NATIVE_MINT.toString());
+      assert.equal(referral.claimable.toNumber(), reward);
+      assert.equal(referral.totalEarned.toNumber(), reward);
+      assert.equal(treasuryBalanceAfter - treasuryBalanceBefore, fee - reward);
+
+      console.log("✅ Recompensa acreditada:", reward, "lamports");
+    });
+
+    it("Referidor debe cobrar sus recompensas", async () => {
+      const referrerBalanceBefore = await provider.connection.getBalance(buyer.publicKey);
+
+      await program.methods
+        .claimReferralRewards()
+        .accounts({
+          referralAccount: referralPda,
+          platform: platformPda,
+          referrer: buyer.publicKey,
+          referralVault: null,
+          referrerTokenAccount: null,
+          mint: null,
+          tokenProgram: null,
+        })
+        .signers([buyer])
+        .rpc();
+
+      const referral = await program.account.referralAccount.fetch(referralPda);
+      const referrerBalanceAfter = await provider.connection.getBalance(buyer.publicKey);
+
+      assert.equal(referral.claimable.toNumber(), 0);
+      assert.equal(referral.totalClaimed.toNumber(), referral.totalEarned.toNumber());
+      assert.equal(referrerBalanceAfter - referrerBalanceBefore, referral.totalClaimed.toNumber());
+
+      try {
+        await program.methods
+          .claimReferralRewards()
+          .accounts({
+            referralAccount: referralPda,
+            platform: platformPda,
+            referrer: buyer.publicKey,
+            referralVault: null,
+            referrerTokenAccount: null,
+            mint: null,
+            tokenProgram: null,
+          })
+          .signers([buyer])
+          .rpc();
+
+        assert.fail("Debería haber fallado sin recompensas pendientes");
+      } catch (error) {
+        assert.include(error.toString(), "NoReferralRewards");
+      }
+
+      console.log("✅ Recompensas cobradas:", referral.totalClaimed.toNumber(), "lamports");
+    });
+  });
+
+  describe("19. Treasury", () => {
+    const recipient = Keypair.generate();
+    let withdrawalPda: PublicKey;
+
+    const withdrawAccounts = () => ({
+      withdrawal: withdrawalPda,
+      treasury: treasuryPda,
+      admin: authority.publicKey,
+      recipient: recipient.publicKey,
+      treasuryTokenAccount: null,
+      recipientTokenAccount: null,
+      mint: null,
+      tokenProgram: null,
+    });
+
+    it("Administrador debe proponer un retiro de SOL", async () => {
+      const treasury = await program.account.treasury.fetch(treasuryPda);
+      [withdrawalPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury_withdrawal"), treasury.withdrawalCount.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+
+      await program.methods
+        .proposeTreasuryWithdrawal(NATIVE_MINT, new BN(LAMPORTS_PER_SOL / 1000), recipient.publicKey)
+        .accounts({
+          withdrawal: withdrawalPda,
+          treasury: treasuryPda,
+          admin: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      const withdrawal = await program.account.treasuryWithdrawal.fetch(withdrawalPda);
+
+      assert.equal(withdrawal.approvals.length, 1);
+      assert.equal(withdrawal.proposer.toString(), authority.publicKey.toString());
+      assert.isNull(withdrawal.executedAt);
+
+      console.log("✅ Retiro propuesto:", withdrawalPda.toString());
+    });
+
+    it("No debe ejecutar el retiro sin alcanzar el umbral", async () => {
+      try {
+        await program.methods.withdrawTreasury().accounts(withdrawAccounts()).rpc();
+
+        assert.fail("Debería haber fallado con una sola aprobación");
+      } catch (error) {
+        assert.include(error.toString(), "ApprovalThresholdNotMet");
+      }
+
+      console.log("✅ Retiro bloqueado por umbral");
+    });
+
+    it("No debe permitir aprobar a quien no es administrador", async () => {
+      try {
+        await program.methods
+          .approveTreasuryWithdrawal()
+          .accounts({
+            withdrawal: withdrawalPda,
+            treasury: treasuryPda,
+            admin: seller.publicKey,
+          })
+          .signers([seller])
+          .rpc();
+
+        assert.fail("Debería haber fallado con un firmante no administrador");
+      } catch (error) {
+        assert.include(error.toString(), "NotTreasuryAdmin");
+      }
+
+      console.log("✅ Aprobación rechazada para no administrador");
+    });
+
+    it("Debe ejecutar el retiro con 2 de 3 aprobaciones", async () => {
+      await program.methods
+        .approveTreasuryWithdrawal()
+        .accounts({
+          withdrawal: withdrawalPda,
+          treasury: treasuryPda,
+          admin: treasuryAdminB.publicKey,
+        })
+        .signers([treasuryAdminB])
+        .rpc();
+
+      const treasuryBalanceBefore = await provider.connection.getBalance(treasuryPda);
+
+      await program.methods.withdrawTreasury().accounts(withdrawAccounts()).rpc();
+
+      const withdrawal = await program.account.treasuryWithdrawal.fetch(withdrawalPda);
+      const treasuryBalanceAfter = await provider.connection.getBalance(treasuryPda);
+      const recipientBalance = await provider.connection.getBalance(recipient.publicKey);
+
+      assert.isNotNull(withdrawal.executedAt);
+      assert.equal(treasuryBalanceBefore - treasuryBalanceAfter, withdrawal.amount.toNumber());
+      assert.equal(recipientBalance, withdrawal.amount.toNumber());
+
+      try {
+        await program.methods.withdrawTreasury().accounts(withdrawAccounts()).rpc();
+
+        assert.fail("Debería haber fallado al ejecutar dos veces");
+      } catch (error) {
+        assert.include(error.toString(), "WithdrawalAlreadyExecuted");
+      }
+
+      console.log("✅ Retiro ejecutado:", withdrawal.amount.toNumber(), "lamports");
+    });
+  });
+
+  describe("20. Consejo de Administración", () => {
+    it("Debe crear el consejo con aprobación 2 de 3", async () => {
+      await program.methods
+        .initializeAdminCouncil([authority.publicKey, memberB.publicKey, memberC.publicKey], 2)
+        .accounts({
+          council: councilPda,
+          platform: platformPda,
+          authority: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      const council = await program.account.adminCouncil.fetch(councilPda);
+      const platform = await program.account.platform.fetch(platformPda);
+
+      assert.equal(council.members.length, 3);
+      assert.equal(council.threshold, 2);
+      assert.equal(platform.adminCouncil.toString(), councilPda.toString());
+
+      console.log("✅ Consejo creado:", councilPda.toString());
+    });
+
+    it("La autoridad ya no debe cambiar la configuración por sí sola", async () => {
+      try {
+        await program.methods
+          .setReferralShare(new BN(2500))
+          .accounts({
+            platform: platformPda,
+            authority: authority.publicKey,
+          })
+          .rpc();
+
+        assert.fail("Debería haber fallado con el consejo activo");
+      } catch (error) {
+        assert.include(error.toString(), "CouncilGoverned");
+      }
+
+      // Tampoco puede cambiar mints ni crear feeds de precio
+      try {
+        await program.methods
+          .updateMint(new BN(1), new BN(10_000 * 1_000_000), null, new BN(0), null, true)
+          .accounts({
+            supportedMint: usdcMintPda,
+            platform: platformPda,
+            authority: authority.publicKey,
+          })
+          .rpc();
+
+        assert.fail("Debería haber fallado con el consejo activo");
+      } catch (error) {
+        assert.include(error.toString(), "CouncilGoverned");
+      }
+
+      const [councilFeedPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("price_feed"), Buffer.from("COUNCIL_FEED")],
+        program.programId
+      );
+      try {
+        await program.methods
+          .initializePriceFeed("COUNCIL_FEED", NATIVE_MINT, authority.publicKey)
+          .accounts({
+            priceFeed: councilFeedPda,
+            platform: platformPda,
+            authority: authority.publicKey,
+            proposal: null,
+            council: null,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+
+        assert.fail("Debería haber fallado con el consejo activo");
+      } catch (error) {
+        assert.include(error.toString(), "CouncilGoverned");
+      }
+
+      console.log("✅ Configuración directa bloqueada");
+    });
+
+    it("Con el consejo activo debe dar de alta un mint, su tasa y un feed aprobados", async () => {
+      const councilMint = await createMint(provider.connection, authority.payer, authority.publicKey, null, 6);
+      const [councilMintPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("supported_mint"), councilMint.toBuffer()],
+        program.programId
+      );
+      const [councilRatePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("reference_rate"), councilMint.toBuffer()],
+        program.programId
+      );
+      const [councilFeedPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("price_feed"), Buffer.from("COUNCIL_FEED")],
+        program.programId
+      );
+      const limits = { minTradeAmount: new BN(1_000_000), maxTradeAmount: new BN(10_000 * 1_000_000) };
+      const registerAccounts = (proposal: PublicKey | null) => ({
+        supportedMint: councilMintPda,
+        mint: councilMint,
+        platform: platformPda,
+        authority: authority.publicKey,
+        proposal,
+        council: proposal ? councilPda : null,
+        systemProgram: SystemProgram.programId,
+      });
+
+      try {
+        await program.methods
+          .registerMint(limits.minTradeAmount, limits.maxTradeAmount, null, new BN(0), null)
+          .accounts(registerAccounts(null))
+          .rpc();
+
+        assert.fail("Debería haber fallado sin propuesta aprobada");
+      } catch (error) {
+        assert.include(error.toString(), "CouncilGoverned");
+      }
+
+      const registerProposalPda = await proposeAndApprove({
+        registerMint: { mint: councilMint, ...limits, feeBpsOverride: null, minFee: new BN(0), maxFee: null },
+      });
+
+      // Parámetros distintos a los aprobados
+      try {
+        await program.methods
+          .registerMint(limits.minTradeAmount, limits.maxTradeAmount, new BN(10), new BN(0), null)
+          .accounts(registerAccounts(registerProposalPda))
+          .rpc();
+
+        assert.fail("Debería haber fallado con parámetros no aprobados");
+      } catch (error) {
+        assert.include(error.toString(), "SetupNotApproved");
+      }
+
+      await program.methods
+        .registerMint(limits.minTradeAmount, limits.maxTradeAmount, null, new BN(0), null)
+        .accounts(registerAccounts(registerProposalPda))
+        .rpc();
+
+      const rateProposalPda = await proposeAndApprove({
+        initializeReferenceRate: { mint: councilMint, maxStalenessSeconds: new BN(3600) },
+      });
+      await program.methods
+        .initializeReferenceRate(new BN(3600))
+        .accounts({
+          referenceRate: councilRatePda,
+          supportedMint: councilMintPda,
+          platform: platformPda,
+          authority: authority.publicKey,
+          proposal: rateProposalPda,
+          council: councilPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      const feedProposalPda = await proposeAndApprove({
+        initializePriceFeed: { priceFeed: councilFeedPda, mint: councilMint, updater: authority.publicKey },
+      });
+      await program.methods
+        .initializePriceFeed("COUNCIL_FEED", councilMint, authority.publicKey)
+        .accounts({
+          priceFeed: councilFeedPda,
+          platform: platformPda,
+          authority: authority.publicKey,
+          proposal: feedProposalPda,
+          council: councilPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      const supportedMint = await program.account.supportedMint.fetch(councilMintPda);
+      const referenceRate = await program.account.referenceRate.fetch(councilRatePda);
+      const priceFeed = await program.account.priceFeed.fetch(councilFeedPda);
+      const registerProposal = await program.account.adminProposal.fetch(registerProposalPda);
+
+      assert.equal(supportedMint.enabled, true);
+      assert.equal(referenceRate.mint.toString(), councilMint.toString());
+      assert.equal(priceFeed.mint.toString(), councilMint.toString());
+      assert.isNotNull(registerProposal.executedAt);
+
+      // Cada propuesta se consume una sola vez
+      try {
+        await program.methods
+          .executeAdminAction()
+          .accounts(executeAccounts(registerProposalPda))
+          .rpc();
+
+        assert.fail("Debería haber fallado con la propuesta ya ejecutada");
+      } catch (error) {
+        assert.include(error.toString(), "ProposalAlreadyExecuted");
+      }
+
+      console.log("✅ Alta aprobada por el consejo:", councilMint.toString());
+    });
+
+    it("Debe ejecutar un cambio de configuración aprobado 2 de 3", async () => {
+      const council = await program.account.adminCouncil.fetch(councilPda);
+      const [proposalPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("admin_proposal"), council.proposalCount.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+
+      await program.methods
+        .proposeAdminAction({ setReferralShare: { shareBps: new BN(2500) } }, null)
+        .accounts({
+          proposal: proposalPda,
+          council: councilPda,
+          proposer: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      try {
+        await program.methods.executeAdminAction().accounts(executeAccounts(proposalPda)).rpc();
+
+        assert.fail("Debería haber fallado con una sola aprobación");
+      } catch (error) {
+        assert.include(error.toString(), "ApprovalThresholdNotMet");
+      }
+
+      try {
+        await program.methods
+          .approveAdminAction()
+          .accounts({
+            proposal: proposalPda,
+            council: councilPda,
+            member: seller.publicKey,
+          })
+          .signers([seller])
+          .rpc();
+
+        assert.fail("Debería haber fallado con un firmante que no es miembro");
+      } catch (error) {
+        assert.include(error.toString(), "NotCouncilMember");
+      }
+
+      await program.methods
+        .approveAdminAction()
+        .accounts({
+          proposal: proposalPda,
+          council: councilPda,
+          member: memberC.publicKey,
+        })
+        .signers([memberC])
+        .rpc();
+
+      await program.methods.executeAdminAction().accounts(executeAccounts(proposalPda)).rpc();
+
+      const platform = await program.account.platform.fetch(platformPda);
+      const proposal = await program.account.adminProposal.fetch(proposalPda);
+
+      assert.equal(platform.referralShareBps.toNumber(), 2500);
+      assert.isNotNull(proposal.executedAt);
+      assert.isAbove(proposal.expiresAt.toNumber(), proposal.createdAt.toNumber());
+
+      try {
+        await program.methods.executeAdminAction().accounts(executeAccounts(proposalPda)).rpc();
+
+        assert.fail("Debería haber fallado al ejecutar dos veces");
+      } catch (error) {
+        assert.include(error.toString(), "ProposalAlreadyExecuted");
+      }
+
+      console.log("✅ Parte de referidos actualizada por el consejo:", platform.referralShareBps.toNumber(), "bps");
+    });
+  });
+
+  describe("21. Cambios con Timelock", () => {
+    const newOperator = Keypair.generate();
+    let pendingChangePda: PublicKey;
+
+    it("Rotar el operador debe quedar programado con espera", async () => {
+      const proposalPda = await proposeAndApprove({
+        grantRole: { role: { operator: {} }, account: newOperator.publicKey },
+      });
+      [pendingChangePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("pending_change"), proposalPda.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .executeAdminAction()
+        .accounts({ ...executeAccounts(proposalPda), pendingChange: pendingChangePda })
+        .rpc();
+
+      const platform = await program.account.platform.fetch(platformPda);
+      const pendingChange = await program.account.pendingChange.fetch(pendingChangePda);
+
+      assert.equal(platform.authority.toString(), authority.publicKey.toString());
+      assert.equal(pendingChange.proposal.toString(), proposalPda.toString());
+      assert.equal(
+        pendingChange.eta.toNumber() - pendingChange.scheduledAt.toNumber(),
+        platform.timelockDelaySeconds.toNumber()
+      );
+
+      console.log("✅ Cambio programado para:", new Date(pendingChange.eta.toNumber() * 1000).toISOString());
+    });
+
+    it("No debe aplicarse antes de cumplir la espera", async () => {
+      try {
+        await program.methods
+          .executePendingChange()
+          .accounts({
+            pendingChange: pendingChangePda,
+            scheduledBy: authority.publicKey,
+            council: councilPda,
+            platform: platformPda,
+            treasury: null,
+            feeSchedule: null,
+            supportedMint: null,
+          })
+          .rpc();
+
+        assert.fail("Debería haber fallado durante el timelock");
+      } catch (error) {
+        assert.include(error.toString(), "ChangeStillLocked");
+      }
+
+      console.log("✅ Cambio bloqueado durante la espera");
+    });
+
+    it("Un miembro del consejo debe poder cancelarlo", async () => {
+      try {
+        await program.methods
+          .cancelPendingChange()
+          .accounts({
+            pendingChange: pendingChangePda,
+            scheduledBy: authority.publicKey,
+            council: councilPda,
+            member: seller.publicKey,
+          })
+          .signers([seller])
+          .rpc();
+
+        assert.fail("Debería haber fallado con un firmante que no es miembro");
+      } catch (error) {
+        assert.include(error.toString(), "NotCouncilMember");
+      }
+
+      await program.methods
+        .cancelPendingChange()
+        .accounts({
+          pendingChange: pendingChangePda,
+          scheduledBy: authority.publicKey,
+          council: councilPda,
+          member: memberC.publicKey,
+        })
+        .signers([memberC])
+        .rpc();
+
+      const pendingChange = await program.account.pendingChange.fetchNullable(pendingChangePda);
+      const platform = await program.account.platform.fetch(platformPda);
+
+      assert.isNull(pendingChange);
+      assert.equal(platform.authority.toString(), authority.publicKey.toString());
+
+      console.log("✅ Cambio cancelado");
+    });
+
+    it("Los cambios de comisión o estado de un mint deben pasar por el timelock", async () => {
+      const proposalPda = await proposeAndApprove({
+        updateMint: {
+          mint: usdcMint,
+          minTradeAmount: new BN(1_000_000),
+          maxTradeAmount: new BN(10_000 * 1_000_000),
+          feeBpsOverride: null,
+          minFee: new BN(0),
+          maxFee: null,
+          enabled: false,
+        },
+      });
+      const [mintChangePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("pending_change"), proposalPda.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .executeAdminAction()
+        .accounts({ ...executeAccounts(proposalPda), pendingChange: mintChangePda })
+        .rpc();
+
+      const pendingChange = await program.account.pendingChange.fetch(mintChangePda);
+      assert.equal(pendingChange.action.updateMint.mint.toString(), usdcMint.toString());
+
+      try {
+        await program.methods
+          .executePendingChange()
+          .accounts({
+            pendingChange: mintChangePda,
+            scheduledBy: authority.publicKey,
+            council: councilPda,
+            platform: platformPda,
+            treasury: null,
+            feeSchedule: null,
+            supportedMint: usdcMintPda,
+          })
+          .rpc();
+
+        assert.fail("Debería haber fallado durante el timelock");
+      } catch (error) {
+        assert.include(error.toString(), "ChangeStillLocked");
+      }
+
+      // El mint sigue habilitado hasta que venza la espera
+      const supportedMint = await program.account.supportedMint.fetch(usdcMintPda);
+      assert.isTrue(supportedMint.enabled);
+
+      console.log("✅ Cambio del mint programado con espera");
+    });
+  });
+
+  describe("22. Circuit Breakers", () => {
+    const orderId = "ORDER_PAUSED";
+    let orderPda: PublicKey;
+    let escrowPda: PublicKey;
+
+    // Intentar crear una orden de venta de SOL y devolver el error
+    const tryCreateOrder = async () => {
+      try {
+        await program.methods
+          .createOrder(
+            orderId,
+            new BN(0.1 * LAMPORTS_PER_SOL),
+            new BN(300_000_000),
+            { stp: {} },
+            "STP_REF_PAUSED",
+            false,
+            { sell: {} },
+            null,
+            null
+          )
+          .accounts({
+            order: orderPda,
+            escrow: escrowPda,
+            makerProfile: sellerProfilePda,
+            maker: seller.publicKey,
+            kycRecord: kycRecordPda(seller.publicKey),
+            makerBlocked: blockedAddressPda(seller.publicKey),
+            platform: platformPda,
+            supportedMint: solMintPda,
+            referenceRate: solRatePda,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([seller])
+          .rpc();
+
+        return null;
+      } catch (error) {
+        return error.toString();
+      }
+    };
+
+    before(async () => {
+      [orderPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("order"), Buffer.from(orderId)],
+        program.programId
+      );
+      [escrowPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), orderPda.toBuffer()],
+        program.programId
+      );
+    });
+
+    it("Solo el guardián debe poder pausar operaciones", async () => {
+      try {
+        await program.methods
+          .setPausedOperations(1)
+          .accounts({
+            platform: platformPda,
+            guardian: seller.publicKey,
+          })
+          .signers([seller])
+          .rpc();
+
+        assert.fail("Debería haber fallado con un firmante que no es guardián");
+      } catch (error) {
+        assert.include(error.toString(), "ConstraintHasOne");
+      }
+
+      console.log("✅ Pausa rechazada para quien no es guardián");
+    });
+
+    it("Debe bloquear la creación de órdenes mientras está pausada", async () => {
+      await program.methods
+        .setPausedOperations(1) // Creación de órdenes
+        .accounts({
+          platform: platformPda,
+          guardian: authority.publicKey,
+        })
+        .rpc();
+
+      const platform = await program.account.platform.fetch(platformPda);
+      assert.equal(platform.pausedOperations, 1);
+      assert.include(await tryCreateOrder(), "OperationPaused");
+
+      await program.methods
+        .setPausedOperations(0)
+        .accounts({
+          platform: platformPda,
+          guardian: authority.publicKey,
+        })
+        .rpc();
+
+      console.log("✅ Creación de órdenes pausada y reanudada");
+    });
+
+    it("Debe bloquear nuevas órdenes en un mint pausado", async () => {
+      await program.methods
+        .setMintTradingPaused(true)
+        .accounts({
+          supportedMint: solMintPda,
+          platform: platformPda,
+          guardian: authority.publicKey,
+        })
+        .rpc();
+
+      const solConfig = await program.account.supportedMint.fetch(solMintPda);
+      assert.equal(solConfig.tradingPaused, true);
+      assert.include(await tryCreateOrder(), "MintTradingPaused");
+
+      await program.methods
+        .setMintTradingPaused(false)
+        .accounts({
+          supportedMint: solMintPda,
+          platform: platformPda,
+          guardian: authority.publicKey,
+        })
+        .rpc();
+
+      console.log("✅ Trading de SOL pausado y reanudado");
+    });
+
+    it("Debe rechazar aceptar una orden de un mint pausado", async () => {
+      assert.isNull(await tryCreateOrder());
+
+      await program.methods
+        .setMintTradingPaused(true)
+        .accounts({
+          supportedMint: solMintPda,
+          platform: platformPda,
+          guardian: authority.publicKey,
+        })
+        .rpc();
+
+      try {
+        await program.methods
+          .acceptOrder(null)
+          .accounts({
+            order: orderPda,
+            buyerProfile: buyerProfilePda,
+            makerProfile: sellerProfilePda,
+            feeSchedule: feeSchedulePda,
+            buyer: buyer.publicKey,
+            kycRecord: kycRecordPda(buyer.publicKey),
+            buyerBlocked: blockedAddressPda(buyer.publicKey),
+            makerBlocked: blockedAddressPda(seller.publicKey),
+            supportedMint: solMintPda,
+            platform: platformPda,
+          })
+          .signers([buyer])
+          .rpc();
+
+        assert.fail("Debería haber fallado con el mint pausado");
+      } catch (error) {
+        assert.include(error.toString(), "MintTradingPaused");
+      }
+
+      await program.methods
+        .setMintTradingPaused(false)
+        .accounts({
+          supportedMint: solMintPda,
+          platform: platformPda,
+          guardian: authority.publicKey,
+        })
+        .rpc();
+
+      await program.methods
+        .cancelUnfundedOrder()
+        .accounts({
+          dailyStats: await dailyStatsPda(),
+          order: orderPda,
+          makerProfile: sellerProfilePda,
+          maker: seller.publicKey,
+        })
+        .signers([seller])
+        .rpc();
+
+      console.log("✅ Aceptación bloqueada en un mint pausado");
+    });
+
+    it("Debe bloquear el pre-fondeo y los trades de anuncio con depósitos pausados", async () => {
+      await program.methods
+        .setPausedOperations(4) // Depósitos
+        .accounts({
+          platform: platformPda,
+          guardian: authority.publicKey,
+        })
+        .rpc();
+
+      const prefundOrderId = "ORDER_PAUSED_PREFUND";
+      const [prefundOrderPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("order"), Buffer.from(prefundOrderId)],
+        program.programId
+      );
+      const [prefundEscrowPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), prefundOrderPda.toBuffer()],
+        program.programId
+      );
+
+      try {
+        await program.methods
+          .createOrder(
+            prefundOrderId,
+            new BN(0.1 * LAMPORTS_PER_SOL),
+            new BN(400_000_000),
+            { stp: {} },
+            "STP_REF_PAUSED_PREFUND",
+            true,
+            { sell: {} },
+            null,
+            null
+          )
+          .accounts({
+            order: prefundOrderPda,
+            escrow: prefundEscrowPda,
+            makerProfile: sellerProfilePda,
+            maker: seller.publicKey,
+            kycRecord: kycRecordPda(seller.publicKey),
+            makerBlocked: blockedAddressPda(seller.publicKey),
+            makerTokenAccount: null,
+            escrowTokenAccount: await createWsolEscrowAccount(prefundEscrowPda),
+            mint: NATIVE_MINT,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            platform: platformPda,
+            supportedMint: solMintPda,
+            referenceRate: solRatePda,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([seller])
+          .rpc();
+
+        assert.fail("Debería haber fallado con los depósitos pausados");
+      } catch (error) {
+        assert.include(error.toString(), "OperationPaused");
+      }
+
+      // Anuncio del seller de la sección 9
+      const [adPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("ad"), Buffer.from("AD_001_SOL")],
+        program.programId
+      );
+      const ad = await program.account.ad.fetch(adPda);
+      const [tradePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("trade"), adPda.toBuffer(), ad.tradeCount.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+
+      try {
+        await program.methods
+          .openTrade(new BN(500_000_000), { spei: {} })
+          .accounts({
+            ad: adPda,
+            trade: tradePda,
+            supportedMint: solMintPda,
+            buyerProfile: buyerProfilePda,
+            merchantProfile: sellerProfilePda,
+            feeSchedule: feeSchedulePda,
+            platform: platformPda,
+            buyer: buyer.publicKey,
+            buyerBlocked: blockedAddressPda(buyer.publicKey),
+            kycRecord: kycRecordPda(buyer.publicKey),
+            merchantBlocked: blockedAddressPda(seller.publicKey),
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([buyer])
+          .rpc();
+
+        assert.fail("Debería haber fallado con los depósitos pausados");
+      } catch (error) {
+        assert.include(error.toString(), "OperationPaused");
+      }
+
+      await program.methods
+        .setPausedOperations(0)
+        .accounts({
+          platform: platformPda,
+          guardian: authority.publicKey,
+        })
+        .rpc();
+
+      console.log("✅ Pre-fondeo y trades de anuncio pausados con los depósitos");
+    });
+  });
+
+  describe("23. Suspensiones y Baneos", () => {
+    const moderate = (method: any, signer = authority.payer) =>
+      method
+        .accounts({
+          userProfile: buyerProfilePda,
+          platform: platformPda,
+          moderator: signer.publicKey,
+        })
+        .signers(signer === authority.payer ? [] : [signer])
+        .rpc();
+
+    it("Solo la autoridad o cumplimiento deben poder suspender", async () => {
+      try {
+        await moderate(program.methods.suspendUser(101, new BN(3600)), seller);
+
+        assert.fail("Debería haber fallado sin rol de cumplimiento");
+      } catch (error) {
+        assert.include(error.toString(), "Unauthorized");
+      }
+
+      console.log("✅ Suspensión rechazada para usuario sin rol");
+    });
+
+    it("Usuario suspendido no debe iniciar órdenes nuevas", async () => {
+      await moderate(program.methods.suspendUser(101, new BN(3600)));
+
+      const profile = await program.account.userProfile.fetch(buyerProfilePda);
+      assert.deepEqual(profile.status, { suspended: {} });
+      assert.equal(profile.statusReason, 101);
+      assert.equal(profile.statusActor.toString(), authority.publicKey.toString());
+      assert.equal(profile.suspendedUntil.toNumber() - profile.statusUpdatedAt.toNumber(), 3600);
+      assert.equal(profile.isActive, false);
+
+      const orderId = "ORDER_SUSPENDED";
+      const [orderPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("order"), Buffer.from(orderId)],
+        program.programId
+      );
+      const [escrowPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), orderPda.toBuffer()],
+        program.programId
+      );
+
+      try {
+        await program.methods
+          .createOrder(
+            orderId,
+            new BN(0.1 * LAMPORTS_PER_SOL),
+            new BN(400_000_000), // 400 MXN
+            { stp: {} },
+            "STP_REF_SUSPENDED",
+            false,
+            { sell: {} },
+            null,
+            null
+          )
+          .accounts({
+            order: orderPda,
+            escrow: escrowPda,
+            makerProfile: buyerProfilePda,
+            maker: buyer.publicKey,
+            kycRecord: kycRecordPda(buyer.publicKey),
+            makerBlocked: blockedAddressPda(buyer.publicKey),
+            platform: platformPda,
+            supportedMint: solMintPda,
+            referenceRate: solRatePda,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([buyer])
+          .rpc();
+
+        assert.fail("Debería haber fallado con el usuario suspendido");
+      } catch (error) {
+        assert.include(error.toString(), "UserSuspended");
+      }
+
+      console.log("✅ Usuario suspendido por 1 hora");
+    });
+
+    it("Debe banear y reactivar registrando el motivo", async () => {
+      await moderate(program.methods.banUser(102));
+
+      let profile = await program.account.userProfile.fetch(buyerProfilePda);
+      assert.deepEqual(profile.status, { banned: {} });
+      assert.isNull(profile.suspendedUntil);
+
+      try {
+        await moderate(program.methods.suspendUser(103, new BN(3600)));
+
+        assert.fail("Una suspensión no debería reemplazar un baneo");
+      } catch (error) {
+        assert.include(error.toString(), "UserBanned");
+      }
+
+      await moderate(program.methods.reinstateUser(104));
+
+      profile = await program.account.userProfile.fetch(buyerProfilePda);
+      assert.deepEqual(profile.status, { active: {} });
+      assert.equal(profile.statusReason, 104);
+      assert.equal(profile.isActive, true);
+
+      console.log("✅ Usuario baneado y reactivado");
+    });
+  });
+
+  describe("24. Retención Legal", () => {
+    const orderId = "ORDER_LEGAL_HOLD";
+    const amount = new BN(0.05 * LAMPORTS_PER_SOL);
+    const courtAccount = Keypair.generate();
+    let orderPda: PublicKey;
+    let escrowPda: PublicKey;
+    let escrowWsolAccount: PublicKey;
+
+    const manageHold = (method: any, signer = authority.payer) =>
+      method
+        .accounts({
+          order: orderPda,
+          platform: platformPda,
+          moderator: signer.publicKey,
+        })
+        .signers(signer === authority.payer ? [] : [signer])
+        .rpc();
+
+    const transferFrozen = (pendingChange: PublicKey, destination: PublicKey, signer = authority.payer) =>
+      program.methods
+        .transferFrozenFunds()
+        .accounts({
+          order: orderPda,
+          escrow: escrowPda,
+          platform: platformPda,
+          authority: signer.publicKey,
+          pendingChange,
+          scheduledBy: authority.publicKey,
+          escrowTokenAccount: escrowWsolAccount,
+          seller: seller.publicKey,
+          destination,
+          destinationTokenAccount: null,
+          mint: NATIVE_MINT,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers(signer === authority.payer ? [] : [signer])
+        .rpc();
+
+    before(async () => {
+      [orderPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("order"), Buffer.from(orderId)],
+        program.programId
+      );
+      [escrowPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), orderPda.toBuffer()],
+        program.programId
+      );
+      escrowWsolAccount = await createWsolEscrowAccount(escrowPda);
+
+      await program.methods
+        .createOrder(
+          orderId,
+          amount,
+          new BN(200_000_000), // 200 MXN
+          { stp: {} },
+          "STP_REF_HOLD",
+          true,
+          { sell: {} },
+          null,
+          null
+        )
+        .accounts({
+          order: orderPda,
+          escrow: escrowPda,
+          makerProfile: sellerProfilePda,
+          maker: seller.publicKey,
+          kycRecord: kycRecordPda(seller.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          makerTokenAccount: null,
+          escrowTokenAccount: escrowWsolAccount,
+          mint: NATIVE_MINT,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          platform: platformPda,
+          supportedMint: solMintPda,
+          referenceRate: solRatePda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([seller])
+        .rpc();
+    });
+
+    it("Solo la autoridad o cumplimiento deben poder congelar una orden", async () => {
+      try {
+        await manageHold(program.methods.placeLegalHold("CASE-2024-001"), seller);
+
+        assert.fail("Debería haber fallado sin rol de cumplimiento");
+      } catch (error) {
+        assert.include(error.toString(), "Unauthorized");
+      }
+
+      console.log("✅ Retención rechazada para usuario sin rol");
+    });
+
+    it("Una orden congelada no debe poder cancelarse", async () => {
+      await manageHold(program.methods.placeLegalHold("CASE-2024-001"));
+
+      const order = await program.account.order.fetch(orderPda);
+      assert.deepEqual(order.status, { frozen: {} });
+      assert.deepEqual(order.heldStatus, { open: {} });
+      assert.equal(order.legalHoldCase, "CASE-2024-001");
+      assert.equal(order.legalHoldBy.toString(), authority.publicKey.toString());
+
+      try {
+        await program.methods
+          .cancelOrder()
+          .accounts({
+            dailyStats: await dailyStatsPda(),
+            order: orderPda,
+            escrow: escrowPda,
+            escrowTokenAccount: escrowWsolAccount,
+            sellerTokenAccount: null,
+            makerProfile: sellerProfilePda,
+            takerProfile: null,
+            seller: seller.publicKey,
+            mint: NATIVE_MINT,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([seller])
+          .rpc();
+
+        assert.fail("Debería haber fallado con la orden congelada");
+      } catch (error) {
+        assert.include(error.toString(), "OrderFrozen");
+      }
+
+      console.log("✅ Orden congelada, cancelación bloqueada");
+    });
+
+    it("Levantar la retención debe restaurar el estado previo", async () => {
+      await manageHold(program.methods.liftLegalHold());
+
+      const order = await program.account.order.fetch(orderPda);
+      assert.deepEqual(order.status, { open: {} });
+      assert.isNull(order.heldStatus);
+      assert.isNull(order.legalHoldCase);
+
+      console.log("✅ Retención levantada");
+    });
+
+    it("La transferencia judicial debe requerir aprobación del consejo y su timelock", async () => {
+      await manageHold(program.methods.placeLegalHold("CASE-2024-002"));
+
+      const proposalPda = await proposeAndApprove({
+        seizeFrozenFunds: { order: orderPda, destination: courtAccount.publicKey },
+      });
+      const [pendingChangePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("pending_change"), proposalPda.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .executeAdminAction()
+        .accounts({ ...executeAccounts(proposalPda), pendingChange: pendingChangePda })
+        .rpc();
+
+      const pendingChange = await program.account.pendingChange.fetch(pendingChangePda);
+      assert.equal(
+        pendingChange.action.seizeFrozenFunds.destination.toString(),
+        courtAccount.publicKey.toString()
+      );
+
+      try {
+        await transferFrozen(pendingChangePda, courtAccount.publicKey, seller);
+
+        assert.fail("Debería haber fallado sin ser la autoridad");
+      } catch (error) {
+        assert.include(error.toString(), "Unauthorized");
+      }
+
+      try {
+        await transferFrozen(pendingChangePda, seller.publicKey);
+
+        assert.fail("Debería haber fallado con un destino distinto al aprobado");
+      } catch (error) {
+        assert.include(error.toString(), "CourtTransferNotApproved");
+      }
+
+      try {
+        await transferFrozen(pendingChangePda, courtAccount.publicKey);
+
+        assert.fail("Debería haber fallado durante el timelock");
+      } catch (error) {
+        assert.include(error.toString(), "ChangeStillLocked");
+      }
+
+      const order = await program.account.order.fetch(orderPda);
+      assert.deepEqual(order.status, { frozen: {} });
+
+      console.log("✅ Transferencia judicial programada; bloqueada hasta cumplir la espera");
+    });
+  });
+
+  describe("25. Blocklist de Sanciones", () => {
+    const sanctionedUser = Keypair.generate();
+
+    const updateBlocklist = (method: any, addresses: PublicKey[], signer = authority.payer) =>
+      method
+        .accounts({
+          platform: platformPda,
+          moderator: signer.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(
+          addresses.map((address) => ({
+            pubkey: blockedAddressPda(address),
+            isWritable: true,
+            isSigner: false,
+          }))
+        )
+        .signers(signer === authority.payer ? [] : [signer])
+        .rpc();
+
+    before(async () => {
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(sanctionedUser.publicKey, 2 * LAMPORTS_PER_SOL)
+      );
+    });
+
+    it("Solo la autoridad o cumplimiento deben poder bloquear direcciones", async () => {
+      const addresses = [sanctionedUser.publicKey];
+
+      try {
+        await updateBlocklist(program.methods.blockAddresses(addresses, 201), addresses, seller);
+
+        assert.fail("Debería haber fallado sin rol de cumplimiento");
+      } catch (error) {
+        assert.include(error.toString(), "Unauthorized");
+      }
+
+      console.log("✅ Bloqueo rechazado para usuario sin rol");
+    });
+
+    it("Debe bloquear direcciones en lote e impedirles operar", async () => {
+      const addresses = [sanctionedUser.publicKey, buyer.publicKey];
+      await updateBlocklist(program.methods.blockAddresses(addresses, 201), addresses);
+
+      const entry = await program.account.blockedAddress.fetch(blockedAddressPda(buyer.publicKey));
+      assert.equal(entry.address.toString(), buyer.publicKey.toString());
+      assert.equal(entry.reasonCode, 201);
+      assert.equal(entry.blockedBy.toString(), authority.publicKey.toString());
+
+      const [sanctionedProfilePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("user_profile"), sanctionedUser.publicKey.toBuffer()],
+        program.programId
+      );
+
+      try {
+        await program.methods
+          .createUserProfile()
+          .accounts({
+            userProfile: sanctionedProfilePda,
+            user: sanctionedUser.publicKey,
+            kycRecord: kycRecordPda(sanctionedUser.publicKey),
+            userBlocked: blockedAddressPda(sanctionedUser.publicKey),
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([sanctionedUser])
+          .rpc();
+
+        assert.fail("Debería haber fallado con la dirección bloqueada");
+      } catch (error) {
+        assert.include(error.toString(), "AddressBlocked");
+      }
+
+      const orderId = "ORDER_BLOCKED";
+      const [orderPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("order"), Buffer.from(orderId)],
+        program.programId
+      );
+      const [escrowPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), orderPda.toBuffer()],
+        program.programId
+      );
+
+      try {
+        await program.methods
+          .createOrder(
+            orderId,
+            new BN(0.1 * LAMPORTS_PER_SOL),
+            new BN(400_000_000), // 400 MXN
+            { stp: {} },
+            "STP_REF_BLOCKED",
+            false,
+            { sell: {} },
+            null,
+            null
+          )
+          .accounts({
+            order: orderPda,
+            escrow: escrowPda,
+            makerProfile: buyerProfilePda,
+            maker: buyer.publicKey,
+            kycRecord: kycRecordPda(buyer.publicKey),
+            makerBlocked: blockedAddressPda(buyer.publicKey),
+            platform: platformPda,
+            supportedMint: solMintPda,
+            referenceRate: solRatePda,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([buyer])
+          .rpc();
+
+        assert.fail("Debería haber fallado con la dirección bloqueada");
+      } catch (error) {
+        assert.include(error.toString(), "AddressBlocked");
+      }
+
+      console.log("✅ Direcciones bloqueadas en lote");
+    });
+
+    it("Una dirección bloqueada no debe llenar órdenes, crear anuncios ni abrir trades", async () => {
+      // Orden de compra de otro usuario que el buyer bloqueado intenta llenar
+      const orderId = "ORDER_BLOCKED_FILL";
+      const [orderPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("order"), Buffer.from(orderId)],
+        program.programId
+      );
+      const [escrowPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), orderPda.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .createOrder(
+          orderId,
+          new BN(0.05 * LAMPORTS_PER_SOL),
+          new BN(200_000_000), // 200 MXN
+          { spei: {} },
+          "STP_REF_BLOCKED_FILL",
+          false,
+          { buy: {} },
+          null,
+          null
+        )
+        .accounts({
+          order: orderPda,
+          escrow: escrowPda,
+          makerProfile: sellerProfilePda,
+          maker: seller.publicKey,
+          kycRecord: kycRecordPda(seller.publicKey),
+          makerBlocked: blockedAddressPda(seller.publicKey),
+          platform: platformPda,
+          supportedMint: solMintPda,
+          referenceRate: solRatePda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([seller])
+        .rpc();
+
+      try {
+        await program.methods
+          .fillOrder()
+          .accounts({
+            order: orderPda,
+            escrow: escrowPda,
+            supportedMint: solMintPda,
+            sellerProfile: buyerProfilePda,
+            makerProfile: sellerProfilePda,
+            feeSchedule: feeSchedulePda,
+            seller: buyer.publicKey,
+            kycRecord: kycRecordPda(buyer.publicKey),
+            sellerBlocked: blockedAddressPda(buyer.publicKey),
+            makerBlocked: blockedAddressPda(seller.publicKey),
+            sellerTokenAccount: null,
+            escrowTokenAccount: await createWsolEscrowAccount(escrowPda),
+            mint: NATIVE_MINT,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            platform: platformPda,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([buyer])
+          .rpc();
+
+        assert.fail("Debería haber fallado al llenar con la dirección bloqueada");
+      } catch (error) {
+        assert.include(error.toString(), "AddressBlocked");
+      }
+
+      const blockedAdId = "AD_BLOCKED";
+      const [blockedAdPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("ad"), Buffer.from(blockedAdId)],
+        program.programId
+      );
+      const [blockedVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("ad_vault"), blockedAdPda.toBuffer()],
+        program.programId
+      );
+
+      try {
+        await program.methods
+          .createAd(
+            blockedAdId,
+            new BN(4_000_000_000),
+            new BN(100_000_000),
+            new BN(500_000_000),
+            [{ spei: {} }]
+          )
+          .accounts({
+            ad: blockedAdPda,
+            vault: blockedVaultPda,
+            vaultTokenAccount: await createWsolEscrowAccount(blockedVaultPda),
+            supportedMint: solMintPda,
+            merchantProfile: buyerProfilePda,
+            merchantBlocked: blockedAddressPda(buyer.publicKey),
+            kycRecord: kycRecordPda(buyer.publicKey),
+            platform: platformPda,
+            referenceRate: solRatePda,
+            merchant: buyer.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([buyer])
+          .rpc();
+
+        assert.fail("Debería haber fallado al crear el anuncio con la dirección bloqueada");
+      } catch (error) {
+        assert.include(error.toString(), "AddressBlocked");
+      }
+
+      // Anuncio del seller de la sección 9
+      const [adPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("ad"), Buffer.from("AD_001_SOL")],
+        program.programId
+      );
+      const ad = await program.account.ad.fetch(adPda);
+      const [tradePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("trade"), adPda.toBuffer(), ad.tradeCount.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+
+      try {
+        await program.methods
+          .openTrade(new BN(200_000_000), { spei: {} }) // 200 MXN
+          .accounts({
+            ad: adPda,
+            trade: tradePda,
+            supportedMint: solMintPda,
+            buyerProfile: buyerProfilePda,
+            merchantProfile: sellerProfilePda,
+            feeSchedule: feeSchedulePda,
+            platform: platformPda,
+            buyer: buyer.publicKey,
+            buyerBlocked: blockedAddressPda(buyer.publicKey),
+            kycRecord: kycRecordPda(buyer.publicKey),
+            merchantBlocked: blockedAddressPda(seller.publicKey),
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([buyer])
+          .rpc();
+
+        assert.fail("Debería haber fallado al abrir el trade con la dirección bloqueada");
+      } catch (error) {
+        assert.include(error.toString(), "AddressBlocked");
+      }
+
+      console.log("✅ Llenado, anuncios y trades bloqueados para la dirección");
+    });
+
+    it("Debe desbloquear direcciones en lote", async () => {
+      const addresses = [sanctionedUser.publicKey, buyer.publicKey];
+      await updateBlocklist(program.methods.unblockAddresses(addresses), addresses);
+
+      const entry = await provider.connection.getAccountInfo(blockedAddressPda(buyer.publicKey));
+      assert.isNull(entry);
+
+      console.log("✅ Direcciones desbloqueadas");
+    });
   });
 
   console.log("\n🎉 Todos los tests completados exitosamente!");